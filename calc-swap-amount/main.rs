@@ -0,0 +1,102 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+
+use cosmwasm_std::{Coin, MemoryStorage};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{Pool, TickLiquidityNet};
+use serde::Deserialize;
+
+use single_sided_lp_cl::cl_sim::{InitializedTick, PoolSnapshot};
+use single_sided_lp_cl::execute::{estimate_single_sided_join, SingleSidedJoinEstimate};
+
+/// A pool snapshot and the join to calculate the swap for
+#[derive(Deserialize)]
+struct CalcSwapAmountInput {
+    /// The pool as returned by the poolmanager `pool` query
+    pool: Pool,
+    /// The liquidity depths returned by the concentratedliquidity `liquidity-net-in-direction`
+    /// query, for both token0 and token1 in
+    liquidity_depths: Vec<TickLiquidityNet>,
+    token_provided: Coin,
+    lower_tick: i64,
+    upper_tick: i64,
+}
+
+// Reads the input from the file given as the first argument, or from stdin, and prints the
+// recommended swap and the position it is expected to result in as JSON.
+fn main() {
+    let input = match env::args().nth(1) {
+        Some(path) => fs::read(path).expect("failed to read input file"),
+        None => {
+            let mut buf = vec![];
+            io::stdin()
+                .read_to_end(&mut buf)
+                .expect("failed to read stdin");
+            buf
+        }
+    };
+    let estimate = calc_swap_amount(&input);
+
+    println!(
+        "{}",
+        serde_json_wasm::to_string(&estimate).expect("failed to serialize estimate")
+    );
+}
+
+// calc_swap_amount parses the input and estimates the join it describes
+fn calc_swap_amount(input: &[u8]) -> SingleSidedJoinEstimate {
+    let input: CalcSwapAmountInput =
+        serde_json_wasm::from_slice(input).expect("failed to parse input");
+
+    let ticks = input
+        .liquidity_depths
+        .iter()
+        .map(|depth| InitializedTick::from_liquidity_net(depth.tick_index, &depth.liquidity_net))
+        .collect::<Result<Vec<_>, _>>()
+        .expect("failed to parse liquidity depths");
+    let snapshot = PoolSnapshot::new(&input.pool, ticks).expect("failed to parse pool");
+
+    // The tick math caches its exponent table in storage, an in memory storage is enough here
+    let mut storage = MemoryStorage::new();
+    estimate_single_sided_join(
+        &mut storage,
+        &snapshot,
+        &input.token_provided,
+        input.lower_tick,
+        input.upper_tick,
+    )
+    .expect("failed to estimate join")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use cosmwasm_std::{Decimal256, Uint128};
+
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let input: CalcSwapAmountInput =
+            serde_json_wasm::from_slice(include_bytes!("example.json")).unwrap();
+        let estimate = calc_swap_amount(include_bytes!("example.json"));
+
+        // The range only needs about 10 token1 for the 1_000_000 token0, the swap output is rounded
+        // down so token1 limits the position and most of the dust is token0
+        assert_eq!(
+            estimate,
+            SingleSidedJoinEstimate {
+                token_in: Coin::new(10, input.pool.token0.clone()),
+                expected_token_out: Coin::new(9, input.pool.token1.clone()),
+                position_amount0: Uint128::new(909086),
+                position_amount1: Uint128::new(9),
+                liquidity: Decimal256::from_str("909085.398518435391942419").unwrap(),
+                dust: vec![
+                    Coin::new(90904, input.pool.token0),
+                    Coin::new(0, input.pool.token1),
+                ],
+            }
+        );
+    }
+}
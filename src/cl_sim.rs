@@ -0,0 +1,966 @@
+use std::str::FromStr;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, Decimal256, QuerierWrapper, Storage, Uint128, Uint256, Uint512};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    ConcentratedliquidityQuerier, Pool,
+};
+
+use crate::tick::{
+    calculate_sqrt_price_to_tick, tick_to_sqrt_price, MAX_TICK, MIN_INITIALIZED_TICK,
+};
+use crate::ContractError;
+
+// The methods in this file mirror the swap strategies of the chain's concentrated liquidity module
+// (zeroForOne / oneForZero), including where the chain rounds up in the pool's favor. The chain
+// works with 36 decimal places for sqrt prices, we work with the 18 of Decimal256.
+
+/// An initialized tick of a pool and the liquidity that is added when crossing it left to right.
+#[cw_serde]
+pub struct InitializedTick {
+    pub tick_index: i64,
+    /// Absolute value of the tick's liquidity net
+    pub liquidity_net: Decimal256,
+    /// Whether crossing the tick left to right removes liquidity
+    pub liquidity_net_negative: bool,
+}
+
+impl InitializedTick {
+    pub fn from_liquidity_net(tick_index: i64, liquidity_net: &str) -> Result<Self, ContractError> {
+        let (liquidity_net_negative, liquidity_net) = match liquidity_net.strip_prefix('-') {
+            Some(abs) => (true, parse_chain_dec(abs)?),
+            None => (false, parse_chain_dec(liquidity_net)?),
+        };
+        Ok(InitializedTick {
+            tick_index,
+            liquidity_net,
+            liquidity_net_negative,
+        })
+    }
+}
+
+/// Everything needed to simulate a swap against a concentrated liquidity pool.
+#[cw_serde]
+pub struct PoolSnapshot {
+    pub token0: String,
+    pub token1: String,
+    pub current_sqrt_price: Decimal256,
+    pub current_tick: i64,
+    pub current_tick_liquidity: Decimal256,
+    pub spread_factor: Decimal256,
+    /// Initialized ticks of the pool, sorted by tick index
+    pub ticks: Vec<InitializedTick>,
+}
+
+impl PoolSnapshot {
+    pub fn new(pool: &Pool, mut ticks: Vec<InitializedTick>) -> Result<Self, ContractError> {
+        ticks.sort_by_key(|tick| tick.tick_index);
+        ticks.dedup_by_key(|tick| tick.tick_index);
+        Ok(PoolSnapshot {
+            token0: pool.token0.clone(),
+            token1: pool.token1.clone(),
+            current_sqrt_price: parse_chain_dec(&pool.current_sqrt_price)?,
+            current_tick: pool.current_tick,
+            current_tick_liquidity: parse_chain_dec(&pool.current_tick_liquidity)?,
+            spread_factor: parse_chain_dec(&pool.spread_factor)?,
+            ticks,
+        })
+    }
+
+    // Returns the initialized ticks a swap in the given direction will cross, in crossing order.
+    // Swapping token0 in moves the price down, so these are the ticks at or below the current tick.
+    fn ticks_in_direction(&self, zero_for_one: bool) -> Vec<&InitializedTick> {
+        if zero_for_one {
+            self.ticks
+                .iter()
+                .rev()
+                .filter(|tick| tick.tick_index <= self.current_tick)
+                .collect()
+        } else {
+            self.ticks
+                .iter()
+                .filter(|tick| tick.tick_index > self.current_tick)
+                .collect()
+        }
+    }
+}
+
+/// How many ticks away from the current tick a snapshot covers in each direction. Around a price of 1
+/// this is a move of the price by a factor of 10 either way.
+pub const SNAPSHOT_TICK_RANGE: i64 = 9_000_000;
+
+/// Queries the pool's initialized ticks in both directions, up to `SNAPSHOT_TICK_RANGE` ticks away from
+/// the current tick, and builds a snapshot out of them. An uninitialized tick is added at each bound, so a
+/// simulated swap that would move the price past it fails with `NotEnoughLiquidityForSwap` instead of
+/// missing the liquidity beyond.
+pub fn query_pool_snapshot(
+    querier: &QuerierWrapper,
+    pool: &Pool,
+) -> Result<PoolSnapshot, ContractError> {
+    let cl_querier = ConcentratedliquidityQuerier::new(querier);
+
+    let lower_bound = (pool.current_tick - SNAPSHOT_TICK_RANGE).max(MIN_INITIALIZED_TICK);
+    let upper_bound = (pool.current_tick + SNAPSHOT_TICK_RANGE).min(MAX_TICK as i64);
+
+    let mut ticks = vec![];
+    for (token_in, bound_tick) in [(&pool.token0, lower_bound), (&pool.token1, upper_bound)] {
+        let liquidity_net = cl_querier.liquidity_net_in_direction(
+            pool.id,
+            token_in.clone(),
+            0,
+            true,
+            bound_tick,
+            false,
+        )?;
+        for depth in liquidity_net.liquidity_depths {
+            ticks.push(InitializedTick::from_liquidity_net(
+                depth.tick_index,
+                &depth.liquidity_net,
+            )?);
+        }
+    }
+    // Pushed last, the bounds are dropped by the dedup if they are initialized ticks
+    for bound_tick in [lower_bound, upper_bound] {
+        ticks.push(InitializedTick {
+            tick_index: bound_tick,
+            liquidity_net: Decimal256::zero(),
+            liquidity_net_negative: false,
+        });
+    }
+
+    PoolSnapshot::new(pool, ticks)
+}
+
+/// The outcome of a simulated swap.
+#[cw_serde]
+pub struct SwapSimulation {
+    pub token_in: Coin,
+    pub token_out: Coin,
+    /// Spread rewards charged on the token in
+    pub spread_rewards: Decimal256,
+    pub end_tick: i64,
+    pub end_sqrt_price: Decimal256,
+    pub end_liquidity: Decimal256,
+}
+
+struct SwapStep {
+    sqrt_price_next: Decimal256,
+    amount_in: Decimal256,
+    amount_out: Decimal256,
+    spread_reward_charge: Decimal256,
+}
+
+// Mirrors the state the chain keeps while iterating over swap steps
+struct SwapState {
+    sqrt_price: Decimal256,
+    tick: i64,
+    liquidity: Decimal256,
+}
+
+impl SwapState {
+    fn new(snapshot: &PoolSnapshot) -> Self {
+        SwapState {
+            sqrt_price: snapshot.current_sqrt_price,
+            tick: snapshot.current_tick,
+            liquidity: snapshot.current_tick_liquidity,
+        }
+    }
+
+    // Moves the state to the end of a swap step, crossing the target tick if it was reached
+    fn apply_step(
+        &mut self,
+        storage: &mut dyn Storage,
+        zero_for_one: bool,
+        target: &InitializedTick,
+        sqrt_price_target: Decimal256,
+        sqrt_price_next: Decimal256,
+    ) -> Result<(), ContractError> {
+        if sqrt_price_next == sqrt_price_target {
+            // Crossing a tick going left flips the sign of its liquidity net
+            let add_liquidity = target.liquidity_net_negative == zero_for_one;
+            self.liquidity = if add_liquidity {
+                self.liquidity.checked_add(target.liquidity_net)?
+            } else {
+                self.liquidity.checked_sub(target.liquidity_net)?
+            };
+            // When crossing a tick going left, the current tick becomes the one right below it
+            self.tick = if zero_for_one {
+                target.tick_index - 1
+            } else {
+                target.tick_index
+            };
+        } else if sqrt_price_next != self.sqrt_price {
+            self.tick = calculate_sqrt_price_to_tick(storage, sqrt_price_next)?;
+        }
+        self.sqrt_price = sqrt_price_next;
+        Ok(())
+    }
+}
+
+/// Simulates a `MsgSwapExactAmountIn` against the pool snapshot.
+pub fn simulate_swap_exact_amount_in(
+    storage: &mut dyn Storage,
+    snapshot: &PoolSnapshot,
+    token_in: &Coin,
+) -> Result<SwapSimulation, ContractError> {
+    let zero_for_one = swap_direction(snapshot, &token_in.denom, true)?;
+
+    let mut state = SwapState::new(snapshot);
+    let mut amount_remaining = dec_from_uint(token_in.amount);
+    let mut amount_calculated = Decimal256::zero();
+    let mut spread_rewards = Decimal256::zero();
+
+    let mut next_ticks = snapshot.ticks_in_direction(zero_for_one).into_iter();
+    while !amount_remaining.is_zero() {
+        let target = next_ticks
+            .next()
+            .ok_or(ContractError::NotEnoughLiquidityForSwap {})?;
+        let sqrt_price_target = tick_to_sqrt_price(target.tick_index)?;
+
+        let step = compute_swap_step_out_given_in(
+            zero_for_one,
+            state.sqrt_price,
+            sqrt_price_target,
+            state.liquidity,
+            amount_remaining,
+            snapshot.spread_factor,
+        )?;
+
+        // A partial step's amount in is rounded up and may exceed what remains by the last decimal place,
+        // the chain's loop then ends the swap just like on zero
+        amount_remaining =
+            amount_remaining.saturating_sub(step.amount_in.checked_add(step.spread_reward_charge)?);
+        amount_calculated = amount_calculated.checked_add(step.amount_out)?;
+        spread_rewards = spread_rewards.checked_add(step.spread_reward_charge)?;
+
+        state.apply_step(
+            storage,
+            zero_for_one,
+            target,
+            sqrt_price_target,
+            step.sqrt_price_next,
+        )?;
+    }
+
+    let token_out_denom = other_denom(snapshot, zero_for_one);
+    Ok(SwapSimulation {
+        token_in: Coin {
+            denom: token_in.denom.clone(),
+            amount: token_in
+                .amount
+                .checked_sub(uint_from_dec_floor(amount_remaining)?)?,
+        },
+        token_out: Coin {
+            denom: token_out_denom,
+            amount: uint_from_dec_floor(amount_calculated)?,
+        },
+        spread_rewards,
+        end_tick: state.tick,
+        end_sqrt_price: state.sqrt_price,
+        end_liquidity: state.liquidity,
+    })
+}
+
+/// Simulates a `MsgSwapExactAmountOut` against the pool snapshot.
+pub fn simulate_swap_exact_amount_out(
+    storage: &mut dyn Storage,
+    snapshot: &PoolSnapshot,
+    token_out: &Coin,
+) -> Result<SwapSimulation, ContractError> {
+    let zero_for_one = swap_direction(snapshot, &token_out.denom, false)?;
+
+    let mut state = SwapState::new(snapshot);
+    let mut amount_remaining = dec_from_uint(token_out.amount);
+    let mut amount_calculated = Decimal256::zero();
+    let mut spread_rewards = Decimal256::zero();
+
+    let mut next_ticks = snapshot.ticks_in_direction(zero_for_one).into_iter();
+    while !amount_remaining.is_zero() {
+        let target = next_ticks
+            .next()
+            .ok_or(ContractError::NotEnoughLiquidityForSwap {})?;
+        let sqrt_price_target = tick_to_sqrt_price(target.tick_index)?;
+
+        let step = compute_swap_step_in_given_out(
+            zero_for_one,
+            state.sqrt_price,
+            sqrt_price_target,
+            state.liquidity,
+            amount_remaining,
+            snapshot.spread_factor,
+        )?;
+
+        // Likewise a partial step's amount out may exceed what remains by the last decimal place
+        amount_remaining = amount_remaining.saturating_sub(step.amount_out);
+        amount_calculated = amount_calculated
+            .checked_add(step.amount_in.checked_add(step.spread_reward_charge)?)?;
+        spread_rewards = spread_rewards.checked_add(step.spread_reward_charge)?;
+
+        state.apply_step(
+            storage,
+            zero_for_one,
+            target,
+            sqrt_price_target,
+            step.sqrt_price_next,
+        )?;
+    }
+
+    let token_in_denom = if zero_for_one {
+        snapshot.token0.clone()
+    } else {
+        snapshot.token1.clone()
+    };
+    Ok(SwapSimulation {
+        // The token in is rounded up in the pool's favor
+        token_in: Coin {
+            denom: token_in_denom,
+            amount: Uint128::try_from(amount_calculated.to_uint_ceil())?,
+        },
+        token_out: token_out.clone(),
+        spread_rewards,
+        end_tick: state.tick,
+        end_sqrt_price: state.sqrt_price,
+        end_liquidity: state.liquidity,
+    })
+}
+
+/// The outcome of a simulated position creation.
+#[cw_serde]
+pub struct PositionSimulation {
+    pub liquidity: Decimal256,
+    /// Amount of token0 the position takes
+    pub amount0: Uint128,
+    /// Amount of token1 the position takes
+    pub amount1: Uint128,
+}
+
+/// Simulates a `MsgCreatePosition`: the liquidity the provided amounts give in the tick range at the given
+/// sqrt price, and the amounts of each token the chain takes for that liquidity.
+pub fn simulate_create_position(
+    sqrt_price: Decimal256,
+    lower_tick: i64,
+    upper_tick: i64,
+    amount0: Uint128,
+    amount1: Uint128,
+) -> Result<PositionSimulation, ContractError> {
+    let sqrt_price_lower = tick_to_sqrt_price(lower_tick)?;
+    let sqrt_price_upper = tick_to_sqrt_price(upper_tick)?;
+
+    let liquidity = get_liquidity_from_amounts(
+        sqrt_price,
+        sqrt_price_lower,
+        sqrt_price_upper,
+        dec_from_uint(amount0),
+        dec_from_uint(amount1),
+    )?;
+
+    // The chain rounds the amounts up when adding liquidity
+    let (amount0_used, amount1_used) = if sqrt_price <= sqrt_price_lower {
+        (
+            calc_amount_zero_delta(liquidity, sqrt_price_lower, sqrt_price_upper, true)?,
+            Decimal256::zero(),
+        )
+    } else if sqrt_price < sqrt_price_upper {
+        (
+            calc_amount_zero_delta(liquidity, sqrt_price, sqrt_price_upper, true)?,
+            calc_amount_one_delta(liquidity, sqrt_price, sqrt_price_lower, true)?,
+        )
+    } else {
+        (
+            Decimal256::zero(),
+            calc_amount_one_delta(liquidity, sqrt_price_lower, sqrt_price_upper, true)?,
+        )
+    };
+
+    Ok(PositionSimulation {
+        liquidity,
+        amount0: uint_from_dec_floor(amount0_used)?.min(amount0),
+        amount1: uint_from_dec_floor(amount1_used)?.min(amount1),
+    })
+}
+
+/// The liquidity the given amounts provide in the range between the two sqrt prices, mirroring GetLiquidityFromAmounts.
+pub fn get_liquidity_from_amounts(
+    sqrt_price: Decimal256,
+    mut sqrt_price_a: Decimal256,
+    mut sqrt_price_b: Decimal256,
+    amount0: Decimal256,
+    amount1: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    if sqrt_price_a > sqrt_price_b {
+        std::mem::swap(&mut sqrt_price_a, &mut sqrt_price_b);
+    }
+    if sqrt_price <= sqrt_price_a {
+        // The range is above the current price, only token0 is needed
+        liquidity0(amount0, sqrt_price_a, sqrt_price_b)
+    } else if sqrt_price < sqrt_price_b {
+        let liquidity_from_amount0 = liquidity0(amount0, sqrt_price, sqrt_price_b)?;
+        let liquidity_from_amount1 = liquidity1(amount1, sqrt_price, sqrt_price_a)?;
+        Ok(liquidity_from_amount0.min(liquidity_from_amount1))
+    } else {
+        // The range is below the current price, only token1 is needed
+        liquidity1(amount1, sqrt_price_b, sqrt_price_a)
+    }
+}
+
+// liquidity0 = amount0 * (sqrtPriceA * sqrtPriceB) / (sqrtPriceB - sqrtPriceA)
+pub fn liquidity0(
+    amount: Decimal256,
+    mut sqrt_price_a: Decimal256,
+    mut sqrt_price_b: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    if sqrt_price_a > sqrt_price_b {
+        std::mem::swap(&mut sqrt_price_a, &mut sqrt_price_b);
+    }
+    let product = sqrt_price_a.checked_mul(sqrt_price_b)?;
+    let diff = sqrt_price_b.checked_sub(sqrt_price_a)?;
+    Ok(amount.checked_mul(product)?.checked_div(diff)?)
+}
+
+// liquidity1 = amount1 / (sqrtPriceB - sqrtPriceA)
+pub fn liquidity1(
+    amount: Decimal256,
+    mut sqrt_price_a: Decimal256,
+    mut sqrt_price_b: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    if sqrt_price_a > sqrt_price_b {
+        std::mem::swap(&mut sqrt_price_a, &mut sqrt_price_b);
+    }
+    let diff = sqrt_price_b.checked_sub(sqrt_price_a)?;
+    Ok(amount.checked_div(diff)?)
+}
+
+// Returns whether the swap moves token0 into the pool. `is_token_in` tells whether the given denom is
+// the token going into or out of the pool.
+fn swap_direction(
+    snapshot: &PoolSnapshot,
+    denom: &str,
+    is_token_in: bool,
+) -> Result<bool, ContractError> {
+    if denom == snapshot.token0 {
+        Ok(is_token_in)
+    } else if denom == snapshot.token1 {
+        Ok(!is_token_in)
+    } else {
+        Err(ContractError::DenomNotInPool {
+            provided_denom: denom.to_string(),
+        })
+    }
+}
+
+fn other_denom(snapshot: &PoolSnapshot, zero_for_one: bool) -> String {
+    if zero_for_one {
+        snapshot.token1.clone()
+    } else {
+        snapshot.token0.clone()
+    }
+}
+
+// compute_swap_step_out_given_in mirrors ComputeSwapWithStepsOutGivenIn. The spread factor is charged on
+// the amount in before the price moves, so only the remaining amount less spread rewards moves the price.
+fn compute_swap_step_out_given_in(
+    zero_for_one: bool,
+    sqrt_price_current: Decimal256,
+    sqrt_price_target: Decimal256,
+    liquidity: Decimal256,
+    amount_remaining_in: Decimal256,
+    spread_factor: Decimal256,
+) -> Result<SwapStep, ContractError> {
+    // Estimate the amount of token in needed until the target sqrt price is reached
+    let amount_in_to_target = if zero_for_one {
+        calc_amount_zero_delta(liquidity, sqrt_price_target, sqrt_price_current, true)?
+    } else {
+        calc_amount_one_delta(liquidity, sqrt_price_target, sqrt_price_current, true)?
+    };
+
+    let amount_remaining_in_less_spread_reward =
+        amount_remaining_in.checked_mul(Decimal256::one().checked_sub(spread_factor)?)?;
+
+    let sqrt_price_next = if amount_remaining_in_less_spread_reward >= amount_in_to_target {
+        sqrt_price_target
+    } else if zero_for_one {
+        get_next_sqrt_price_from_amount0_in_round_up(
+            sqrt_price_current,
+            liquidity,
+            amount_remaining_in_less_spread_reward,
+        )?
+    } else {
+        get_next_sqrt_price_from_amount1_in_round_down(
+            sqrt_price_current,
+            liquidity,
+            amount_remaining_in_less_spread_reward,
+        )?
+    };
+    let has_reached_target = sqrt_price_next == sqrt_price_target;
+
+    // If the target was not reached, recalculate how much of the amount remaining was needed
+    let amount_in = if has_reached_target {
+        amount_in_to_target
+    } else if zero_for_one {
+        calc_amount_zero_delta(liquidity, sqrt_price_next, sqrt_price_current, true)?
+    } else {
+        calc_amount_one_delta(liquidity, sqrt_price_next, sqrt_price_current, true)?
+    };
+
+    // The amount out is rounded down in the pool's favor
+    let amount_out = if zero_for_one {
+        calc_amount_one_delta(liquidity, sqrt_price_next, sqrt_price_current, false)?
+    } else {
+        calc_amount_zero_delta(liquidity, sqrt_price_next, sqrt_price_current, false)?
+    };
+
+    let spread_reward_charge = if spread_factor.is_zero() {
+        Decimal256::zero()
+    } else if has_reached_target {
+        compute_spread_reward_charge_from_amount_in(amount_in, spread_factor)?
+    } else {
+        // The step used up the amount remaining, so everything not swapped is the spread reward
+        amount_remaining_in.checked_sub(amount_in)?
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        spread_reward_charge,
+    })
+}
+
+// compute_swap_step_in_given_out mirrors ComputeSwapWithStepsInGivenOut
+fn compute_swap_step_in_given_out(
+    zero_for_one: bool,
+    sqrt_price_current: Decimal256,
+    sqrt_price_target: Decimal256,
+    liquidity: Decimal256,
+    amount_remaining_out: Decimal256,
+    spread_factor: Decimal256,
+) -> Result<SwapStep, ContractError> {
+    // Estimate the amount of token out available until the target sqrt price is reached
+    let amount_out_to_target = if zero_for_one {
+        calc_amount_one_delta(liquidity, sqrt_price_target, sqrt_price_current, false)?
+    } else {
+        calc_amount_zero_delta(liquidity, sqrt_price_target, sqrt_price_current, false)?
+    };
+
+    let sqrt_price_next = if amount_remaining_out >= amount_out_to_target {
+        sqrt_price_target
+    } else if zero_for_one {
+        get_next_sqrt_price_from_amount1_out_round_down(
+            sqrt_price_current,
+            liquidity,
+            amount_remaining_out,
+        )?
+    } else {
+        get_next_sqrt_price_from_amount0_out_round_up(
+            sqrt_price_current,
+            liquidity,
+            amount_remaining_out,
+        )?
+    };
+    let has_reached_target = sqrt_price_next == sqrt_price_target;
+
+    let amount_out = if has_reached_target {
+        amount_out_to_target
+    } else if zero_for_one {
+        calc_amount_one_delta(liquidity, sqrt_price_next, sqrt_price_current, false)?
+    } else {
+        calc_amount_zero_delta(liquidity, sqrt_price_next, sqrt_price_current, false)?
+    };
+
+    // The amount in is rounded up in the pool's favor
+    let amount_in = if zero_for_one {
+        calc_amount_zero_delta(liquidity, sqrt_price_next, sqrt_price_current, true)?
+    } else {
+        calc_amount_one_delta(liquidity, sqrt_price_next, sqrt_price_current, true)?
+    };
+
+    let spread_reward_charge = if spread_factor.is_zero() {
+        Decimal256::zero()
+    } else {
+        compute_spread_reward_charge_from_amount_in(amount_in, spread_factor)?
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        spread_reward_charge,
+    })
+}
+
+// The spread reward on an amount that was swapped in full: amount_in * spread_factor / (1 - spread_factor),
+// rounded up.
+fn compute_spread_reward_charge_from_amount_in(
+    amount_in: Decimal256,
+    spread_factor: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    quo_round_up(
+        amount_in.checked_mul(spread_factor)?,
+        Decimal256::one().checked_sub(spread_factor)?,
+    )
+}
+
+/// Amount of token0 between two sqrt prices for the given liquidity: liquidity * (sqrtPriceA - sqrtPriceB) / (sqrtPriceA * sqrtPriceB)
+pub fn calc_amount_zero_delta(
+    liquidity: Decimal256,
+    mut sqrt_price_a: Decimal256,
+    mut sqrt_price_b: Decimal256,
+    should_round_up: bool,
+) -> Result<Decimal256, ContractError> {
+    if sqrt_price_b > sqrt_price_a {
+        std::mem::swap(&mut sqrt_price_a, &mut sqrt_price_b);
+    }
+    let diff = sqrt_price_a.checked_sub(sqrt_price_b)?;
+    if should_round_up {
+        Ok(quo_round_up(
+            quo_round_up(mul_round_up(liquidity, diff)?, sqrt_price_a)?,
+            sqrt_price_b,
+        )?
+        .ceil())
+    } else {
+        Ok(liquidity
+            .checked_mul(diff)?
+            .checked_div(sqrt_price_a)?
+            .checked_div(sqrt_price_b)?)
+    }
+}
+
+/// Amount of token1 between two sqrt prices for the given liquidity: liquidity * (sqrtPriceA - sqrtPriceB)
+pub fn calc_amount_one_delta(
+    liquidity: Decimal256,
+    mut sqrt_price_a: Decimal256,
+    mut sqrt_price_b: Decimal256,
+    should_round_up: bool,
+) -> Result<Decimal256, ContractError> {
+    if sqrt_price_b > sqrt_price_a {
+        std::mem::swap(&mut sqrt_price_a, &mut sqrt_price_b);
+    }
+    let diff = sqrt_price_a.checked_sub(sqrt_price_b)?;
+    if should_round_up {
+        Ok(mul_round_up(liquidity, diff)?.ceil())
+    } else {
+        Ok(liquidity.checked_mul(diff)?)
+    }
+}
+
+/// The sqrt price after swapping the given amount of token0 in: liquidity * sqrtPriceCurrent / (liquidity + amount * sqrtPriceCurrent)
+pub fn get_next_sqrt_price_from_amount0_in_round_up(
+    sqrt_price_current: Decimal256,
+    liquidity: Decimal256,
+    amount_zero_remaining_in: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    if amount_zero_remaining_in.is_zero() {
+        return Ok(sqrt_price_current);
+    }
+    let product = amount_zero_remaining_in.checked_mul(sqrt_price_current)?;
+    let denominator = liquidity.checked_add(product)?;
+    quo_round_up(liquidity.checked_mul(sqrt_price_current)?, denominator)
+}
+
+/// The sqrt price after swapping the given amount of token1 in: sqrtPriceCurrent + amount / liquidity
+pub fn get_next_sqrt_price_from_amount1_in_round_down(
+    sqrt_price_current: Decimal256,
+    liquidity: Decimal256,
+    amount_one_remaining_in: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    Ok(sqrt_price_current.checked_add(amount_one_remaining_in.checked_div(liquidity)?)?)
+}
+
+/// The sqrt price after swapping the given amount of token0 out: liquidity * sqrtPriceCurrent / (liquidity - amount * sqrtPriceCurrent)
+pub fn get_next_sqrt_price_from_amount0_out_round_up(
+    sqrt_price_current: Decimal256,
+    liquidity: Decimal256,
+    amount_zero_remaining_out: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    if amount_zero_remaining_out.is_zero() {
+        return Ok(sqrt_price_current);
+    }
+    let product = amount_zero_remaining_out.checked_mul(sqrt_price_current)?;
+    let denominator = liquidity
+        .checked_sub(product)
+        .map_err(|_| ContractError::NotEnoughLiquidityForSwap {})?;
+    if denominator.is_zero() {
+        return Err(ContractError::NotEnoughLiquidityForSwap {});
+    }
+    quo_round_up(liquidity.checked_mul(sqrt_price_current)?, denominator)
+}
+
+/// The sqrt price after swapping the given amount of token1 out: sqrtPriceCurrent - amount / liquidity
+pub fn get_next_sqrt_price_from_amount1_out_round_down(
+    sqrt_price_current: Decimal256,
+    liquidity: Decimal256,
+    amount_one_remaining_out: Decimal256,
+) -> Result<Decimal256, ContractError> {
+    sqrt_price_current
+        .checked_sub(quo_round_up(amount_one_remaining_out, liquidity)?)
+        .map_err(|_| ContractError::NotEnoughLiquidityForSwap {})
+}
+
+/// Parses a decimal string returned by the chain, which may carry more decimal places than Decimal256
+/// supports (e.g. sqrt prices have 36), truncating the extra decimal places.
+pub fn parse_chain_dec(value: &str) -> Result<Decimal256, ContractError> {
+    let truncated = match value.split_once('.') {
+        Some((integer_part, fractional_part)) if fractional_part.len() > 18 => {
+            format!("{}.{}", integer_part, &fractional_part[0..18])
+        }
+        _ => value.to_string(),
+    };
+    Ok(Decimal256::from_str(&truncated)?)
+}
+
+pub fn dec_from_uint(amount: Uint128) -> Decimal256 {
+    Decimal256::from_ratio(amount, 1u128)
+}
+
+pub fn uint_from_dec_floor(amount: Decimal256) -> Result<Uint128, ContractError> {
+    Ok(Uint128::try_from(amount.to_uint_floor())?)
+}
+
+// a * b rounded up at the last decimal place
+fn mul_round_up(a: Decimal256, b: Decimal256) -> Result<Decimal256, ContractError> {
+    let fractional = Uint512::from(Decimal256::one().atomics());
+    let product = Uint512::from(a.atomics()).checked_mul(Uint512::from(b.atomics()))?;
+    let rounded = product
+        .checked_add(fractional.checked_sub(Uint512::one())?)?
+        .checked_div(fractional)?;
+    Ok(Decimal256::new(Uint256::try_from(rounded)?))
+}
+
+// a / b rounded up at the last decimal place
+fn quo_round_up(a: Decimal256, b: Decimal256) -> Result<Decimal256, ContractError> {
+    let fractional = Uint512::from(Decimal256::one().atomics());
+    let divisor = Uint512::from(b.atomics());
+    let numerator = Uint512::from(a.atomics()).checked_mul(fractional)?;
+    let rounded = numerator
+        .checked_add(divisor.checked_sub(Uint512::one())?)?
+        .checked_div(divisor)?;
+    Ok(Decimal256::new(Uint256::try_from(rounded)?))
+}
+
+// full_range_snapshot is a pool of token0 and token1 at a price of 1 with 1000000 liquidity over the full range
+#[cfg(test)]
+pub(crate) fn full_range_snapshot(spread_factor: &str) -> PoolSnapshot {
+    PoolSnapshot {
+        token0: "token0".to_string(),
+        token1: "token1".to_string(),
+        current_sqrt_price: Decimal256::one(),
+        current_tick: 0,
+        current_tick_liquidity: Decimal256::from_str("1000000").unwrap(),
+        spread_factor: Decimal256::from_str(spread_factor).unwrap(),
+        ticks: vec![
+            InitializedTick::from_liquidity_net(MIN_INITIALIZED_TICK, "1000000").unwrap(),
+            InitializedTick::from_liquidity_net(MAX_TICK as i64, "-1000000").unwrap(),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_dependencies;
+
+    use super::*;
+    use crate::tick::{MAX_TICK, MIN_INITIALIZED_TICK};
+
+    fn snapshot_with_ticks(spread_factor: &str, ticks: Vec<(i64, &str)>) -> PoolSnapshot {
+        PoolSnapshot {
+            token0: "token0".to_string(),
+            token1: "token1".to_string(),
+            current_sqrt_price: Decimal256::one(),
+            current_tick: 0,
+            current_tick_liquidity: Decimal256::from_str("1000000").unwrap(),
+            spread_factor: Decimal256::from_str(spread_factor).unwrap(),
+            ticks: ticks
+                .into_iter()
+                .map(|(tick_index, liquidity_net)| {
+                    InitializedTick::from_liquidity_net(tick_index, liquidity_net).unwrap()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_chain_dec() {
+        assert_eq!(
+            parse_chain_dec("1.000000000000000000123456789012345678").unwrap(),
+            Decimal256::one()
+        );
+        assert_eq!(
+            parse_chain_dec("0.5").unwrap(),
+            Decimal256::from_str("0.5").unwrap()
+        );
+        assert_eq!(
+            parse_chain_dec("42").unwrap(),
+            Decimal256::from_str("42").unwrap()
+        );
+
+        let tick = InitializedTick::from_liquidity_net(100, "-1500.5").unwrap();
+        assert!(tick.liquidity_net_negative);
+        assert_eq!(tick.liquidity_net, Decimal256::from_str("1500.5").unwrap());
+    }
+
+    #[test]
+    fn test_swap_exact_amount_in_within_tick() {
+        let mut deps = mock_dependencies();
+
+        // token1 in: sqrt price moves up by 1000 / 1000000 to 1.001, the amount out is
+        // 1000000 * 0.001 / 1.001 rounded down
+        let snapshot = full_range_snapshot("0");
+        let res = simulate_swap_exact_amount_in(
+            deps.as_mut().storage,
+            &snapshot,
+            &Coin::new(1000, "token1"),
+        )
+        .unwrap();
+        assert_eq!(res.token_in, Coin::new(1000, "token1"));
+        assert_eq!(res.token_out, Coin::new(999, "token0"));
+        assert_eq!(res.spread_rewards, Decimal256::zero());
+        assert_eq!(res.end_sqrt_price, Decimal256::from_str("1.001").unwrap());
+        assert_eq!(res.end_tick, 2001);
+
+        // with a 1% spread factor only 990 move the price, the rest is charged as spread rewards
+        let snapshot = full_range_snapshot("0.01");
+        let res = simulate_swap_exact_amount_in(
+            deps.as_mut().storage,
+            &snapshot,
+            &Coin::new(1000, "token1"),
+        )
+        .unwrap();
+        assert_eq!(res.token_in, Coin::new(1000, "token1"));
+        assert_eq!(res.token_out, Coin::new(989, "token0"));
+        assert_eq!(res.spread_rewards, Decimal256::from_str("10").unwrap());
+        assert_eq!(res.end_sqrt_price, Decimal256::from_str("1.00099").unwrap());
+        assert_eq!(res.end_tick, 1980);
+    }
+
+    #[test]
+    fn test_swap_exact_amount_in_crosses_ticks() {
+        let mut deps = mock_dependencies();
+
+        // An extra position of 1000000 liquidity between ticks -100000 and 100000 is in range, swapping
+        // token0 in moves the price below tick -100000 where only the full range liquidity is left.
+        let mut snapshot = snapshot_with_ticks(
+            "0",
+            vec![
+                (MIN_INITIALIZED_TICK, "1000000"),
+                (-100000, "1000000"),
+                (100000, "-1000000"),
+                (MAX_TICK as i64, "-1000000"),
+            ],
+        );
+        snapshot.current_tick_liquidity = Decimal256::from_str("2000000").unwrap();
+
+        let res = simulate_swap_exact_amount_in(
+            deps.as_mut().storage,
+            &snapshot,
+            &Coin::new(20000, "token0"),
+        )
+        .unwrap();
+        assert_eq!(res.token_in, Coin::new(20000, "token0"));
+        assert_eq!(res.token_out, Coin::new(19753, "token1"));
+        assert_eq!(res.end_tick, -292653);
+        assert_eq!(res.end_liquidity, Decimal256::from_str("1000000").unwrap());
+
+        // with the same liquidity over the full range the swap stays in the first tick range
+        let mut deep_snapshot = snapshot_with_ticks(
+            "0",
+            vec![
+                (MIN_INITIALIZED_TICK, "2000000"),
+                (MAX_TICK as i64, "-2000000"),
+            ],
+        );
+        deep_snapshot.current_tick_liquidity = Decimal256::from_str("2000000").unwrap();
+
+        let res = simulate_swap_exact_amount_in(
+            deps.as_mut().storage,
+            &deep_snapshot,
+            &Coin::new(20000, "token0"),
+        )
+        .unwrap();
+        assert_eq!(res.token_out, Coin::new(19801, "token1"));
+        assert_eq!(res.end_tick, -197040);
+        assert_eq!(res.end_liquidity, Decimal256::from_str("2000000").unwrap());
+    }
+
+    #[test]
+    fn test_swap_exact_amount_out_matches_amount_in() {
+        let mut deps = mock_dependencies();
+        let snapshot = full_range_snapshot("0.01");
+
+        let res = simulate_swap_exact_amount_out(
+            deps.as_mut().storage,
+            &snapshot,
+            &Coin::new(989, "token0"),
+        )
+        .unwrap();
+        assert_eq!(res.token_out, Coin::new(989, "token0"));
+        // the same 1000 token1 of the exact in swap above are needed, 10 of which are spread rewards
+        assert_eq!(res.token_in, Coin::new(1000, "token1"));
+        assert_eq!(res.spread_rewards, Decimal256::from_str("10").unwrap());
+        assert_eq!(res.end_tick, 1980);
+    }
+
+    #[test]
+    fn test_simulate_create_position() {
+        // full range at price 1 takes both tokens in equal amounts
+        let res = simulate_create_position(
+            Decimal256::one(),
+            MIN_INITIALIZED_TICK,
+            MAX_TICK as i64,
+            Uint128::new(1000),
+            Uint128::new(1000),
+        )
+        .unwrap();
+        assert_eq!(res.amount0, Uint128::new(1000));
+        assert_eq!(res.amount1, Uint128::new(1000));
+
+        // the range from price 0.99 to 1.1 needs far more token0, so token0 limits the liquidity
+        let res = simulate_create_position(
+            Decimal256::one(),
+            -100000,
+            100000,
+            Uint128::new(1000),
+            Uint128::new(500),
+        )
+        .unwrap();
+        assert_eq!(
+            res.liquidity,
+            Decimal256::from_str("21488.088481701515466327").unwrap()
+        );
+        assert_eq!(res.amount0, Uint128::new(1000));
+        assert_eq!(res.amount1, Uint128::new(108));
+
+        // a range above the current price only takes token0
+        let res = simulate_create_position(
+            Decimal256::one(),
+            100,
+            200,
+            Uint128::new(1000),
+            Uint128::new(500),
+        )
+        .unwrap();
+        assert_eq!(res.amount0, Uint128::new(1000));
+        assert_eq!(res.amount1, Uint128::zero());
+    }
+
+    #[test]
+    fn test_swap_not_enough_liquidity() {
+        let mut deps = mock_dependencies();
+        let snapshot = snapshot_with_ticks("0", vec![(-100, "1000000"), (100, "-1000000")]);
+
+        let err = simulate_swap_exact_amount_in(
+            deps.as_mut().storage,
+            &snapshot,
+            &Coin::new(1000000, "token0"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::NotEnoughLiquidityForSwap {}));
+
+        let err = simulate_swap_exact_amount_in(
+            deps.as_mut().storage,
+            &snapshot,
+            &Coin::new(1000, "token2"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::DenomNotInPool { .. }));
+    }
+}
@@ -1,16 +1,42 @@
 #[cfg(not(feature = "imported"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Reply, Response};
+use cosmwasm_std::{
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+};
 use cw2::set_contract_version;
 
 use crate::error::ContractError;
-use crate::execute::{handle_swap_reply, single_sided_swap_and_lp};
-use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg};
-use crate::state::SWAP_REPLY_STATES;
+use crate::execute::{
+    balanced_join, change_range, full_range_swap_and_lp, handle_join_reply, handle_swap_reply,
+    handle_withdraw_reply, rebalance, single_sided_swap_and_lp,
+};
+use crate::grants::{check_grants, query_required_grants};
+use crate::guard::{update_config, validate_twap_window};
+use crate::ladder::{ladder_join, query_simulate_ladder_join};
+use crate::migrate::{handle_exit_pool_reply, migrate_from_gamm};
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::multi_pool::{handle_multi_pool_swap_reply, multi_pool_join};
+use crate::rewards::{
+    compound, handle_collect_reply, handle_harvest_collect_reply, handle_harvest_swap_reply,
+    handle_reward_swap_reply, harvest_to, set_swap_route,
+};
 use crate::state::{Config, CONFIG};
+use crate::state::{
+    COLLECT_REPLY_STATES, EXIT_POOL_REPLY_STATES, HARVEST_REPLY_STATES, JOIN_REPLY_STATES,
+    MULTI_POOL_REPLY_STATES, REWARD_SWAP_REPLY_STATES, SWAP_REPLY_STATES, WITHDRAW_REPLY_STATES,
+};
+use crate::stats::{query_global_stats, query_pool_stats};
 
 // Msg Reply IDs
 pub const SWAP_REPLY_ID: u64 = 1u64;
+pub const WITHDRAW_REPLY_ID: u64 = 2u64;
+pub const COLLECT_REPLY_ID: u64 = 3u64;
+pub const REWARD_SWAP_REPLY_ID: u64 = 4u64;
+pub const HARVEST_COLLECT_REPLY_ID: u64 = 5u64;
+pub const HARVEST_SWAP_REPLY_ID: u64 = 6u64;
+pub const EXIT_POOL_REPLY_ID: u64 = 7u64;
+pub const JOIN_REPLY_ID: u64 = 8u64;
+pub const MULTI_POOL_SWAP_REPLY_ID: u64 = 9u64;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:osmosis-single-sided-swap-and-lp";
@@ -27,7 +53,12 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let owner = deps.api.addr_validate(&msg.owner)?;
-    let state = Config { owner };
+    let state = Config {
+        owner,
+        swap_mode: msg.swap_mode,
+        max_spot_twap_deviation_bps: msg.max_spot_twap_deviation_bps,
+        twap_window_seconds: validate_twap_window(msg.twap_window_seconds)?,
+    };
 
     CONFIG.save(deps.storage, &state)?;
     Ok(Response::new().add_attribute("method", "instantiate"))
@@ -46,25 +77,179 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // Fail up front if the sender has not granted the contract what the flow sends on their behalf
+    if let Some(flow) = msg.flow() {
+        check_grants(deps.as_ref(), &env, &info.sender, &flow)?;
+    }
+
     match msg {
         ExecuteMsg::SingleSidedSwapAndJoin {
             pool_id,
-            lower_tick,
-            upper_tick,
+            range,
             token_provided,
             token_min_amount0,
             token_min_amount1,
+            max_spot_twap_deviation_bps,
+            expected_sqrt_price,
+            deadline,
+            best_effort,
         } => single_sided_swap_and_lp(
             &env,
             &info,
             deps,
             pool_id,
-            lower_tick,
-            upper_tick,
+            range,
+            token_provided,
+            token_min_amount0,
+            token_min_amount1,
+            max_spot_twap_deviation_bps,
+            expected_sqrt_price,
+            deadline,
+            best_effort.unwrap_or(false),
+        ),
+        ExecuteMsg::FullRangeSwapAndJoin {
+            pool_id,
+            token_provided,
+            token_min_amount0,
+            token_min_amount1,
+            superfluid_validator,
+            max_spot_twap_deviation_bps,
+            best_effort,
+        } => full_range_swap_and_lp(
+            &env,
+            &info,
+            deps,
+            pool_id,
             token_provided,
             token_min_amount0,
             token_min_amount1,
+            superfluid_validator,
+            max_spot_twap_deviation_bps,
+            best_effort.unwrap_or(false),
+        ),
+        ExecuteMsg::BalancedJoin {
+            pool_id,
+            range,
+            tokens_provided,
+            max_slippage,
+        } => balanced_join(
+            &env,
+            &info,
+            deps,
+            pool_id,
+            range,
+            tokens_provided,
+            max_slippage,
+        ),
+        ExecuteMsg::MultiPoolJoin {
+            pools,
+            token_provided,
+            max_slippage,
+        } => multi_pool_join(&env, &info, deps, pools, token_provided, max_slippage),
+        ExecuteMsg::LadderJoin {
+            pool_id,
+            center,
+            shape,
+            buckets,
+            token_provided,
+            max_slippage,
+        } => ladder_join(
+            &env,
+            &info,
+            deps,
+            pool_id,
+            center,
+            shape,
+            buckets,
+            token_provided,
+            max_slippage,
         ),
+        ExecuteMsg::Rebalance {
+            position_id,
+            new_range,
+            max_slippage,
+        } => rebalance(&env, &info, deps, position_id, new_range, max_slippage),
+        ExecuteMsg::ChangeRange {
+            position_id,
+            new_lower_tick,
+            new_upper_tick,
+            max_slippage,
+        } => change_range(
+            &env,
+            &info,
+            deps,
+            position_id,
+            new_lower_tick,
+            new_upper_tick,
+            max_slippage,
+        ),
+        ExecuteMsg::MigrateFromGamm {
+            gamm_pool_id,
+            shares,
+            cl_pool_id,
+            range,
+            max_slippage,
+        } => migrate_from_gamm(
+            &env,
+            &info,
+            deps,
+            gamm_pool_id,
+            shares,
+            cl_pool_id,
+            range,
+            max_slippage,
+        ),
+        ExecuteMsg::Compound {
+            position_id,
+            max_slippage,
+        } => compound(&env, &info, deps, position_id, max_slippage),
+        ExecuteMsg::HarvestTo {
+            position_ids,
+            target_denom,
+            min_out,
+        } => harvest_to(&env, &info, deps, position_ids, target_denom, min_out),
+        ExecuteMsg::UpdateConfig {
+            swap_mode,
+            max_spot_twap_deviation_bps,
+            twap_window_seconds,
+        } => update_config(
+            deps,
+            &info,
+            swap_mode,
+            max_spot_twap_deviation_bps,
+            twap_window_seconds,
+        ),
+        ExecuteMsg::SetSwapRoute {
+            token_in_denom,
+            token_out_denom,
+            route,
+        } => set_swap_route(deps, &info, token_in_denom, token_out_denom, route),
+    }
+}
+
+/// Handling contract queries
+#[cfg_attr(not(feature = "imported"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::RequiredGrants { granter, flow } => {
+            to_json_binary(&query_required_grants(deps, &env, granter, flow)?)
+        }
+        QueryMsg::PoolStats { pool_id } => to_json_binary(&query_pool_stats(deps, pool_id)?),
+        QueryMsg::GlobalStats {} => to_json_binary(&query_global_stats(deps)?),
+        QueryMsg::SimulateLadderJoin {
+            pool_id,
+            center,
+            shape,
+            buckets,
+            token_provided,
+        } => to_json_binary(&query_simulate_ladder_join(
+            deps,
+            pool_id,
+            center,
+            shape,
+            buckets,
+            token_provided,
+        )?),
     }
 }
 
@@ -80,6 +265,73 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
 
         // call reply function to handle the swap return
         handle_swap_reply(deps, env, msg, swap_msg_state)
+    } else if msg.id == WITHDRAW_REPLY_ID {
+        // get intermediate withdraw reply state. Error if not found.
+        let withdraw_msg_state = WITHDRAW_REPLY_STATES.load(deps.storage, msg.id)?;
+
+        // prune intermedate state
+        WITHDRAW_REPLY_STATES.remove(deps.storage, msg.id);
+
+        // call reply function to handle the withdrawn assets
+        handle_withdraw_reply(deps, env, msg, withdraw_msg_state)
+    } else if msg.id == COLLECT_REPLY_ID {
+        // get intermediate collect reply state. Error if not found.
+        let compound_state = COLLECT_REPLY_STATES.load(deps.storage, msg.id)?;
+
+        // prune intermedate state
+        COLLECT_REPLY_STATES.remove(deps.storage, msg.id);
+
+        // call reply function to handle the collected rewards
+        handle_collect_reply(deps, env, msg, compound_state)
+    } else if msg.id == REWARD_SWAP_REPLY_ID {
+        // get intermediate reward swap reply state. Error if not found.
+        let reward_swap_msg_state = REWARD_SWAP_REPLY_STATES.load(deps.storage, msg.id)?;
+
+        // prune intermedate state
+        REWARD_SWAP_REPLY_STATES.remove(deps.storage, msg.id);
+
+        // call reply function to handle the swapped rewards
+        handle_reward_swap_reply(deps, env, msg, reward_swap_msg_state)
+    } else if msg.id == HARVEST_COLLECT_REPLY_ID || msg.id == HARVEST_SWAP_REPLY_ID {
+        // get intermediate harvest reply state. Error if not found.
+        let harvest_state = HARVEST_REPLY_STATES.load(deps.storage, msg.id)?;
+
+        // prune intermedate state
+        HARVEST_REPLY_STATES.remove(deps.storage, msg.id);
+
+        // call reply function to handle the collected or swapped rewards
+        if msg.id == HARVEST_COLLECT_REPLY_ID {
+            handle_harvest_collect_reply(deps, env, msg, harvest_state)
+        } else {
+            handle_harvest_swap_reply(deps, env, msg, harvest_state)
+        }
+    } else if msg.id == EXIT_POOL_REPLY_ID {
+        // get intermediate exit pool reply state. Error if not found.
+        let exit_pool_msg_state = EXIT_POOL_REPLY_STATES.load(deps.storage, msg.id)?;
+
+        // prune intermedate state
+        EXIT_POOL_REPLY_STATES.remove(deps.storage, msg.id);
+
+        // call reply function to handle the exit assets
+        handle_exit_pool_reply(deps, env, msg, exit_pool_msg_state)
+    } else if msg.id == JOIN_REPLY_ID {
+        // get intermediate join reply state. Error if not found.
+        let join_msg_state = JOIN_REPLY_STATES.load(deps.storage, msg.id)?;
+
+        // prune intermedate state
+        JOIN_REPLY_STATES.remove(deps.storage, msg.id);
+
+        // call reply function to handle the outcome of the position message
+        handle_join_reply(deps, msg, join_msg_state)
+    } else if msg.id == MULTI_POOL_SWAP_REPLY_ID {
+        // get intermediate multi pool join state. Error if not found.
+        let multi_pool_state = MULTI_POOL_REPLY_STATES.load(deps.storage, msg.id)?;
+
+        // prune intermedate state
+        MULTI_POOL_REPLY_STATES.remove(deps.storage, msg.id);
+
+        // call reply function to split the swap output across the pools
+        handle_multi_pool_swap_reply(deps, env, msg, multi_pool_state)
     } else {
         Ok(Response::new())
     }
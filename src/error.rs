@@ -1,6 +1,7 @@
 use cosmwasm_std::{
-    CheckedFromRatioError, CheckedMultiplyRatioError, Coin, ConversionOverflowError, Decimal256,
-    Decimal256RangeExceeded, DivideByZeroError, OverflowError, StdError, Uint128,
+    CheckedFromRatioError, CheckedMultiplyRatioError, Coin, ConversionOverflowError, Decimal,
+    Decimal256, Decimal256RangeExceeded, DivideByZeroError, OverflowError, StdError, Timestamp,
+    Uint128,
 };
 use cw_utils::PaymentError;
 use thiserror::Error;
@@ -103,12 +104,116 @@ pub enum ContractError {
     #[error("Price must be between 0.000000000001 and 100000000000000000000000000000000000000. Got {:?}", price)]
     PriceBoundError { price: Decimal256 },
 
+    #[error(
+        "Sqrt price {sqrt_price} is not within a tick of the tick {tick} derived from its square"
+    )]
+    SqrtPriceToTickError { sqrt_price: Decimal256, tick: i64 },
+
+    #[error("Invalid tick range, lower tick {lower_tick} must be below upper tick {upper_tick}")]
+    InvalidTickRange { lower_tick: i64, upper_tick: i64 },
+
+    #[error("Pool tick spacing must be positive")]
+    InvalidTickSpacing {},
+
     #[error("Cannot handle negative powers in uints")]
     CannotHandleNegativePowersInUint {},
 
     #[error("Denom (provided_denom) does not exist in pool")]
     DenomNotInPool { provided_denom: String },
 
+    #[error("Not enough liquidity in the pool to complete the swap")]
+    NotEnoughLiquidityForSwap {},
+
+    #[error("Amount of {amount} {denom} is below the minimum of {min_amount}")]
+    AmountBelowMinimum {
+        denom: String,
+        amount: Uint128,
+        min_amount: Uint128,
+    },
+
+    #[error("Max slippage must be at most 1, got {max_slippage}")]
+    InvalidMaxSlippage { max_slippage: Decimal },
+
+    #[error("MsgExec response has no result at index {index}")]
+    InvalidMsgExecResponse { index: usize },
+
     #[error("Failed Swap: {reason:?}")]
     FailedSwap { reason: String },
+
+    #[error("Failed Withdraw: {reason:?}")]
+    FailedWithdraw { reason: String },
+
+    #[error("Failed Collect: {reason:?}")]
+    FailedCollect { reason: String },
+
+    #[error("Failed Exit Pool: {reason:?}")]
+    FailedExitPool { reason: String },
+
+    #[error("Assets of GAMM pool {gamm_pool_id} are not the tokens of CL pool {cl_pool_id}")]
+    GammPoolTokensMismatch { gamm_pool_id: u64, cl_pool_id: u64 },
+
+    #[error("Spot price {spot_price} deviates from the TWAP {twap} by more than {max_deviation_bps} bps")]
+    SpotPriceDeviatesFromTwap {
+        spot_price: Decimal256,
+        twap: Decimal256,
+        max_deviation_bps: u64,
+    },
+
+    #[error("Sqrt price {sqrt_price} moved more than {tolerance_bps} bps from the expected {expected_sqrt_price}")]
+    SqrtPriceMoved {
+        sqrt_price: Decimal256,
+        expected_sqrt_price: Decimal256,
+        tolerance_bps: u64,
+    },
+
+    #[error("Deadline {deadline} passed, block time is {block_time}")]
+    DeadlineExceeded {
+        deadline: Timestamp,
+        block_time: Timestamp,
+    },
+
+    #[error("TWAP window must be between 1 and {max_twap_window_seconds} seconds, got {twap_window_seconds}")]
+    InvalidTwapWindow {
+        twap_window_seconds: u64,
+        max_twap_window_seconds: u64,
+    },
+
+    #[error("Pool {pool_id} has no TWAP over the last {twap_window_seconds} seconds, it may be younger than that: {reason}")]
+    NoTwapHistory {
+        pool_id: u64,
+        twap_window_seconds: u64,
+        reason: String,
+    },
+
+    #[error("Missing authz grant for {msg_type_url} to the contract")]
+    MissingAuthzGrant { msg_type_url: String },
+
+    #[error("Swap route from {token_in_denom} must end in {token_out_denom}")]
+    InvalidSwapRoute {
+        token_in_denom: String,
+        token_out_denom: String,
+    },
+
+    #[error(
+        "Swapping {token_in} is simulated to return nothing, there is no amount out to swap for"
+    )]
+    ZeroSwapOutput { token_in: Coin },
+
+    #[error("No tokens provided")]
+    NoTokensProvided {},
+
+    #[error("No positions provided")]
+    NoPositionsProvided {},
+
+    #[error("No pools provided")]
+    NoPoolsProvided {},
+
+    #[error("Pool weights must sum to a positive amount")]
+    InvalidPoolWeights {},
+
+    #[error("Pool {pool_id} is not a pool of the pair of pool {other_pool_id}")]
+    PoolPairMismatch { pool_id: u64, other_pool_id: u64 },
+
+    #[error("A ladder takes 1 to {max_buckets} buckets, got {buckets}")]
+    InvalidLadderBuckets { buckets: u64, max_buckets: u64 },
 }
@@ -0,0 +1,193 @@
+use cosmwasm_std::{Coin, Decimal256, Event, Uint128};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool;
+
+// The types and attribute names of the events are stable, indexers consume them directly.
+// The wasm module prefixes the types with "wasm-".
+
+/// Emitted when a join sends its swap, with the numbers the swap was sized with
+pub struct JoinSwapEvent {
+    pub pool_id: u64,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    pub current_tick: i64,
+    /// The ratios of the assets of the range at the current tick, from calc_asset_ratio_from_ticks
+    pub asset0_ratio: Decimal256,
+    pub asset1_ratio: Decimal256,
+    /// The tick the swap is predicted to move the pool to
+    pub predicted_tick: i64,
+    pub token_in: Coin,
+    pub expected_token_out: Coin,
+}
+
+impl JoinSwapEvent {
+    pub const TYPE: &'static str = "join_swap";
+}
+
+impl From<JoinSwapEvent> for Event {
+    fn from(event: JoinSwapEvent) -> Self {
+        Event::new(JoinSwapEvent::TYPE)
+            .add_attribute("pool_id", event.pool_id.to_string())
+            .add_attribute("lower_tick", event.lower_tick.to_string())
+            .add_attribute("upper_tick", event.upper_tick.to_string())
+            .add_attribute("current_tick", event.current_tick.to_string())
+            .add_attribute("asset0_ratio", event.asset0_ratio.to_string())
+            .add_attribute("asset1_ratio", event.asset1_ratio.to_string())
+            .add_attribute("predicted_tick", event.predicted_tick.to_string())
+            .add_attribute("token_in", event.token_in.to_string())
+            .add_attribute("expected_token_out", event.expected_token_out.to_string())
+    }
+}
+
+/// Emitted when a join sends the message that puts the tokens into the position
+pub struct JoinPositionEvent {
+    pub pool_id: u64,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    /// The swap output, if the join swapped
+    pub token_out: Option<Coin>,
+    /// The amounts sent to the position message
+    pub amount0: Uint128,
+    pub amount1: Uint128,
+    /// The amounts the position is expected to leave over at the current price of the pool
+    pub dust0: Uint128,
+    pub dust1: Uint128,
+}
+
+impl JoinPositionEvent {
+    pub const TYPE: &'static str = "join_position";
+
+    /// The dust as coins of the pool's tokens
+    pub fn dust(&self, pool: &Pool) -> [Coin; 2] {
+        [
+            Coin {
+                denom: pool.token0.clone(),
+                amount: self.dust0,
+            },
+            Coin {
+                denom: pool.token1.clone(),
+                amount: self.dust1,
+            },
+        ]
+    }
+}
+
+impl From<JoinPositionEvent> for Event {
+    fn from(event: JoinPositionEvent) -> Self {
+        let mut e = Event::new(JoinPositionEvent::TYPE)
+            .add_attribute("pool_id", event.pool_id.to_string())
+            .add_attribute("lower_tick", event.lower_tick.to_string())
+            .add_attribute("upper_tick", event.upper_tick.to_string());
+        if let Some(token_out) = event.token_out {
+            e = e.add_attribute("token_out", token_out.to_string());
+        }
+        e.add_attribute("amount0", event.amount0)
+            .add_attribute("amount1", event.amount1)
+            .add_attribute("dust0", event.dust0)
+            .add_attribute("dust1", event.dust1)
+    }
+}
+
+/// Emitted when the position message of a best effort join fails. The swap is kept, the tokens stay
+/// with the user.
+pub struct JoinFailedEvent {
+    pub pool_id: u64,
+    pub reason: String,
+    /// The tokens that were sent to the position message
+    pub tokens: Vec<Coin>,
+}
+
+impl JoinFailedEvent {
+    pub const TYPE: &'static str = "join_failed";
+}
+
+impl From<JoinFailedEvent> for Event {
+    fn from(event: JoinFailedEvent) -> Self {
+        let tokens: Vec<String> = event.tokens.iter().map(|c| c.to_string()).collect();
+        Event::new(JoinFailedEvent::TYPE)
+            .add_attribute("pool_id", event.pool_id.to_string())
+            .add_attribute("reason", event.reason)
+            .add_attribute("tokens", tokens.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_join_swap_event() {
+        let event: Event = JoinSwapEvent {
+            pool_id: 1,
+            lower_tick: -1000,
+            upper_tick: 1000,
+            current_tick: 0,
+            asset0_ratio: Decimal256::from_str("0.5").unwrap(),
+            asset1_ratio: Decimal256::from_str("0.5").unwrap(),
+            predicted_tick: -10,
+            token_in: Coin::new(500, "token0"),
+            expected_token_out: Coin::new(499, "token1"),
+        }
+        .into();
+
+        assert_eq!(event.ty, "join_swap");
+        let attributes: Vec<(&str, &str)> = event
+            .attributes
+            .iter()
+            .map(|a| (a.key.as_str(), a.value.as_str()))
+            .collect();
+        assert_eq!(
+            attributes,
+            vec![
+                ("pool_id", "1"),
+                ("lower_tick", "-1000"),
+                ("upper_tick", "1000"),
+                ("current_tick", "0"),
+                ("asset0_ratio", "0.5"),
+                ("asset1_ratio", "0.5"),
+                ("predicted_tick", "-10"),
+                ("token_in", "500token0"),
+                ("expected_token_out", "499token1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join_position_event_without_swap() {
+        let event: Event = JoinPositionEvent {
+            pool_id: 1,
+            lower_tick: -1000,
+            upper_tick: 1000,
+            token_out: None,
+            amount0: Uint128::new(500),
+            amount1: Uint128::new(499),
+            dust0: Uint128::new(1),
+            dust1: Uint128::zero(),
+        }
+        .into();
+
+        assert_eq!(event.ty, "join_position");
+        assert!(!event.attributes.iter().any(|a| a.key == "token_out"));
+        assert!(event
+            .attributes
+            .iter()
+            .any(|a| a.key == "dust0" && a.value == "1"));
+    }
+
+    #[test]
+    fn test_join_failed_event() {
+        let event: Event = JoinFailedEvent {
+            pool_id: 1,
+            reason: "slippage".to_string(),
+            tokens: vec![Coin::new(500, "token0"), Coin::new(499, "token1")],
+        }
+        .into();
+
+        assert_eq!(event.ty, "join_failed");
+        assert!(event
+            .attributes
+            .iter()
+            .any(|a| a.key == "tokens" && a.value == "500token0,499token1"));
+    }
+}
@@ -1,145 +1,467 @@
-use crate::contract::SWAP_REPLY_ID;
-use crate::state::{SwapMsgReplyState, SWAP_REPLY_STATES};
-use cosmwasm_std::Decimal256;
+use crate::contract::{JOIN_REPLY_ID, SWAP_REPLY_ID, WITHDRAW_REPLY_ID};
+use crate::state::{
+    JoinMsgReplyState, PositionTarget, SwapMode, SwapMsgReplyState, SwapSizing,
+    WithdrawMsgReplyState, CONFIG, JOIN_REPLY_STATES, SWAP_REPLY_STATES, WITHDRAW_REPLY_STATES,
+};
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    Coin, DepsMut, Env, MessageInfo, Reply, Response, SubMsg, SubMsgResponse, SubMsgResult, Uint128,
+    Addr, Binary, Coin, DepsMut, Env, Event, MessageInfo, QuerierWrapper, Reply, Response,
+    StdError, Storage, SubMsg, SubMsgResponse, SubMsgResult, Uint128,
 };
-use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::MsgCreatePosition;
+use cosmwasm_std::{Decimal, Decimal256, Timestamp};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::authz::v1beta1::MsgExecResponse;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as OsmosisCoin;
 use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool;
-use osmosis_std::types::osmosis::poolmanager::v1beta1::MsgSwapExactAmountInResponse;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    ConcentratedliquidityQuerier, FullPositionBreakdown, MsgAddToPosition, MsgCollectIncentives,
+    MsgCollectIncentivesResponse, MsgCollectSpreadRewards, MsgCollectSpreadRewardsResponse,
+    MsgCreatePosition, MsgWithdrawPosition, MsgWithdrawPositionResponse, Position,
+};
 use osmosis_std::types::osmosis::poolmanager::v1beta1::PoolmanagerQuerier;
-use osmosis_std::types::osmosis::poolmanager::v1beta1::{MsgSwapExactAmountIn, SwapAmountInRoute};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountOut, MsgSwapExactAmountOutResponse, SwapAmountOutRoute,
+};
+use osmosis_std::types::osmosis::superfluid::MsgCreateFullRangePositionAndSuperfluidDelegate;
 use std::str::FromStr;
 
-use crate::tick::{price_to_tick, tick_to_price};
+use crate::cl_sim::{
+    dec_from_uint, parse_chain_dec, query_pool_snapshot, simulate_create_position,
+    simulate_swap_exact_amount_in, uint_from_dec_floor, PoolSnapshot,
+};
+use crate::events::{JoinFailedEvent, JoinPositionEvent, JoinSwapEvent};
+use crate::grants::check_swap_grant;
+use crate::guard::{check_deadline, check_expected_sqrt_price, check_spot_twap_deviation};
+use crate::msg::{ExpectedSqrtPrice, RangeSpec};
+use crate::range::{resolve_range, same_width_range};
+use crate::rewards::sum_coins;
+use crate::solver::calc_two_sided_swap;
+use crate::stats::record_join;
+use crate::tick::tick_to_price;
 use crate::ContractError;
 use osmosis_std::types::cosmos::authz::v1beta1::MsgExec;
 
+// How many percent more than estimated the exact out swap of a single sided join may swap in
+const EXACT_OUT_TOKEN_IN_MARGIN_PERCENT: u64 = 1;
+
 // swap_for_single_side_lp is the primary entry point for the contract
 // The paramaters to note are:
 // - pool_id: The id of the pool the position will be created in
-// - lower_tick: The desired lower tick of the position
-// - upper_tick: The desired upper tick of the position
+// - range: The desired tick range of the position, resolved against the pool's current tick
 // - token_provided: The amount of tokens to be provided to the pool. This value must be a length of 1. This will be the token that is swapped for the other token.
 // - token_min_amount0: The minimum amount of token0 that will be used to create the position.
 // - token_min_amount1: The minimum amount of token1 that will be used to create the position.
+// - max_spot_twap_deviation_bps: How far the spot price may deviate from the TWAP. Defaults to the one in the config.
+// - expected_sqrt_price: The sqrt price the join was signed against and how far the pool may have moved from it.
+// - deadline: The block time after which the join is refused.
+// - best_effort: Whether a failed position message leaves the tokens with the user instead of reverting the join.
 pub fn single_sided_swap_and_lp(
     env: &Env,
     info: &MessageInfo,
-    mut deps: DepsMut,
+    deps: DepsMut,
     pool_id: u64,
-    lower_tick: i64,
-    upper_tick: i64,
+    range: RangeSpec,
     token_provided: Coin,
     token_min_amount0: Uint128,
     token_min_amount1: Uint128,
+    max_spot_twap_deviation_bps: Option<u64>,
+    expected_sqrt_price: Option<ExpectedSqrtPrice>,
+    deadline: Option<Timestamp>,
+    best_effort: bool,
 ) -> Result<Response, ContractError> {
-    let sender = info.sender.to_string();
+    // Refuse a message that sat in the mempool for too long
+    check_deadline(env, deadline)?;
 
-    // Get the pool the position will be created in
-    let pool: Pool = PoolmanagerQuerier::new(&deps.querier)
-        .pool(pool_id)?
-        .pool
-        .ok_or(ContractError::PoolNotFound { pool_id: pool_id })?
-        .try_into()
-        .unwrap();
-    let spread_factor = Decimal256::from_str(pool.spread_factor.as_str())?;
-
-    // Get a rough estimate of the ratio of assets in the pool at the tick range the user wants to create a position in
-    let (asset_0_ratio, asset_1_ratio) =
-        calc_asset_ratio_from_ticks(upper_tick, pool.current_tick, lower_tick)?;
-
-    deps.api.debug(&format!(
-        "initial asset ratios: asset_0_ratio {asset_0_ratio:?}, asset_1_ratio {asset_1_ratio:?}"
-    ));
-
-    let token_out_denom: String;
-    let token_in: Coin;
-    let token_provided_swap_amount: Uint128;
-
-    // Depending on which asset we are swapping in, utilize the ratio of assets calculated above to determine
-    // how much of the the tokenIn we provided will be used for swapping
-    if token_provided.denom == pool.token0.clone() {
-        if pool.current_tick > upper_tick {
-            // If the current tick is greater than the upper tick, we will swap the entire amount of token0 for token1
-            token_provided_swap_amount = token_provided.amount;
-        } else {
-            // Otherwise, we utilize the ratio of assets calculated above to determine how much of token0 to swap for token1
-            let token_provided_dec =
-                Decimal256::from_str(token_provided.amount.to_string().as_str())?;
-            token_provided_swap_amount = token_provided_dec
-                .checked_mul(asset_1_ratio.checked_add(spread_factor)?)?
-                .to_uint_floor()
-                .try_into()?;
-        }
-        token_out_denom = pool.token1.clone();
-        token_in = Coin {
-            denom: pool.token0.clone(),
-            amount: token_provided_swap_amount,
-        };
-    } else if token_provided.denom == pool.token1.clone() {
-        if pool.current_tick < lower_tick {
-            // If the current tick is less than the lower tick, we will swap the entire amount of token1 for token0
-            token_provided_swap_amount = token_provided.amount;
+    // Get the pool the position will be created in, its price must not have moved since the message was signed
+    let pool = query_cl_pool(&deps.querier, pool_id)?;
+    check_expected_sqrt_price(&pool, expected_sqrt_price)?;
+
+    // Resolve the tick range of the position against the current state of the pool
+    let (lower_tick, upper_tick) =
+        resolve_range(deps.storage, &range, pool.current_tick, pool.tick_spacing)?;
+
+    swap_and_lp(
+        env,
+        info,
+        deps,
+        &pool,
+        lower_tick,
+        upper_tick,
+        token_provided,
+        token_min_amount0,
+        token_min_amount1,
+        PositionTarget::NewPosition,
+        max_spot_twap_deviation_bps,
+        best_effort,
+    )
+}
+
+// full_range_swap_and_lp creates a full range position from a single token
+// The paramaters to note are:
+// - pool_id: The id of the pool the position will be created in
+// - token_provided: The amount of tokens to be provided to the pool. This will be the token that is swapped for the other token.
+// - token_min_amount0: The minimum amount of token0 that will be used to create the position.
+// - token_min_amount1: The minimum amount of token1 that will be used to create the position.
+// - superfluid_validator: If set, the position is superfluid delegated to this validator. The pool must be superfluid enabled.
+// - max_spot_twap_deviation_bps: How far the spot price may deviate from the TWAP. Defaults to the one in the config.
+// - best_effort: Whether a failed position message leaves the tokens with the user instead of reverting the join.
+pub fn full_range_swap_and_lp(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    pool_id: u64,
+    token_provided: Coin,
+    token_min_amount0: Uint128,
+    token_min_amount1: Uint128,
+    superfluid_validator: Option<String>,
+    max_spot_twap_deviation_bps: Option<u64>,
+    best_effort: bool,
+) -> Result<Response, ContractError> {
+    let pool = query_cl_pool(&deps.querier, pool_id)?;
+    let (lower_tick, upper_tick) = resolve_range(
+        deps.storage,
+        &RangeSpec::FullRange,
+        pool.current_tick,
+        pool.tick_spacing,
+    )?;
+
+    let position_target = match superfluid_validator {
+        Some(validator) => PositionTarget::FullRangeSuperfluid { validator },
+        None => PositionTarget::NewPosition,
+    };
+
+    swap_and_lp(
+        env,
+        info,
+        deps,
+        &pool,
+        lower_tick,
+        upper_tick,
+        token_provided,
+        token_min_amount0,
+        token_min_amount1,
+        position_target,
+        max_spot_twap_deviation_bps,
+        best_effort,
+    )
+}
+
+// balanced_join creates a position from both tokens of the pool in any ratio
+// The paramaters to note are:
+// - pool_id: The id of the pool the position will be created in
+// - range: The desired tick range of the position, resolved against the pool's current tick
+// - tokens_provided: The tokens to be provided to the pool, one or both of the pool's tokens. The part of the token
+//   in excess for the range is swapped for the other one.
+// - max_slippage: How far below their simulated amounts the swap output and the position amounts may be.
+pub fn balanced_join(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    pool_id: u64,
+    range: RangeSpec,
+    tokens_provided: Vec<Coin>,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    validate_max_slippage(max_slippage)?;
+
+    let pool = query_cl_pool(&deps.querier, pool_id)?;
+    let (amount0, amount1) = pool_token_amounts(&pool, &tokens_provided)?;
+
+    // Resolve the tick range of the position against the current state of the pool
+    let (lower_tick, upper_tick) =
+        resolve_range(deps.storage, &range, pool.current_tick, pool.tick_spacing)?;
+
+    let snapshot = query_pool_snapshot(&deps.querier, &pool)?;
+    let response = swap_to_ratio_and_join(
+        deps,
+        env,
+        info.sender.clone(),
+        &pool,
+        &snapshot,
+        lower_tick,
+        upper_tick,
+        amount0,
+        amount1,
+        max_slippage,
+        PositionTarget::NewPosition,
+    )?;
+    Ok(response.add_attribute("action", "balanced_join"))
+}
+
+// pool_token_amounts sums the provided tokens per token of the pool, they must all be one of the pool's tokens
+fn pool_token_amounts(
+    pool: &Pool,
+    tokens_provided: &[Coin],
+) -> Result<(Uint128, Uint128), ContractError> {
+    let mut amount0 = Uint128::zero();
+    let mut amount1 = Uint128::zero();
+    for token in tokens_provided {
+        if token.denom == pool.token0 {
+            amount0 = amount0.checked_add(token.amount)?;
+        } else if token.denom == pool.token1 {
+            amount1 = amount1.checked_add(token.amount)?;
         } else {
-            // Otherwise, we utilize the ratio of assets calculated above to determine how much of token1 to swap for token0
-            let token_provided_dec =
-                Decimal256::from_str(token_provided.amount.to_string().as_str())?;
-            token_provided_swap_amount = token_provided_dec
-                .checked_mul(asset_0_ratio.checked_add(spread_factor)?)?
-                .to_uint_floor()
-                .try_into()?;
+            return Err(ContractError::DenomNotInPool {
+                provided_denom: token.denom.clone(),
+            });
         }
-        token_out_denom = pool.token0.clone();
-        token_in = Coin {
-            denom: pool.token1.clone(),
-            amount: token_provided_swap_amount,
-        };
-    } else {
-        return Err(ContractError::DenomNotInPool {
-            provided_denom: token_provided.denom,
-        });
     }
+    if amount0.is_zero() && amount1.is_zero() {
+        return Err(ContractError::NoTokensProvided {});
+    }
+    Ok((amount0, amount1))
+}
 
-    deps.api.debug(&format!("token_in PRE {token_in:?}"));
+// swap_and_lp swaps the share of the provided token that is needed for a position in the given tick range
+// and stores what to do with the tokens once the swap reply comes back
+fn swap_and_lp(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    pool: &Pool,
+    lower_tick: i64,
+    upper_tick: i64,
+    token_provided: Coin,
+    token_min_amount0: Uint128,
+    token_min_amount1: Uint128,
+    position_target: PositionTarget,
+    max_spot_twap_deviation_bps: Option<u64>,
+    best_effort: bool,
+) -> Result<Response, ContractError> {
+    let snapshot = query_pool_snapshot(&deps.querier, pool)?;
 
-    // Since we have knowledge of liquidity in the pool, we can further refine this ratio since we will have an idea
-    // of where the swap will bring the current tick to.
-    let refined_token_in = iterative_naive_approach(
-        &mut deps,
-        token_in,
-        token_provided.clone(),
-        pool,
-        upper_tick,
+    // Determine how much of the provided token to swap for the other token in the pool
+    let single_sided_swap = calc_single_sided_swap(
+        deps.storage,
+        &snapshot,
+        &token_provided,
         lower_tick,
-        //1095,
+        upper_tick,
     )?;
+    let (refined_token_in, token_out_denom) = match single_sided_swap {
+        Some(single_sided_swap) => single_sided_swap,
+        None => {
+            // The range only takes the provided token, create the position straight away
+            let (amount0, amount1) = if token_provided.denom == pool.token0 {
+                (token_provided.amount, Uint128::zero())
+            } else {
+                (Uint128::zero(), token_provided.amount)
+            };
+            let response = join_without_swap(
+                deps,
+                env,
+                info.sender.clone(),
+                pool,
+                snapshot.current_sqrt_price,
+                lower_tick,
+                upper_tick,
+                amount0,
+                amount1,
+                token_min_amount0,
+                token_min_amount1,
+                position_target,
+                best_effort,
+            )?;
+            return Ok(response.add_attribute("action", "swap_for_single_side_lp"));
+        }
+    };
+
+    // The swap is sized at the spot price, so it must not have been moved away from the TWAP
+    check_spot_twap_deviation(deps.as_ref(), env, pool, max_spot_twap_deviation_bps)?;
+
+    let (asset0_ratio, asset1_ratio) =
+        calc_asset_ratio_from_ticks(upper_tick, snapshot.current_tick, lower_tick)?;
+    let swap = simulate_swap_exact_amount_in(deps.storage, &snapshot, &refined_token_in)?;
+    let swap_event = JoinSwapEvent {
+        pool_id: pool.id,
+        lower_tick,
+        upper_tick,
+        current_tick: snapshot.current_tick,
+        asset0_ratio,
+        asset1_ratio,
+        predicted_tick: swap.end_tick,
+        token_in: refined_token_in.clone(),
+        expected_token_out: swap.token_out.clone(),
+    };
 
-    deps.api.debug(&format!(
-        "swapping {refined_token_in:?}, for {token_out_denom:?}"
-    ));
+    let join_swap = match CONFIG.load(deps.storage)?.swap_mode {
+        // The chain rejects swapping for nothing
+        SwapMode::ExactOut if swap.token_out.amount.is_zero() => {
+            return Err(ContractError::ZeroSwapOutput {
+                token_in: refined_token_in,
+            })
+        }
+        SwapMode::ExactOut => JoinSwap::ExactOut {
+            // Up to the margin more than estimated may be swapped in, as far as there is enough of it
+            token_in_max: Coin {
+                denom: refined_token_in.denom,
+                amount: apply_slippage_up(
+                    refined_token_in.amount,
+                    Decimal::percent(EXACT_OUT_TOKEN_IN_MARGIN_PERCENT),
+                )
+                .min(token_provided.amount),
+            },
+            token_out_amount: swap.token_out.amount,
+        },
+        SwapMode::ExactIn => JoinSwap::ExactIn {
+            token_in: refined_token_in,
+            token_out_min_amount: Uint128::one(),
+        },
+    };
 
     // Create the swap execMsg and store the intermediate state
     let exec_msg = create_swap_exec_msg_and_store_state(
         deps,
-        env.clone(),
-        info.clone(),
-        sender.clone(),
-        pool_id,
+        env,
+        info.sender.clone(),
+        pool.id,
         token_out_denom.clone(),
         token_provided,
-        refined_token_in,
+        join_swap,
+        Uint128::zero(),
         lower_tick,
         upper_tick,
         token_min_amount0,
         token_min_amount1,
+        position_target,
+        best_effort,
     )?;
 
     Ok(Response::new()
         .add_attribute("action", "swap_for_single_side_lp")
+        .add_event(Event::from(swap_event))
         .add_submessage(SubMsg::reply_on_success(exec_msg, SWAP_REPLY_ID)))
 }
 
+pub(crate) fn query_cl_pool(querier: &QuerierWrapper, pool_id: u64) -> Result<Pool, ContractError> {
+    PoolmanagerQuerier::new(querier)
+        .pool(pool_id)?
+        .pool
+        .ok_or(ContractError::PoolNotFound { pool_id })?
+        .try_into()
+        .map_err(|_| ContractError::PoolNotFound { pool_id })
+}
+
+// calc_single_sided_swap determines the swap that brings the provided token to the ratio of assets
+// needed for a position in the given tick range. It returns the token to swap in and the denom to swap for,
+// or None if the range only takes the provided token.
+pub fn calc_single_sided_swap(
+    storage: &mut dyn Storage,
+    snapshot: &PoolSnapshot,
+    token_provided: &Coin,
+    lower_tick: i64,
+    upper_tick: i64,
+) -> Result<Option<(Coin, String)>, ContractError> {
+    // A range entirely above the current price only takes token0 and a range entirely below it only
+    // takes token1, providing that token needs no swap
+    if (token_provided.denom == snapshot.token0 && snapshot.current_tick < lower_tick)
+        || (token_provided.denom == snapshot.token1 && snapshot.current_tick >= upper_tick)
+    {
+        return Ok(None);
+    }
+
+    let (amount0, amount1, token_out_denom) = if token_provided.denom == snapshot.token0 {
+        (
+            token_provided.amount,
+            Uint128::zero(),
+            snapshot.token1.clone(),
+        )
+    } else if token_provided.denom == snapshot.token1 {
+        (
+            Uint128::zero(),
+            token_provided.amount,
+            snapshot.token0.clone(),
+        )
+    } else {
+        return Err(ContractError::DenomNotInPool {
+            provided_denom: token_provided.denom.clone(),
+        });
+    };
+
+    // Size the swap against the simulated swap, which charges the spread factor on the input before the
+    // price moves like the chain does, so the output after fees lands on the ratio of the range
+    let token_in =
+        calc_two_sided_swap(storage, snapshot, amount0, amount1, lower_tick, upper_tick)?;
+
+    Ok(token_in.map(|token_in| (token_in, token_out_denom)))
+}
+
+/// The swap the solver picks for a single sided join and the position it is expected to result in.
+#[cw_serde]
+pub struct SingleSidedJoinEstimate {
+    pub token_in: Coin,
+    pub expected_token_out: Coin,
+    pub position_amount0: Uint128,
+    pub position_amount1: Uint128,
+    pub liquidity: Decimal256,
+    pub dust: Vec<Coin>,
+}
+
+// estimate_single_sided_join runs the same swap calculation as single_sided_swap_and_lp against the pool
+// snapshot and simulates the resulting swap and position creation.
+pub fn estimate_single_sided_join(
+    storage: &mut dyn Storage,
+    snapshot: &PoolSnapshot,
+    token_provided: &Coin,
+    lower_tick: i64,
+    upper_tick: i64,
+) -> Result<SingleSidedJoinEstimate, ContractError> {
+    let token_out_denom = if token_provided.denom == snapshot.token0 {
+        snapshot.token1.clone()
+    } else {
+        snapshot.token0.clone()
+    };
+    let (token_in, expected_token_out, sqrt_price) =
+        match calc_single_sided_swap(storage, snapshot, token_provided, lower_tick, upper_tick)? {
+            Some((token_in, _)) => {
+                let swap = simulate_swap_exact_amount_in(storage, snapshot, &token_in)?;
+                (swap.token_in, swap.token_out, swap.end_sqrt_price)
+            }
+            // Without a swap the position is created at the current price
+            None => (
+                Coin {
+                    denom: token_provided.denom.clone(),
+                    amount: Uint128::zero(),
+                },
+                Coin {
+                    denom: token_out_denom,
+                    amount: Uint128::zero(),
+                },
+                snapshot.current_sqrt_price,
+            ),
+        };
+
+    let token_provided_remaining = token_provided.amount.checked_sub(token_in.amount)?;
+    let (amount0, amount1) = if token_provided.denom == snapshot.token0 {
+        (token_provided_remaining, expected_token_out.amount)
+    } else {
+        (expected_token_out.amount, token_provided_remaining)
+    };
+
+    let position = simulate_create_position(sqrt_price, lower_tick, upper_tick, amount0, amount1)?;
+
+    Ok(SingleSidedJoinEstimate {
+        token_in,
+        expected_token_out,
+        position_amount0: position.amount0,
+        position_amount1: position.amount1,
+        liquidity: position.liquidity,
+        dust: vec![
+            Coin {
+                denom: snapshot.token0.clone(),
+                amount: amount0.checked_sub(position.amount0)?,
+            },
+            Coin {
+                denom: snapshot.token1.clone(),
+                amount: amount1.checked_sub(position.amount1)?,
+            },
+        ],
+    })
+}
+
 // handle_swap_reply is called after the swap has been executed successfully
 // This function will create the provided position on behalf of the user with the tokens that were provided and swapped
 pub fn handle_swap_reply(
@@ -149,56 +471,123 @@ pub fn handle_swap_reply(
     swap_msg_reply_state: SwapMsgReplyState,
 ) -> Result<Response, ContractError> {
     if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
-        // Parse the swap response
-        let res: MsgSwapExactAmountInResponse = b.try_into().map_err(ContractError::Std)?;
-
-        // TODO: For some reason, this is returning a string with a new line character and a few other non relevant characters
-        // followed by the correct value. My guess is, the actual response type is MsgExecResponse and not MsgSwapExactAmountInResponse,
-        // but I am not aware of how to parse the MsgExecResponse type into MsgSwapExactAmountInResponse. For now, we just filter out the
-        // non relevant characters and parse the string into a Uint128.
-        let token_out_amount = res
-            .token_out_amount
-            .chars()
-            .filter(|c| c.is_digit(10))
-            .collect::<String>();
-        let token_out_amount = Uint128::from_str(&token_out_amount)?;
-
-        deps.api
-            .debug(&format!("token_out_amount {token_out_amount:?}"));
-
-        // Create the tokens provided vector and order it lexicographically
-        let token_out_coin = Coin {
-            denom: swap_msg_reply_state.token_out_denom,
-            amount: token_out_amount,
+        // Parse the swap response of the swap mode the swap was sent with
+        let (token_in_amount, token_out_amount) = match swap_msg_reply_state.exact_token_out_amount
+        {
+            None => {
+                let res: MsgSwapExactAmountInResponse = parse_msg_exec_response(b, 0)?;
+                (
+                    swap_msg_reply_state.token_in.amount,
+                    Uint128::from_str(&res.token_out_amount)?,
+                )
+            }
+            Some(token_out_amount) => {
+                let res: MsgSwapExactAmountOutResponse = parse_msg_exec_response(b, 0)?;
+                (Uint128::from_str(&res.token_in_amount)?, token_out_amount)
+            }
         };
-        let token_in_coin = Coin {
-            denom: swap_msg_reply_state.token_provided_remaining_coin.denom,
-            amount: swap_msg_reply_state.token_provided_remaining_coin.amount,
+        let token_in = Coin {
+            denom: swap_msg_reply_state.token_in.denom.clone(),
+            amount: token_in_amount,
         };
-        let mut tokens_provided = vec![token_out_coin, token_in_coin];
-        tokens_provided.sort_by(|a, b| a.denom.cmp(&b.denom));
-
-        deps.api
-            .debug(&format!("tokens_provided {tokens_provided:?}"));
-
-        // Create the create position message
-        let create_position_msg = MsgCreatePosition {
-            pool_id: swap_msg_reply_state.pool_id,
-            sender: swap_msg_reply_state.original_sender.to_string(),
-            lower_tick: swap_msg_reply_state.lower_tick,
-            upper_tick: swap_msg_reply_state.upper_tick,
-            tokens_provided: tokens_provided.into_iter().map(|c| c.into()).collect(),
-            token_min_amount0: swap_msg_reply_state.token_min_amount0.to_string(),
-            token_min_amount1: swap_msg_reply_state.token_min_amount1.to_string(),
+        let token_out = Coin {
+            denom: swap_msg_reply_state.token_out_denom,
+            amount: token_out_amount,
         };
 
-        // Execute the create position message on behalf of the user
+        // The position is created with the swap output, the token out the user provided besides it
+        // and the remaining provided token, including what an exact out swap did not spend of its maximum
+        let token_provided_remaining_coin = Coin {
+            denom: token_in.denom.clone(),
+            amount: swap_msg_reply_state
+                .token_provided_remaining_coin
+                .amount
+                .checked_add(
+                    swap_msg_reply_state
+                        .token_in
+                        .amount
+                        .checked_sub(token_in_amount)?,
+                )?,
+        };
+        let tokens_provided = position_tokens(vec![
+            Coin {
+                denom: token_out.denom.clone(),
+                amount: token_out
+                    .amount
+                    .checked_add(swap_msg_reply_state.token_out_provided_amount)?,
+            },
+            token_provided_remaining_coin,
+        ]);
+
+        // The swap moved the pool, the dust is estimated at its price afterwards
+        let pool = query_cl_pool(&deps.querier, swap_msg_reply_state.pool_id)?;
+        let position_event = join_position_event(
+            &pool,
+            parse_chain_dec(&pool.current_sqrt_price)?,
+            swap_msg_reply_state.lower_tick,
+            swap_msg_reply_state.upper_tick,
+            Some(token_out.clone()),
+            &tokens_provided,
+        )?;
+
+        // What the user brought into the join before the swap
+        let provided = vec![
+            Coin {
+                denom: token_in.denom.clone(),
+                amount: swap_msg_reply_state
+                    .token_provided_remaining_coin
+                    .amount
+                    .checked_add(swap_msg_reply_state.token_in.amount)?,
+            },
+            Coin {
+                denom: token_out.denom,
+                amount: swap_msg_reply_state.token_out_provided_amount,
+            },
+        ];
+        let dust = position_event.dust(&pool);
+
+        let position_msg = join_msg(
+            &deps.querier,
+            swap_msg_reply_state.position_target,
+            &swap_msg_reply_state.original_sender,
+            swap_msg_reply_state.pool_id,
+            swap_msg_reply_state.lower_tick,
+            swap_msg_reply_state.upper_tick,
+            tokens_provided.clone(),
+            swap_msg_reply_state.token_min_amount0,
+            swap_msg_reply_state.token_min_amount1,
+        )?;
+
+        // Execute the position message on behalf of the user
         let exec_msg: MsgExec = MsgExec {
             grantee: env.contract.address.to_string(),
-            msgs: vec![create_position_msg.to_any()],
+            msgs: vec![position_msg],
         };
 
-        return Ok(Response::default().add_message(exec_msg));
+        // A best effort join records itself and reports the position once the position message succeeded
+        if swap_msg_reply_state.best_effort {
+            JOIN_REPLY_STATES.save(
+                deps.storage,
+                JOIN_REPLY_ID,
+                &JoinMsgReplyState {
+                    pool_id: pool.id,
+                    original_sender: swap_msg_reply_state.original_sender,
+                    tokens_provided,
+                    provided,
+                    swapped: Some(token_in),
+                    dust: dust.to_vec(),
+                    position_event: position_event.into(),
+                },
+            )?;
+            return Ok(
+                Response::default().add_submessage(SubMsg::reply_always(exec_msg, JOIN_REPLY_ID))
+            );
+        }
+
+        record_join(deps.storage, pool.id, &provided, Some(&token_in), &dust)?;
+        return Ok(Response::default()
+            .add_event(Event::from(position_event))
+            .add_message(exec_msg));
     }
 
     Err(ContractError::FailedSwap {
@@ -206,35 +595,833 @@ pub fn handle_swap_reply(
     })
 }
 
-pub fn create_swap_exec_msg_and_store_state(
+// handle_join_reply is called after the position message of a best effort join
+// The join is recorded if the position was created, otherwise the failure is reported and the swapped and
+// remaining tokens, or the provided tokens of a join that didn't swap, stay with the user
+pub fn handle_join_reply(
+    deps: DepsMut,
+    msg: Reply,
+    join_msg_reply_state: JoinMsgReplyState,
+) -> Result<Response, ContractError> {
+    match msg.result {
+        SubMsgResult::Ok(_) => {
+            record_join(
+                deps.storage,
+                join_msg_reply_state.pool_id,
+                &join_msg_reply_state.provided,
+                join_msg_reply_state.swapped.as_ref(),
+                &join_msg_reply_state.dust,
+            )?;
+            Ok(Response::new().add_event(join_msg_reply_state.position_event))
+        }
+        SubMsgResult::Err(reason) => Ok(Response::new()
+            .add_attribute("original_sender", join_msg_reply_state.original_sender)
+            .add_event(Event::from(JoinFailedEvent {
+                pool_id: join_msg_reply_state.pool_id,
+                reason,
+                tokens: join_msg_reply_state.tokens_provided,
+            }))),
+    }
+}
+
+// join_msg creates the message that puts the provided tokens into the position target on behalf of the sender
+fn join_msg(
+    querier: &QuerierWrapper,
+    position_target: PositionTarget,
+    sender: &Addr,
+    pool_id: u64,
+    lower_tick: i64,
+    upper_tick: i64,
+    tokens_provided: Vec<Coin>,
+    token_min_amount0: Uint128,
+    token_min_amount1: Uint128,
+) -> Result<Any, ContractError> {
+    match position_target {
+        PositionTarget::NewPosition => Ok(create_position_msg(
+            sender,
+            pool_id,
+            lower_tick,
+            upper_tick,
+            tokens_provided,
+            token_min_amount0,
+            token_min_amount1,
+        )
+        .to_any()),
+        PositionTarget::ExistingPosition { position_id } => {
+            let pool = query_cl_pool(querier, pool_id)?;
+            Ok(MsgAddToPosition {
+                position_id,
+                sender: sender.to_string(),
+                amount0: amount_of(&tokens_provided, &pool.token0).to_string(),
+                amount1: amount_of(&tokens_provided, &pool.token1).to_string(),
+                token_min_amount0: token_min_amount0.to_string(),
+                token_min_amount1: token_min_amount1.to_string(),
+            }
+            .to_any())
+        }
+        PositionTarget::FullRangeSuperfluid { validator } => {
+            // The superfluid message takes no minimum amounts, so check them against the
+            // position the tokens create at the current price of the pool
+            let pool = query_cl_pool(querier, pool_id)?;
+            let position = simulate_create_position(
+                parse_chain_dec(&pool.current_sqrt_price)?,
+                lower_tick,
+                upper_tick,
+                amount_of(&tokens_provided, &pool.token0),
+                amount_of(&tokens_provided, &pool.token1),
+            )?;
+            check_min_amount(&pool.token0, position.amount0, token_min_amount0)?;
+            check_min_amount(&pool.token1, position.amount1, token_min_amount1)?;
+
+            Ok(MsgCreateFullRangePositionAndSuperfluidDelegate {
+                sender: sender.to_string(),
+                coins: tokens_provided.into_iter().map(|c| c.into()).collect(),
+                val_addr: validator,
+                pool_id,
+            }
+            .to_any())
+        }
+    }
+}
+
+// query_owned_position returns the position with the given id, erroring if it is not owned by the owner
+pub(crate) fn query_owned_position(
+    querier: &QuerierWrapper,
+    position_id: u64,
+    owner: &Addr,
+) -> Result<(Position, FullPositionBreakdown), ContractError> {
+    let breakdown = ConcentratedliquidityQuerier::new(querier)
+        .position_by_id(position_id)?
+        .position
+        .ok_or(ContractError::PositionNotFound)?;
+    let position = breakdown
+        .position
+        .clone()
+        .ok_or(ContractError::PositionNotFound)?;
+    if owner != &position.address {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok((position, breakdown))
+}
+
+// rebalance withdraws a position and creates a new one around the current price with the withdrawn assets
+// The paramaters to note are:
+// - position_id: The id of the position to move, it must be owned by the sender
+// - new_range: The desired tick range of the new position. Defaults to a range of the same width around the current tick.
+// - max_slippage: How far below their simulated amounts the withdrawn assets, the swap output and the new position amounts may be.
+pub fn rebalance(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    position_id: u64,
+    new_range: Option<RangeSpec>,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    validate_max_slippage(max_slippage)?;
+
+    // Get the position to move, only its owner can rebalance it
+    let (position, breakdown) = query_owned_position(&deps.querier, position_id, &info.sender)?;
+
+    // Resolve the tick range of the new position against the current state of the pool
+    let pool = query_cl_pool(&deps.querier, position.pool_id)?;
+    let new_range = match new_range {
+        Some(new_range) => new_range,
+        None => same_width_range(position.lower_tick, position.upper_tick, pool.tick_spacing)?,
+    };
+    let (lower_tick, upper_tick) = resolve_range(
+        deps.storage,
+        &new_range,
+        pool.current_tick,
+        pool.tick_spacing,
+    )?;
+
+    let response = withdraw_and_rejoin(
+        env,
+        info,
+        deps,
+        position,
+        breakdown,
+        lower_tick,
+        upper_tick,
+        max_slippage,
+        false,
+        SwapSizing::Simulated,
+    )?;
+    Ok(response.add_attribute("action", "rebalance"))
+}
+
+// change_range moves a position to a new tick range, adding its spread rewards and incentives to the new position
+// The swap to the ratio of the new range is sized by the ratio calc_asset_ratio_from_ticks gives at the current tick.
+// The paramaters to note are:
+// - position_id: The id of the position to move, it must be owned by the sender
+// - new_lower_tick: The lower tick of the new position
+// - new_upper_tick: The upper tick of the new position
+// - max_slippage: How far below their simulated amounts the withdrawn assets, the swap output and the new position amounts may be.
+pub fn change_range(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    position_id: u64,
+    new_lower_tick: i64,
+    new_upper_tick: i64,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    validate_max_slippage(max_slippage)?;
+
+    // Get the position to move, only its owner can change its range
+    let (position, breakdown) = query_owned_position(&deps.querier, position_id, &info.sender)?;
+
+    let pool = query_cl_pool(&deps.querier, position.pool_id)?;
+    let (lower_tick, upper_tick) = resolve_range(
+        deps.storage,
+        &RangeSpec::Ticks {
+            lower: new_lower_tick,
+            upper: new_upper_tick,
+        },
+        pool.current_tick,
+        pool.tick_spacing,
+    )?;
+
+    let response = withdraw_and_rejoin(
+        env,
+        info,
+        deps,
+        position,
+        breakdown,
+        lower_tick,
+        upper_tick,
+        max_slippage,
+        true,
+        SwapSizing::AssetRatio,
+    )?;
+    Ok(response.add_attribute("action", "change_range"))
+}
+
+// withdraw_and_rejoin withdraws all of the position's liquidity, optionally collecting its spread rewards and
+// incentives first, and stores the range of the new position the withdrawn assets are joined into once the
+// withdraw reply comes back
+fn withdraw_and_rejoin(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    position: Position,
+    breakdown: FullPositionBreakdown,
+    lower_tick: i64,
+    upper_tick: i64,
+    max_slippage: Decimal,
+    collect_rewards: bool,
+    swap_sizing: SwapSizing,
+) -> Result<Response, ContractError> {
+    let mut msgs = vec![];
+    if collect_rewards {
+        msgs.push(
+            MsgCollectSpreadRewards {
+                position_ids: vec![position.position_id],
+                sender: info.sender.to_string(),
+            }
+            .to_any(),
+        );
+        msgs.push(
+            MsgCollectIncentives {
+                position_ids: vec![position.position_id],
+                sender: info.sender.to_string(),
+            }
+            .to_any(),
+        );
+    }
+    // Withdrawing the full liquidity also claims the position's spread rewards and incentives if they weren't
+    // collected above. The chain pays them to the user separately from the withdrawn amounts in the response,
+    // so they stay in the user's wallet and are not moved into the new position.
+    msgs.push(
+        MsgWithdrawPosition {
+            position_id: position.position_id,
+            sender: info.sender.to_string(),
+            liquidity_amount: position.liquidity,
+        }
+        .to_any(),
+    );
+
+    // Withdraw the position on behalf of the user
+    let exec_msg: MsgExec = MsgExec {
+        grantee: env.contract.address.to_string(),
+        msgs,
+    };
+
+    // Save intermediate state
+    // We will utilize this state after the position has been withdrawn
+    WITHDRAW_REPLY_STATES.save(
+        deps.storage,
+        WITHDRAW_REPLY_ID,
+        &WithdrawMsgReplyState {
+            pool_id: position.pool_id,
+            original_sender: info.sender.clone(),
+            lower_tick,
+            upper_tick,
+            max_slippage,
+            withdraw_min_amount0: apply_slippage(
+                coin_amount(breakdown.asset0.as_ref())?,
+                max_slippage,
+            ),
+            withdraw_min_amount1: apply_slippage(
+                coin_amount(breakdown.asset1.as_ref())?,
+                max_slippage,
+            ),
+            collect_rewards,
+            swap_sizing,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("position_id", position.position_id.to_string())
+        .add_submessage(SubMsg::reply_on_success(exec_msg, WITHDRAW_REPLY_ID)))
+}
+
+// handle_withdraw_reply is called after a position has been withdrawn successfully
+// This function swaps the withdrawn assets to the ratio of the new range and creates the new position
+pub fn handle_withdraw_reply(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
-    sender: String,
+    msg: Reply,
+    withdraw_msg_reply_state: WithdrawMsgReplyState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        // Parse the withdraw response, it follows the collect responses if rewards were collected
+        let withdraw_index = if withdraw_msg_reply_state.collect_rewards {
+            2
+        } else {
+            0
+        };
+        let res: MsgWithdrawPositionResponse = parse_msg_exec_response(b.clone(), withdraw_index)?;
+        let mut amount0 = Uint128::from_str(&res.amount0)?;
+        let mut amount1 = Uint128::from_str(&res.amount1)?;
+
+        let pool = query_cl_pool(&deps.querier, withdraw_msg_reply_state.pool_id)?;
+        check_min_amount(
+            &pool.token0,
+            amount0,
+            withdraw_msg_reply_state.withdraw_min_amount0,
+        )?;
+        check_min_amount(
+            &pool.token1,
+            amount1,
+            withdraw_msg_reply_state.withdraw_min_amount1,
+        )?;
+
+        // The rewards paid in the pool's tokens join the new position as well, incentives in other denoms stay
+        // in the user's wallet
+        if withdraw_msg_reply_state.collect_rewards {
+            let spread_rewards: MsgCollectSpreadRewardsResponse =
+                parse_msg_exec_response(b.clone(), 0)?;
+            let incentives: MsgCollectIncentivesResponse = parse_msg_exec_response(b, 1)?;
+            let collected = sum_coins(
+                spread_rewards
+                    .collected_spread_rewards
+                    .iter()
+                    .chain(incentives.collected_incentives.iter()),
+            )?;
+            amount0 = amount0.checked_add(amount_of(&collected, &pool.token0))?;
+            amount1 = amount1.checked_add(amount_of(&collected, &pool.token1))?;
+        }
+
+        // The withdrawal changed the liquidity of the pool, so the snapshot is taken afterwards
+        let snapshot = query_pool_snapshot(&deps.querier, &pool)?;
+        let (lower_tick, upper_tick) = (
+            withdraw_msg_reply_state.lower_tick,
+            withdraw_msg_reply_state.upper_tick,
+        );
+        let token_in = match withdraw_msg_reply_state.swap_sizing {
+            SwapSizing::Simulated => calc_two_sided_swap(
+                deps.storage,
+                &snapshot,
+                amount0,
+                amount1,
+                lower_tick,
+                upper_tick,
+            )?,
+            SwapSizing::AssetRatio => {
+                calc_swap_from_asset_ratio(&snapshot, amount0, amount1, lower_tick, upper_tick)?
+            }
+        };
+        return swap_sized_and_join(
+            deps,
+            &env,
+            withdraw_msg_reply_state.original_sender,
+            &pool,
+            &snapshot,
+            lower_tick,
+            upper_tick,
+            amount0,
+            amount1,
+            token_in,
+            withdraw_msg_reply_state.max_slippage,
+            PositionTarget::NewPosition,
+        );
+    }
+
+    Err(ContractError::FailedWithdraw {
+        reason: msg.result.unwrap_err(),
+    })
+}
+
+// swap_to_ratio_and_join swaps the part of amount0 and amount1 that is in excess for a position in the given
+// tick range and creates the position on behalf of the sender. If the amounts already match the ratio of the
+// range, the position is created without a swap. The swap output and the position amounts are protected
+// with minimums of their simulated values less max_slippage.
+pub fn swap_to_ratio_and_join(
+    deps: DepsMut,
+    env: &Env,
+    sender: Addr,
+    pool: &Pool,
+    snapshot: &PoolSnapshot,
+    lower_tick: i64,
+    upper_tick: i64,
+    amount0: Uint128,
+    amount1: Uint128,
+    max_slippage: Decimal,
+    position_target: PositionTarget,
+) -> Result<Response, ContractError> {
+    let token_in = calc_two_sided_swap(
+        deps.storage,
+        snapshot,
+        amount0,
+        amount1,
+        lower_tick,
+        upper_tick,
+    )?;
+    swap_sized_and_join(
+        deps,
+        env,
+        sender,
+        pool,
+        snapshot,
+        lower_tick,
+        upper_tick,
+        amount0,
+        amount1,
+        token_in,
+        max_slippage,
+        position_target,
+    )
+}
+
+// swap_sized_and_join swaps token_in, the part of amount0 and amount1 found to be in excess for a position in the
+// given tick range, and creates the position on behalf of the sender. Without token_in, or if swapping it returns
+// nothing, the position is created without a swap.
+fn swap_sized_and_join(
+    deps: DepsMut,
+    env: &Env,
+    sender: Addr,
+    pool: &Pool,
+    snapshot: &PoolSnapshot,
+    lower_tick: i64,
+    upper_tick: i64,
+    amount0: Uint128,
+    amount1: Uint128,
+    token_in: Option<Coin>,
+    max_slippage: Decimal,
+    position_target: PositionTarget,
+) -> Result<Response, ContractError> {
+    // A swap too small to return anything can't be sent, its token in is left over as if no swap was needed
+    let sized_swap = match token_in {
+        Some(token_in) => {
+            let swap = simulate_swap_exact_amount_in(deps.storage, snapshot, &token_in)?;
+            (!swap.token_out.amount.is_zero()).then_some((token_in, swap))
+        }
+        None => None,
+    };
+    let (token_in, swap) = match sized_swap {
+        Some(sized_swap) => sized_swap,
+        None => {
+            // No swap is needed, create the position straight away
+            let position = simulate_create_position(
+                snapshot.current_sqrt_price,
+                lower_tick,
+                upper_tick,
+                amount0,
+                amount1,
+            )?;
+            return join_without_swap(
+                deps,
+                env,
+                sender,
+                pool,
+                snapshot.current_sqrt_price,
+                lower_tick,
+                upper_tick,
+                amount0,
+                amount1,
+                apply_slippage(position.amount0, max_slippage),
+                apply_slippage(position.amount1, max_slippage),
+                position_target,
+                false,
+            );
+        }
+    };
+
+    // The swap is sized at the spot price, so it must not have been moved away from the TWAP
+    check_spot_twap_deviation(deps.as_ref(), env, pool, None)?;
+
+    // The position the simulated swap results in gives the minimum amounts of both
+    let (token_provided, token_out_provided_amount, position_amount0, position_amount1) =
+        if token_in.denom == snapshot.token0 {
+            (
+                Coin {
+                    denom: snapshot.token0.clone(),
+                    amount: amount0,
+                },
+                amount1,
+                amount0.checked_sub(swap.token_in.amount)?,
+                amount1.checked_add(swap.token_out.amount)?,
+            )
+        } else {
+            (
+                Coin {
+                    denom: snapshot.token1.clone(),
+                    amount: amount1,
+                },
+                amount0,
+                amount0.checked_add(swap.token_out.amount)?,
+                amount1.checked_sub(swap.token_in.amount)?,
+            )
+        };
+    let position = simulate_create_position(
+        swap.end_sqrt_price,
+        lower_tick,
+        upper_tick,
+        position_amount0,
+        position_amount1,
+    )?;
+
+    let (asset0_ratio, asset1_ratio) =
+        calc_asset_ratio_from_ticks(upper_tick, snapshot.current_tick, lower_tick)?;
+    let swap_event = JoinSwapEvent {
+        pool_id: pool.id,
+        lower_tick,
+        upper_tick,
+        current_tick: snapshot.current_tick,
+        asset0_ratio,
+        asset1_ratio,
+        predicted_tick: swap.end_tick,
+        token_in: token_in.clone(),
+        expected_token_out: swap.token_out.clone(),
+    };
+
+    let join_swap = match CONFIG.load(deps.storage)?.swap_mode {
+        SwapMode::ExactOut => JoinSwap::ExactOut {
+            // Up to max_slippage more than estimated may be swapped in, as far as there is enough of it
+            token_in_max: Coin {
+                denom: token_in.denom,
+                amount: apply_slippage_up(token_in.amount, max_slippage).min(token_provided.amount),
+            },
+            token_out_amount: swap.token_out.amount,
+        },
+        SwapMode::ExactIn => JoinSwap::ExactIn {
+            token_in,
+            // The chain only accepts a positive minimum
+            token_out_min_amount: apply_slippage(swap.token_out.amount, max_slippage)
+                .max(Uint128::one()),
+        },
+    };
+
+    // Create the swap execMsg and store the intermediate state
+    let exec_msg = create_swap_exec_msg_and_store_state(
+        deps,
+        env,
+        sender,
+        pool.id,
+        swap.token_out.denom,
+        token_provided,
+        join_swap,
+        token_out_provided_amount,
+        lower_tick,
+        upper_tick,
+        apply_slippage(position.amount0, max_slippage),
+        apply_slippage(position.amount1, max_slippage),
+        position_target,
+        false,
+    )?;
+
+    Ok(Response::new()
+        .add_event(Event::from(swap_event))
+        .add_submessage(SubMsg::reply_on_success(exec_msg, SWAP_REPLY_ID)))
+}
+
+// parse_msg_exec_response decodes the response of the message at `index` of a MsgExec sent through authz
+pub(crate) fn parse_msg_exec_response<T: prost::Message + Default>(
+    data: Binary,
+    index: usize,
+) -> Result<T, ContractError> {
+    let res: MsgExecResponse = data.try_into()?;
+    let result = res
+        .results
+        .get(index)
+        .ok_or(ContractError::InvalidMsgExecResponse { index })?;
+    T::decode(result.as_slice())
+        .map_err(|e| StdError::parse_err(std::any::type_name::<T>(), e).into())
+}
+
+pub(crate) fn create_position_msg(
+    sender: &Addr,
+    pool_id: u64,
+    lower_tick: i64,
+    upper_tick: i64,
+    tokens_provided: Vec<Coin>,
+    token_min_amount0: Uint128,
+    token_min_amount1: Uint128,
+) -> MsgCreatePosition {
+    MsgCreatePosition {
+        pool_id,
+        sender: sender.to_string(),
+        lower_tick,
+        upper_tick,
+        tokens_provided: tokens_provided.into_iter().map(|c| c.into()).collect(),
+        token_min_amount0: token_min_amount0.to_string(),
+        token_min_amount1: token_min_amount1.to_string(),
+    }
+}
+
+// join_without_swap creates the position from the amounts as they are, for when no swap is needed
+// to fit them to the range. The paramaters to note are:
+// sqrt_price: the current square root price of the pool
+// token_min_amount0 and token_min_amount1: the minimum amounts of the position
+// best_effort: if set, a failing position message doesn't revert the transaction and the join is only recorded
+// once the position was created
+fn join_without_swap(
+    deps: DepsMut,
+    env: &Env,
+    sender: Addr,
+    pool: &Pool,
+    sqrt_price: Decimal256,
+    lower_tick: i64,
+    upper_tick: i64,
+    amount0: Uint128,
+    amount1: Uint128,
+    token_min_amount0: Uint128,
+    token_min_amount1: Uint128,
+    position_target: PositionTarget,
+    best_effort: bool,
+) -> Result<Response, ContractError> {
+    let tokens_provided = position_tokens(vec![
+        Coin {
+            denom: pool.token0.clone(),
+            amount: amount0,
+        },
+        Coin {
+            denom: pool.token1.clone(),
+            amount: amount1,
+        },
+    ]);
+    let position_event = join_position_event(
+        pool,
+        sqrt_price,
+        lower_tick,
+        upper_tick,
+        None,
+        &tokens_provided,
+    )?;
+    let dust = position_event.dust(pool);
+    let position_msg = join_msg(
+        &deps.querier,
+        position_target,
+        &sender,
+        pool.id,
+        lower_tick,
+        upper_tick,
+        tokens_provided.clone(),
+        token_min_amount0,
+        token_min_amount1,
+    )?;
+    let exec_msg: MsgExec = MsgExec {
+        grantee: env.contract.address.to_string(),
+        msgs: vec![position_msg],
+    };
+
+    // A best effort join records itself and reports the position once the position message succeeded
+    if best_effort {
+        JOIN_REPLY_STATES.save(
+            deps.storage,
+            JOIN_REPLY_ID,
+            &JoinMsgReplyState {
+                pool_id: pool.id,
+                original_sender: sender,
+                tokens_provided: tokens_provided.clone(),
+                provided: tokens_provided,
+                swapped: None,
+                dust: dust.to_vec(),
+                position_event: position_event.into(),
+            },
+        )?;
+        return Ok(Response::new().add_submessage(SubMsg::reply_always(exec_msg, JOIN_REPLY_ID)));
+    }
+
+    record_join(deps.storage, pool.id, &tokens_provided, None, &dust)?;
+    Ok(Response::new()
+        .add_event(Event::from(position_event))
+        .add_message(exec_msg))
+}
+
+// join_position_event reports the amounts sent to the position message and the dust the position is
+// expected to leave over at the given sqrt price
+pub(crate) fn join_position_event(
+    pool: &Pool,
+    sqrt_price: Decimal256,
+    lower_tick: i64,
+    upper_tick: i64,
+    token_out: Option<Coin>,
+    tokens_provided: &[Coin],
+) -> Result<JoinPositionEvent, ContractError> {
+    let amount0 = amount_of(tokens_provided, &pool.token0);
+    let amount1 = amount_of(tokens_provided, &pool.token1);
+    let position = simulate_create_position(sqrt_price, lower_tick, upper_tick, amount0, amount1)?;
+    Ok(JoinPositionEvent {
+        pool_id: pool.id,
+        lower_tick,
+        upper_tick,
+        token_out,
+        amount0,
+        amount1,
+        dust0: amount0.checked_sub(position.amount0)?,
+        dust1: amount1.checked_sub(position.amount1)?,
+    })
+}
+
+// The chain only accepts non-zero coins, ordered lexicographically by denom
+pub(crate) fn position_tokens(mut tokens: Vec<Coin>) -> Vec<Coin> {
+    tokens.retain(|c| !c.amount.is_zero());
+    tokens.sort_by(|a, b| a.denom.cmp(&b.denom));
+    tokens
+}
+
+pub(crate) fn validate_max_slippage(max_slippage: Decimal) -> Result<(), ContractError> {
+    if max_slippage > Decimal::one() {
+        return Err(ContractError::InvalidMaxSlippage { max_slippage });
+    }
+    Ok(())
+}
+
+pub(crate) fn apply_slippage(amount: Uint128, max_slippage: Decimal) -> Uint128 {
+    amount * (Decimal::one() - max_slippage)
+}
+
+// apply_slippage_up is the most an amount estimated to be spent may grow to with max_slippage
+pub(crate) fn apply_slippage_up(amount: Uint128, max_slippage: Decimal) -> Uint128 {
+    amount * (Decimal::one() + max_slippage)
+}
+
+fn coin_amount(coin: Option<&OsmosisCoin>) -> Result<Uint128, ContractError> {
+    match coin {
+        Some(coin) => Ok(Uint128::from_str(&coin.amount)?),
+        None => Ok(Uint128::zero()),
+    }
+}
+
+pub(crate) fn amount_of(coins: &[Coin], denom: &str) -> Uint128 {
+    coins
+        .iter()
+        .find(|c| c.denom == denom)
+        .map(|c| c.amount)
+        .unwrap_or_default()
+}
+
+pub(crate) fn check_min_amount(
+    denom: &str,
+    amount: Uint128,
+    min_amount: Uint128,
+) -> Result<(), ContractError> {
+    if amount < min_amount {
+        return Err(ContractError::AmountBelowMinimum {
+            denom: denom.to_string(),
+            amount,
+            min_amount,
+        });
+    }
+    Ok(())
+}
+
+/// How the swap of a join is sent
+pub enum JoinSwap {
+    /// Swap exactly token_in for at least token_out_min_amount
+    ExactIn {
+        token_in: Coin,
+        token_out_min_amount: Uint128,
+    },
+    /// Swap at most token_in_max for exactly token_out_amount
+    ExactOut {
+        token_in_max: Coin,
+        token_out_amount: Uint128,
+    },
+}
+
+pub fn create_swap_exec_msg_and_store_state(
+    deps: DepsMut,
+    env: &Env,
+    sender: Addr,
     pool_id: u64,
     token_out_denom: String,
     token_provided: Coin,
-    refined_token_in: Coin,
+    join_swap: JoinSwap,
+    token_out_provided_amount: Uint128,
     lower_tick: i64,
     upper_tick: i64,
     token_min_amount0: Uint128,
     token_min_amount1: Uint128,
+    position_target: PositionTarget,
+    best_effort: bool,
 ) -> Result<MsgExec, ContractError> {
+    let swap_msg_type_url = match join_swap {
+        JoinSwap::ExactIn { .. } => MsgSwapExactAmountIn::TYPE_URL,
+        JoinSwap::ExactOut { .. } => MsgSwapExactAmountOut::TYPE_URL,
+    };
+    check_swap_grant(deps.as_ref(), env, &sender, swap_msg_type_url)?;
+
     // Create the swap message for the amount calculated above
-    let swap_msg: MsgSwapExactAmountIn = MsgSwapExactAmountIn {
-        sender: sender,
-        routes: vec![SwapAmountInRoute {
-            pool_id: pool_id,
-            token_out_denom: token_out_denom.clone(),
-        }],
-        token_in: Some(refined_token_in.clone().into()),
-        token_out_min_amount: "1".to_string(),
+    let (swap_msg, refined_token_in, exact_token_out_amount) = match join_swap {
+        JoinSwap::ExactIn {
+            token_in,
+            token_out_min_amount,
+        } => (
+            MsgSwapExactAmountIn {
+                sender: sender.to_string(),
+                routes: vec![SwapAmountInRoute {
+                    pool_id,
+                    token_out_denom: token_out_denom.clone(),
+                }],
+                token_in: Some(token_in.clone().into()),
+                token_out_min_amount: token_out_min_amount.to_string(),
+            }
+            .to_any(),
+            token_in,
+            None,
+        ),
+        JoinSwap::ExactOut {
+            token_in_max,
+            token_out_amount,
+        } => (
+            MsgSwapExactAmountOut {
+                sender: sender.to_string(),
+                routes: vec![SwapAmountOutRoute {
+                    pool_id,
+                    token_in_denom: token_in_max.denom.clone(),
+                }],
+                token_in_max_amount: token_in_max.amount.to_string(),
+                token_out: Some(
+                    Coin {
+                        denom: token_out_denom.clone(),
+                        amount: token_out_amount,
+                    }
+                    .into(),
+                ),
+            }
+            .to_any(),
+            token_in_max,
+            Some(token_out_amount),
+        ),
     };
 
     // Execute the swap on behalf of the user
     let exec_msg: MsgExec = MsgExec {
         grantee: env.contract.address.to_string(),
-        msgs: vec![swap_msg.to_any()],
+        msgs: vec![swap_msg],
     };
 
     // Remove the amount of tokens we used from the provided amount and note the remaining amount
@@ -253,18 +1440,23 @@ pub fn create_swap_exec_msg_and_store_state(
         deps.storage,
         SWAP_REPLY_ID,
         &SwapMsgReplyState {
-            pool_id: pool_id,
-            original_sender: info.sender.clone(),
-            lower_tick: lower_tick,
-            upper_tick: upper_tick,
-            token_min_amount0: token_min_amount0,
-            token_min_amount1: token_min_amount1,
-            token_provided_remaining_coin: token_provided_remaining_coin,
-            token_out_denom: token_out_denom,
+            pool_id,
+            original_sender: sender,
+            lower_tick,
+            upper_tick,
+            token_min_amount0,
+            token_min_amount1,
+            token_provided_remaining_coin,
+            token_in: refined_token_in,
+            exact_token_out_amount,
+            token_out_denom,
+            token_out_provided_amount,
+            position_target,
+            best_effort,
         },
     )?;
 
-    return Ok(exec_msg);
+    Ok(exec_msg)
 }
 
 pub fn calc_amount_0_one_unit_liq(
@@ -291,6 +1483,43 @@ pub fn calc_amount_1_one_unit_liq(
     Ok(delta_y)
 }
 
+// calc_swap_from_asset_ratio sizes the swap that brings amount0 and amount1 to the ratio of assets
+// calc_asset_ratio_from_ticks gives for the range at the current tick. The amounts are valued at the price of the
+// current tick, the price impact of the swap and the spread factor are not accounted for. Returns None if no swap
+// is needed.
+pub fn calc_swap_from_asset_ratio(
+    snapshot: &PoolSnapshot,
+    amount0: Uint128,
+    amount1: Uint128,
+    lower_tick: i64,
+    upper_tick: i64,
+) -> Result<Option<Coin>, ContractError> {
+    let (asset0_ratio, asset1_ratio) =
+        calc_asset_ratio_from_ticks(upper_tick, snapshot.current_tick, lower_tick)?;
+    let price = tick_to_price(snapshot.current_tick)?;
+
+    // The ratios are of the amounts per unit of liquidity, so the value of both amounts in token1 is split into
+    // target amounts in that proportion
+    let value = dec_from_uint(amount0)
+        .checked_mul(price)?
+        .checked_add(dec_from_uint(amount1))?;
+    let units = value.checked_div(asset0_ratio.checked_mul(price)?.checked_add(asset1_ratio)?)?;
+    let target_amount0 = uint_from_dec_floor(units.checked_mul(asset0_ratio)?)?;
+    let target_amount1 = uint_from_dec_floor(units.checked_mul(asset1_ratio)?)?;
+
+    let (denom, amount) = if amount0 > target_amount0 {
+        (&snapshot.token0, amount0 - target_amount0)
+    } else if amount1 > target_amount1 {
+        (&snapshot.token1, amount1 - target_amount1)
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(Coin {
+        denom: denom.clone(),
+        amount,
+    }))
+}
+
 pub fn calc_asset_ratio_from_ticks(
     upper_tick: i64,
     current_tick: i64,
@@ -310,345 +1539,3 @@ pub fn calc_asset_ratio_from_ticks(
 
     Ok((asset0_ratio, asset1_ratio))
 }
-
-fn get_next_sqrt_price_from_amount0_in_round_up(
-    liquidity: Decimal256,
-    sqrt_price_current: Decimal256,
-    token_in: Decimal256,
-) -> Decimal256 {
-    let numerator = liquidity * sqrt_price_current;
-    let denominator = liquidity + (token_in * sqrt_price_current);
-    numerator / denominator
-}
-
-fn get_next_sqrt_price_from_amount1_in_round_down(
-    liquidity: Decimal256,
-    sqrt_price_current: Decimal256,
-    token_in: Decimal256,
-) -> Decimal256 {
-    sqrt_price_current + (token_in / liquidity)
-}
-
-fn calc_amount_one_delta(
-    liquidity: Decimal256,
-    mut sqrt_price_current: Decimal256,
-    mut sqrt_price_next: Decimal256,
-    should_round_up: bool,
-) -> Decimal256 {
-    if sqrt_price_next > sqrt_price_current {
-        std::mem::swap(&mut sqrt_price_current, &mut sqrt_price_next);
-    }
-    let result = liquidity * (sqrt_price_current.abs_diff(sqrt_price_next));
-    match should_round_up {
-        true => result.ceil(),
-        false => result,
-    }
-}
-
-fn calc_amount_zero_delta(
-    liquidity: Decimal256,
-    mut sqrt_price_current: Decimal256,
-    mut sqrt_price_next: Decimal256,
-    should_round_up: bool,
-) -> Decimal256 {
-    if sqrt_price_next > sqrt_price_current {
-        std::mem::swap(&mut sqrt_price_current, &mut sqrt_price_next);
-    }
-    let mul1 = liquidity * (sqrt_price_current - sqrt_price_next);
-    let result = mul1 / (sqrt_price_current * sqrt_price_next);
-    match should_round_up {
-        true => result.ceil(),
-        false => result,
-    }
-}
-
-fn iterative_naive_approach(
-    deps: &mut DepsMut,
-    // liquidity: Decimal,
-    // token_out_denom: String,
-    // sqrt_price_current: Decimal,
-    // sqrt_price_next: Decimal,
-    token_to_swap: Coin,
-    token_initially_provided: Coin,
-    pool: Pool,
-    upper_tick: i64,
-    lower_tick: i64,
-) -> Result<Coin, ContractError> {
-    // We are assuming here we have enough liquidity to swap the entire amount of token_in
-    // Will change later
-    let token_0_in = token_to_swap.denom == pool.token0;
-    let token_1_in = token_to_swap.denom == pool.token1;
-    let token_provided_dec =
-        Decimal256::from_str(token_initially_provided.amount.to_string().as_str())?;
-
-    let original = pool.current_sqrt_price.to_string();
-    let parts: Vec<&str> = original.split('.').collect();
-    let integer_part = parts[0];
-    let fractional_part = &parts[1][0..18]; // Take only first 18 digits after the decimal point
-    let rounded = format!("{}.{}", integer_part, fractional_part);
-
-    let pool_cur_sqrt_price = Decimal256::from_str(&rounded).unwrap();
-
-    let spread_factor = Decimal256::from_str(pool.spread_factor.as_str())?;
-
-    // Determine what the next sqrt price will be after the swap
-    let sqrt_price_next: Decimal256;
-    if token_0_in {
-        sqrt_price_next = get_next_sqrt_price_from_amount0_in_round_up(
-            Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-            pool_cur_sqrt_price,
-            Decimal256::from_str(token_to_swap.amount.to_string().as_str()).unwrap(),
-        );
-    } else {
-        sqrt_price_next = get_next_sqrt_price_from_amount1_in_round_down(
-            Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-            pool_cur_sqrt_price,
-            Decimal256::from_str(token_to_swap.amount.to_string().as_str()).unwrap(),
-        );
-    }
-
-    // Transform the sqrt price into a price
-    let price_next = sqrt_price_to_price(sqrt_price_next);
-
-    // Determine the tick this will bring us to
-    let tick_next = price_to_tick(deps.storage, price_next)?;
-    deps.api.debug(&format!("tick_next {tick_next:?}"));
-
-    // Now that we have the tick the swap will bring us to, we can determine the optimal ratio of assets
-    let (asset_0_ratio_new, asset_1_ratio_new) =
-        calc_asset_ratio_from_ticks(upper_tick, tick_next as i64, lower_tick)?;
-    deps.api.debug(&format!(
-        "asset_0_ratio_new {asset_0_ratio_new:?}, asset_1_ratio_new {asset_1_ratio_new:?}"
-    ));
-
-    if token_0_in {
-        let amount_1_delta = calc_amount_one_delta(
-            Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-            pool_cur_sqrt_price,
-            sqrt_price_next,
-            false,
-        );
-
-        let amt0_to_swap = token_provided_dec
-            .checked_mul(asset_1_ratio_new.checked_add(spread_factor)?)?
-            .to_uint_floor();
-        let amt0_for_pos = token_initially_provided
-            .amount
-            .checked_sub(amt0_to_swap.try_into()?);
-        let sqrt_price_next = get_next_sqrt_price_from_amount0_in_round_up(
-            Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-            pool_cur_sqrt_price,
-            Decimal256::from_str(amt0_to_swap.to_string().as_str()).unwrap(),
-        );
-        let price_next = sqrt_price_to_price(sqrt_price_next);
-
-        // Determine the tick this will bring us to
-        let tick_next = price_to_tick(deps.storage, price_next)?;
-        deps.api.debug(&format!("tick_next {tick_next:?}"));
-        deps.api.debug(&format!(
-            "final position pair: amt0 {amt0_for_pos:?}, amt1 {amount_1_delta:?}"
-        ));
-        return Ok(Coin {
-            denom: token_to_swap.denom,
-            amount: amt0_to_swap.try_into()?,
-        });
-    } else if token_1_in {
-        let amount_0_delta = calc_amount_zero_delta(
-            Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-            pool_cur_sqrt_price,
-            sqrt_price_next,
-            true,
-        );
-        deps.api
-            .debug(&format!("amount_0_delta {amount_0_delta:?}"));
-        let amt1_to_swap = token_provided_dec
-            .checked_mul(asset_0_ratio_new.checked_add(spread_factor)?)?
-            .to_uint_floor();
-        let amt1_for_pos = token_initially_provided
-            .amount
-            .checked_sub(amt1_to_swap.try_into()?);
-        let sqrt_price_next = get_next_sqrt_price_from_amount1_in_round_down(
-            Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-            pool_cur_sqrt_price,
-            Decimal256::from_str(amt1_to_swap.to_string().as_str()).unwrap(),
-        );
-        let price_next = sqrt_price_to_price(sqrt_price_next);
-
-        // Determine the tick this will bring us to
-        let tick_next = price_to_tick(deps.storage, price_next)?;
-        deps.api.debug(&format!("tick_next {tick_next:?}"));
-        deps.api.debug(&format!(
-            "final position pair: amt0 {amount_0_delta:?}, amt1 {amt1_for_pos:?}"
-        ));
-        return Ok(Coin {
-            denom: token_to_swap.denom,
-            amount: amt1_to_swap.try_into()?,
-        });
-    } else {
-        return Err(ContractError::DenomNotInPool {
-            provided_denom: token_to_swap.denom,
-        });
-    }
-}
-
-fn sqrt_price_to_price(sqrt_price: Decimal256) -> Decimal256 {
-    return sqrt_price * sqrt_price;
-}
-
-// fn iterative_naive_approach_new(
-//     deps: &mut DepsMut,
-//     // liquidity: Decimal,
-//     // token_out_denom: String,
-//     // sqrt_price_current: Decimal,
-//     // sqrt_price_next: Decimal,
-//     mut token_to_swap: Coin,
-//     token_initially_provided: Coin,
-//     pool: Pool,
-//     upper_tick: i64,
-//     lower_tick: i64,
-//     loop_limit: usize,
-// ) -> Result<Coin, ContractError> {
-//     let token_0_in = token_to_swap.denom == pool.token0;
-//     let token_1_in = token_to_swap.denom == pool.token1;
-//     let token_provided_dec =
-//         Decimal256::from_str(token_initially_provided.amount.to_string().as_str())?;
-//     let threshold = Decimal256::from_str("0.000001")?;
-
-//     let original = pool.current_sqrt_price.to_string();
-//     let parts: Vec<&str> = original.split('.').collect();
-//     let integer_part = parts[0];
-//     let fractional_part = &parts[1][0..18]; // Take only first 18 digits after the decimal point
-//     let rounded = format!("{}.{}", integer_part, fractional_part);
-
-//     let pool_cur_sqrt_price = Decimal256::from_str(&rounded).unwrap();
-
-//     let spread_factor = Decimal256::from_str(pool.spread_factor.as_str())?;
-
-//     let mut loop_counter = 0;
-//     loop {
-//         loop_counter += 1;
-
-//         if loop_counter > loop_limit {
-//             return Ok(Coin {
-//                 denom: token_to_swap.denom,
-//                 amount: token_to_swap.amount,
-//             });
-//         }
-
-//         let sqrt_price_next: Decimal256;
-//         if token_0_in {
-//             sqrt_price_next = get_next_sqrt_price_from_amount0_in_round_up(
-//                 &deps,
-//                 Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-//                 pool_cur_sqrt_price,
-//                 Decimal256::from_str(token_to_swap.amount.to_string().as_str()).unwrap(),
-//             );
-//         } else {
-//             sqrt_price_next = get_next_sqrt_price_from_amount1_in_round_down(
-//                 &deps,
-//                 Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-//                 pool_cur_sqrt_price,
-//                 Decimal256::from_str(token_to_swap.amount.to_string().as_str()).unwrap(),
-//             );
-//         }
-
-//         let price_next = sqrt_price_to_price(sqrt_price_next);
-//         let tick_next = price_to_tick(deps.storage, price_next)?;
-//         deps.api.debug(&format!("tick_next {tick_next:?}"));
-
-//         let (asset_0_ratio_new, asset_1_ratio_new) =
-//             calc_asset_ratio_from_ticks(upper_tick, tick_next as i64, lower_tick)?;
-
-//         let ratio_difference_abs: Decimal256;
-//         if token_0_in {
-//             let amount_1_delta = calc_amount_one_delta(
-//                 Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-//                 pool_cur_sqrt_price,
-//                 sqrt_price_next,
-//                 false,
-//             );
-//             let amt0_to_swap = token_provided_dec
-//                 .checked_mul(asset_1_ratio_new.checked_add(spread_factor)?)?
-//                 .to_uint_floor();
-//             // let amount_1_delta_rounded = amount_1_delta.round_dp(18);
-//             // let amount_1_delta_256 =
-//             //     Decimal256::from_str(&amount_1_delta_rounded.to_string().as_str())?;
-//             let amt0_to_swap_dec = Decimal256::from_str(&amt0_to_swap.to_string().as_str())?;
-
-//             let internal_ratio =
-//                 amount_1_delta.checked_div(amt0_to_swap_dec.checked_add(amount_1_delta)?)?;
-//             // let internal_ratio_dec256 = Decimal256::from_str(&internal_ratio.to_string().as_str())?;
-//             let ratio_diff = internal_ratio.checked_sub(asset_1_ratio_new)?;
-//             ratio_difference_abs = internal_ratio.abs_diff(asset_1_ratio_new);
-
-//             if ratio_difference_abs <= threshold {
-//                 let amt_debug = token_to_swap.amount;
-//                 deps.api.debug(&format!("token_to_swap {amt_debug:?}"));
-//                 deps.api
-//                     .debug(&format!("asset_0_ratio_new {asset_0_ratio_new:?}"));
-//                 deps.api
-//                     .debug(&format!("asset_1_ratio_new {asset_1_ratio_new:?}"));
-//                 return Ok(Coin {
-//                     denom: token_to_swap.denom,
-//                     amount: amt0_to_swap.try_into()?,
-//                 });
-//             }
-//             deps.api
-//                 .debug(&format!("ratio_difference {ratio_difference_abs:?}"));
-//             deps.api
-//                 .debug(&format!("internal_ratio {internal_ratio:?}"));
-//             deps.api
-//                 .debug(&format!("asset_1_ratio_new {asset_1_ratio_new:?}"));
-
-//             if ratio_diff < Decimal256::zero() {
-//                 token_to_swap.amount = token_to_swap.amount.checked_add(Uint128::one())?;
-//             } else {
-//                 token_to_swap.amount = token_to_swap.amount.checked_sub(Uint128::one())?;
-//             }
-//         } else if token_1_in {
-//             let amount_0_delta = calc_amount_zero_delta(
-//                 Decimal256::from_str(pool.current_tick_liquidity.as_str()).unwrap(),
-//                 pool_cur_sqrt_price,
-//                 sqrt_price_next,
-//                 true,
-//             );
-
-//             let amt1_to_swap = token_provided_dec
-//                 .checked_mul(asset_0_ratio_new.checked_add(spread_factor)?)?
-//                 .to_uint_floor();
-//             // let amount_1_delta_rounded = amount_1_delta.round_dp(18);
-//             // let amount_1_delta_256 =
-//             //     Decimal256::from_str(&amount_1_delta_rounded.to_string().as_str())?;
-//             let amt1_to_swap_dec = Decimal256::from_str(&amt1_to_swap.to_string().as_str())?;
-
-//             let internal_ratio =
-//                 amount_0_delta.checked_div(amt1_to_swap_dec.checked_add(amount_0_delta)?)?;
-//             // let internal_ratio_dec256 = Decimal256::from_str(&internal_ratio.to_string().as_str())?;
-//             let ratio_diff = internal_ratio.checked_sub(asset_0_ratio_new)?;
-//             ratio_difference_abs = internal_ratio.abs_diff(asset_0_ratio_new);
-
-//             if ratio_difference_abs <= threshold {
-//                 return Ok(Coin {
-//                     denom: token_to_swap.denom,
-//                     amount: amt1_to_swap.try_into()?,
-//                 });
-//             }
-//             deps.api
-//                 .debug(&format!("ratio_difference {ratio_difference_abs:?}"));
-//             deps.api
-//                 .debug(&format!("internal_ratio {internal_ratio:?}"));
-//             deps.api
-//                 .debug(&format!("asset_0_ratio_new {asset_0_ratio_new:?}"));
-
-//             if ratio_diff < Decimal256::zero() {
-//                 token_to_swap.amount = token_to_swap.amount.checked_add(Uint128::one())?;
-//             } else {
-//                 token_to_swap.amount = token_to_swap.amount.checked_sub(Uint128::one())?;
-//             }
-//         } else {
-//             return Err(ContractError::DenomNotInPool {
-//                 provided_denom: token_to_swap.denom,
-//             });
-//         }
-//     }
-// }
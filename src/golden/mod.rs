@@ -0,0 +1,265 @@
+// Golden vectors of the chain's concentrated liquidity math, captured from OsmosisTestApp by the ignored
+// capture_golden_vectors test in test_tube, which overwrites cl_math.json:
+//   cargo test capture_golden_vectors -- --ignored
+// test_golden_vectors replays every vector against the helpers of cl_sim, tick and execute, any difference to what
+// the chain returned fails the test with the vector that diverged. A fixture without vectors fails it as well.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, Uint128};
+
+use crate::cl_sim::PoolSnapshot;
+
+pub const FIXTURE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/golden/cl_math.json");
+
+#[cw_serde]
+pub struct GoldenVectors {
+    pub swaps: Vec<SwapVector>,
+    pub positions: Vec<PositionVector>,
+    pub ticks: Vec<TickVector>,
+    pub amount_deltas: Vec<AmountDeltaVector>,
+    pub unit_liquidity: Vec<UnitLiquidityVector>,
+}
+
+/// A MsgSwapExactAmountIn against the pool and the state the chain left the pool in
+#[cw_serde]
+pub struct SwapVector {
+    pub pool: PoolSnapshot,
+    pub token_in: Coin,
+    pub token_out: Coin,
+    pub end_tick: i64,
+    /// As returned by the chain, with 36 decimal places
+    pub end_sqrt_price: String,
+}
+
+/// A MsgCreatePosition at the sqrt price and the amounts and liquidity the chain created it with
+#[cw_serde]
+pub struct PositionVector {
+    /// As returned by the chain, with 36 decimal places
+    pub sqrt_price: String,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    pub amount0: Uint128,
+    pub amount1: Uint128,
+    pub used_amount0: Uint128,
+    pub used_amount1: Uint128,
+    pub liquidity_created: String,
+}
+
+/// The spot price a pool was initialized at by its first position and the tick the chain put it at.
+/// The prices are on a tick, so the tick's price is the spot price as well.
+#[cw_serde]
+pub struct TickVector {
+    pub price: String,
+    pub tick: i64,
+}
+
+/// A position on one side of the current price, which holds only one of the tokens. The chain rounds the
+/// amount it takes to create the position up and the amount it reports the position holds down.
+#[cw_serde]
+pub struct AmountDeltaVector {
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    /// As stored by the chain
+    pub liquidity: String,
+    /// Whether the position is above the current price and holds token0, or below it and holds token1
+    pub is_token0: bool,
+    pub amount_round_up: Uint128,
+    pub amount_round_down: Uint128,
+}
+
+/// A position around the current tick of a pool whose price is exactly on that tick and the amounts the chain
+/// took for the liquidity it created, which the amounts of one unit of liquidity are scaled by.
+#[cw_serde]
+pub struct UnitLiquidityVector {
+    pub current_tick: i64,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    pub liquidity_created: String,
+    pub amount0: Uint128,
+    pub amount1: Uint128,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{Decimal256, Uint256};
+
+    use super::*;
+    use crate::cl_sim::{
+        calc_amount_one_delta, calc_amount_zero_delta, dec_from_uint, parse_chain_dec,
+        simulate_create_position, simulate_swap_exact_amount_in, uint_from_dec_floor,
+    };
+    use crate::execute::{
+        calc_amount_0_one_unit_liq, calc_amount_1_one_unit_liq, calc_asset_ratio_from_ticks,
+    };
+    use crate::tick::{
+        calculate_sqrt_price_to_tick, price_to_tick, tick_to_price, tick_to_sqrt_price,
+    };
+
+    // The chain works with 36 decimal places where we have 18, so the last of ours may be rounded differently
+    fn assert_close(actual: Decimal256, expected: Decimal256, vector: &str) {
+        assert_within(actual, expected, Decimal256::new(Uint256::one()), vector);
+    }
+
+    fn assert_within(
+        actual: Decimal256,
+        expected: Decimal256,
+        tolerance: Decimal256,
+        vector: &str,
+    ) {
+        let diff = if actual > expected {
+            actual - expected
+        } else {
+            expected - actual
+        };
+        assert!(
+            diff <= tolerance,
+            "{vector}: {actual} differs from {expected} returned by the chain"
+        );
+    }
+
+    #[test]
+    fn test_golden_vectors() {
+        let fixture = std::fs::read_to_string(FIXTURE_PATH).unwrap();
+        let vectors: GoldenVectors = serde_json_wasm::from_str(&fixture).unwrap();
+        let mut storage = MockStorage::new();
+
+        // Without vectors the test would pass without checking anything
+        assert!(
+            !vectors.swaps.is_empty()
+                && !vectors.positions.is_empty()
+                && !vectors.ticks.is_empty()
+                && !vectors.amount_deltas.is_empty()
+                && !vectors.unit_liquidity.is_empty(),
+            "cl_math.json is missing vectors, capture them with cargo test capture_golden_vectors -- --ignored"
+        );
+
+        for (i, swap) in vectors.swaps.iter().enumerate() {
+            let vector = format!("swap {i}");
+            let simulation =
+                simulate_swap_exact_amount_in(&mut storage, &swap.pool, &swap.token_in).unwrap();
+            assert_eq!(simulation.token_out, swap.token_out, "{vector}");
+            assert_eq!(simulation.end_tick, swap.end_tick, "{vector}");
+
+            let end_sqrt_price = parse_chain_dec(&swap.end_sqrt_price).unwrap();
+            assert_close(simulation.end_sqrt_price, end_sqrt_price, &vector);
+
+            // A swap that stops right at a crossed tick going left leaves the pool one tick below the price
+            let expected_tick = if tick_to_sqrt_price(swap.end_tick + 1).unwrap() == end_sqrt_price
+            {
+                swap.end_tick + 1
+            } else {
+                swap.end_tick
+            };
+            assert_eq!(
+                calculate_sqrt_price_to_tick(&mut storage, end_sqrt_price).unwrap(),
+                expected_tick,
+                "{vector}"
+            );
+        }
+
+        for (i, position) in vectors.positions.iter().enumerate() {
+            let vector = format!("position {i}");
+            let simulation = simulate_create_position(
+                parse_chain_dec(&position.sqrt_price).unwrap(),
+                position.lower_tick,
+                position.upper_tick,
+                position.amount0,
+                position.amount1,
+            )
+            .unwrap();
+            assert_eq!(simulation.amount0, position.used_amount0, "{vector}");
+            assert_eq!(simulation.amount1, position.used_amount1, "{vector}");
+            assert_close(
+                simulation.liquidity,
+                parse_chain_dec(&position.liquidity_created).unwrap(),
+                &vector,
+            );
+        }
+
+        for (i, tick) in vectors.ticks.iter().enumerate() {
+            let vector = format!("tick {i}");
+            let price = Decimal256::from_str(&tick.price).unwrap();
+            assert_eq!(tick_to_price(tick.tick).unwrap(), price, "{vector}");
+            assert_eq!(
+                price_to_tick(&mut storage, price).unwrap(),
+                i128::from(tick.tick),
+                "{vector}"
+            );
+        }
+
+        for (i, delta) in vectors.amount_deltas.iter().enumerate() {
+            let vector = format!("amount delta {i}");
+            let liquidity = parse_chain_dec(&delta.liquidity).unwrap();
+            let sqrt_price_lower = tick_to_sqrt_price(delta.lower_tick).unwrap();
+            let sqrt_price_upper = tick_to_sqrt_price(delta.upper_tick).unwrap();
+            let calc_amount_delta = if delta.is_token0 {
+                calc_amount_zero_delta
+            } else {
+                calc_amount_one_delta
+            };
+            let round_up =
+                calc_amount_delta(liquidity, sqrt_price_lower, sqrt_price_upper, true).unwrap();
+            let round_down =
+                calc_amount_delta(liquidity, sqrt_price_lower, sqrt_price_upper, false).unwrap();
+            assert_eq!(
+                uint_from_dec_floor(round_up).unwrap(),
+                delta.amount_round_up,
+                "{vector}"
+            );
+            assert_eq!(
+                uint_from_dec_floor(round_down).unwrap(),
+                delta.amount_round_down,
+                "{vector}"
+            );
+        }
+
+        for (i, unit) in vectors.unit_liquidity.iter().enumerate() {
+            let vector = format!("unit liquidity {i}");
+            let liquidity = parse_chain_dec(&unit.liquidity_created).unwrap();
+
+            // The chain rounds the amounts it takes up
+            let amount0 = calc_amount_0_one_unit_liq(unit.upper_tick, unit.current_tick)
+                .unwrap()
+                .checked_mul(liquidity)
+                .unwrap();
+            let amount1 = calc_amount_1_one_unit_liq(unit.lower_tick, unit.current_tick)
+                .unwrap()
+                .checked_mul(liquidity)
+                .unwrap();
+            assert_within(
+                amount0,
+                dec_from_uint(unit.amount0),
+                Decimal256::one(),
+                &vector,
+            );
+            assert_within(
+                amount1,
+                dec_from_uint(unit.amount1),
+                Decimal256::one(),
+                &vector,
+            );
+
+            // Rounding each of the amounts by one moves the ratio by at most two over their sum
+            let total = unit.amount0 + unit.amount1;
+            let (asset0_ratio, asset1_ratio) =
+                calc_asset_ratio_from_ticks(unit.upper_tick, unit.current_tick, unit.lower_tick)
+                    .unwrap();
+            let tolerance = Decimal256::from_ratio(2u128, total);
+            assert_within(
+                asset0_ratio,
+                Decimal256::from_ratio(unit.amount0, total),
+                tolerance,
+                &vector,
+            );
+            assert_within(
+                asset1_ratio,
+                Decimal256::from_ratio(unit.amount1, total),
+                tolerance,
+                &vector,
+            );
+        }
+    }
+}
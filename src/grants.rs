@@ -0,0 +1,455 @@
+use cosmwasm_std::{Addr, Deps, Env, QueryRequest, StdResult, Timestamp};
+use osmosis_std::types::cosmos::authz::v1beta1::{
+    GenericAuthorization, MsgGrant, MsgRevoke, QueryGrantsRequest,
+};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    MsgAddToPosition, MsgCollectIncentives, MsgCollectSpreadRewards, MsgCreatePosition,
+    MsgWithdrawPosition,
+};
+use osmosis_std::types::osmosis::gamm::v1beta1::MsgExitPool;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountOut,
+};
+use osmosis_std::types::osmosis::superfluid::MsgCreateFullRangePositionAndSuperfluidDelegate;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::msg::{
+    Flow, GenericAuthorizationJson, GrantJson, MsgGrantJson, MsgRevokeJson, RequiredGrantsResponse,
+};
+use crate::state::{SwapMode, CONFIG};
+use crate::ContractError;
+
+// required_msg_type_urls returns the type urls of the messages the contract sends on behalf of the user in the flow,
+// joins swap with the message of the swap mode
+pub fn required_msg_type_urls(flow: &Flow, swap_mode: &SwapMode) -> Vec<&'static str> {
+    let swap = match swap_mode {
+        SwapMode::ExactIn => MsgSwapExactAmountIn::TYPE_URL,
+        SwapMode::ExactOut => MsgSwapExactAmountOut::TYPE_URL,
+    };
+    match flow {
+        Flow::SingleSidedSwapAndJoin => {
+            vec![swap, MsgCreatePosition::TYPE_URL]
+        }
+        Flow::FullRangeSwapAndJoin { superfluid: false } => {
+            vec![swap, MsgCreatePosition::TYPE_URL]
+        }
+        Flow::FullRangeSwapAndJoin { superfluid: true } => vec![
+            swap,
+            MsgCreateFullRangePositionAndSuperfluidDelegate::TYPE_URL,
+        ],
+        Flow::BalancedJoin => vec![swap, MsgCreatePosition::TYPE_URL],
+        // The aggregate swap of joins into several positions is always exact in
+        Flow::MultiPoolJoin | Flow::LadderJoin => {
+            vec![MsgSwapExactAmountIn::TYPE_URL, MsgCreatePosition::TYPE_URL]
+        }
+        Flow::Rebalance => vec![
+            MsgWithdrawPosition::TYPE_URL,
+            swap,
+            MsgCreatePosition::TYPE_URL,
+        ],
+        Flow::ChangeRange => vec![
+            MsgCollectSpreadRewards::TYPE_URL,
+            MsgCollectIncentives::TYPE_URL,
+            MsgWithdrawPosition::TYPE_URL,
+            swap,
+            MsgCreatePosition::TYPE_URL,
+        ],
+        Flow::MigrateFromGamm => vec![MsgExitPool::TYPE_URL, swap, MsgCreatePosition::TYPE_URL],
+        Flow::Compound => {
+            // The reward swaps are always exact in, the ratio swap follows the swap mode
+            let mut msg_type_urls = vec![
+                MsgCollectSpreadRewards::TYPE_URL,
+                MsgCollectIncentives::TYPE_URL,
+                MsgSwapExactAmountIn::TYPE_URL,
+            ];
+            if swap != MsgSwapExactAmountIn::TYPE_URL {
+                msg_type_urls.push(swap);
+            }
+            msg_type_urls.push(MsgAddToPosition::TYPE_URL);
+            msg_type_urls
+        }
+        Flow::HarvestTo => vec![
+            MsgCollectSpreadRewards::TYPE_URL,
+            MsgCollectIncentives::TYPE_URL,
+            MsgSwapExactAmountIn::TYPE_URL,
+        ],
+    }
+}
+
+pub const GRANTS_QUERY_PATH: &str = "/cosmos.authz.v1beta1.Query/Grants";
+
+/// The response of the authz Grants query in the JSON encoding of the chain. It is decoded with these types
+/// instead of osmosis-std's `QueryGrantsResponse`, whose `Any` can't deserialize authorizations.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QueriedGrants {
+    pub grants: Vec<QueriedGrant>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QueriedGrant {
+    pub authorization: QueriedAuthorization,
+    /// RFC 3339 timestamp, grants without one don't expire
+    pub expiration: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct QueriedAuthorization {
+    #[serde(rename = "@type")]
+    pub type_url: String,
+    /// The message type of generic authorizations
+    #[serde(default)]
+    pub msg: String,
+}
+
+// The expiration suggested for the grants of the RequiredGrants query
+const SUGGESTED_GRANT_DURATION_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+// query_required_grants returns the message types of the flow along with the MsgGrants the granter signs
+// to give them to the contract and the MsgRevokes to take them back
+pub fn query_required_grants(
+    deps: Deps,
+    env: &Env,
+    granter: String,
+    flow: Flow,
+) -> StdResult<RequiredGrantsResponse> {
+    let granter = deps.api.addr_validate(&granter)?.to_string();
+    let grantee = env.contract.address.to_string();
+    let expiration = rfc3339(
+        env.block
+            .time
+            .plus_seconds(SUGGESTED_GRANT_DURATION_SECONDS),
+    );
+    let swap_mode = CONFIG.load(deps.storage)?.swap_mode;
+    let msg_type_urls = required_msg_type_urls(&flow, &swap_mode);
+
+    Ok(RequiredGrantsResponse {
+        msg_grants: msg_type_urls
+            .iter()
+            .map(|msg_type_url| MsgGrantJson {
+                type_url: MsgGrant::TYPE_URL.to_string(),
+                granter: granter.clone(),
+                grantee: grantee.clone(),
+                grant: GrantJson {
+                    authorization: GenericAuthorizationJson {
+                        type_url: GenericAuthorization::TYPE_URL.to_string(),
+                        msg: msg_type_url.to_string(),
+                    },
+                    expiration: expiration.clone(),
+                },
+            })
+            .collect(),
+        msg_revokes: msg_type_urls
+            .iter()
+            .map(|msg_type_url| MsgRevokeJson {
+                type_url: MsgRevoke::TYPE_URL.to_string(),
+                granter: granter.clone(),
+                grantee: grantee.clone(),
+                msg_type_url: msg_type_url.to_string(),
+            })
+            .collect(),
+        msg_type_urls: msg_type_urls.into_iter().map(String::from).collect(),
+    })
+}
+
+// rfc3339 formats the timestamp in UTC to the second, the way the chain encodes timestamps in JSON
+fn rfc3339(timestamp: Timestamp) -> String {
+    let seconds = timestamp.seconds();
+    let days = (seconds / 86400) as i64;
+    let seconds_of_day = seconds % 86400;
+
+    // Civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+// check_grants errors with the first message type of the flow the granter has not granted the contract,
+// so a missing grant fails up front instead of deep inside the MsgExec. The swap messages are left out, a join
+// only swaps if its range needs the other token and the rewards only if they aren't in the target denom yet,
+// so their grant is checked by check_swap_grant once a swap is about to be sent.
+pub(crate) fn check_grants(
+    deps: Deps,
+    env: &Env,
+    granter: &Addr,
+    flow: &Flow,
+) -> Result<(), ContractError> {
+    let swap_mode = CONFIG.load(deps.storage)?.swap_mode;
+    let msg_type_urls: Vec<&str> = required_msg_type_urls(flow, &swap_mode)
+        .into_iter()
+        .filter(|msg_type_url| !is_swap_msg_type_url(msg_type_url))
+        .collect();
+    check_granted(deps, env, granter, &msg_type_urls)
+}
+
+// check_swap_grant errors if the granter has not granted the contract the swap message it is about to send
+pub(crate) fn check_swap_grant(
+    deps: Deps,
+    env: &Env,
+    granter: &Addr,
+    msg_type_url: &str,
+) -> Result<(), ContractError> {
+    check_granted(deps, env, granter, &[msg_type_url])
+}
+
+fn is_swap_msg_type_url(msg_type_url: &str) -> bool {
+    msg_type_url == MsgSwapExactAmountIn::TYPE_URL
+        || msg_type_url == MsgSwapExactAmountOut::TYPE_URL
+}
+
+// check_granted errors with the first of the message types the granter has not granted the contract
+fn check_granted(
+    deps: Deps,
+    env: &Env,
+    granter: &Addr,
+    msg_type_urls: &[&str],
+) -> Result<(), ContractError> {
+    let grants: QueriedGrants = deps.querier.query(&QueryRequest::Stargate {
+        path: GRANTS_QUERY_PATH.to_string(),
+        data: QueryGrantsRequest {
+            granter: granter.to_string(),
+            grantee: env.contract.address.to_string(),
+            msg_type_url: "".to_string(),
+            pagination: None,
+        }
+        .encode_to_vec()
+        .into(),
+    })?;
+    let granted = granted_msg_type_urls(&grants.grants, env.block.time);
+
+    match msg_type_urls
+        .iter()
+        .find(|msg_type_url| !granted.iter().any(|granted| granted == *msg_type_url))
+    {
+        Some(msg_type_url) => Err(ContractError::MissingAuthzGrant {
+            msg_type_url: msg_type_url.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+// granted_msg_type_urls returns the message types of the generic authorizations that have not expired at block_time
+// An expiration that can't be parsed counts as expired
+fn granted_msg_type_urls(grants: &[QueriedGrant], block_time: Timestamp) -> Vec<String> {
+    grants
+        .iter()
+        .filter(|grant| match &grant.expiration {
+            Some(expiration) => parse_rfc3339(expiration)
+                .map_or(false, |expiration| expiration > block_time.seconds()),
+            None => true,
+        })
+        .map(|grant| &grant.authorization)
+        .filter(|authorization| authorization.type_url == GenericAuthorization::TYPE_URL)
+        .map(|authorization| authorization.msg.clone())
+        .collect()
+}
+
+// parse_rfc3339 returns the seconds since the epoch of a UTC timestamp like the chain encodes them in JSON,
+// fractions of a second are dropped
+fn parse_rfc3339(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let time = time.split('.').next()?;
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 from the civil date, the inverse of the conversion in rfc3339
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+
+    use crate::state::Config;
+
+    use super::*;
+
+    fn generic_grant(msg_type_url: &str, expiration: Option<u64>) -> QueriedGrant {
+        QueriedGrant {
+            authorization: QueriedAuthorization {
+                type_url: GenericAuthorization::TYPE_URL.to_string(),
+                msg: msg_type_url.to_string(),
+            },
+            expiration: expiration.map(|seconds| rfc3339(Timestamp::from_seconds(seconds))),
+        }
+    }
+
+    #[test]
+    fn test_granted_msg_type_urls() {
+        let grants = vec![
+            generic_grant(MsgSwapExactAmountIn::TYPE_URL, None),
+            generic_grant(MsgCreatePosition::TYPE_URL, Some(2000)),
+            generic_grant(MsgWithdrawPosition::TYPE_URL, Some(1000)),
+            // Other authorizations than generic ones don't grant any message type
+            QueriedGrant {
+                authorization: QueriedAuthorization {
+                    type_url: "/cosmos.bank.v1beta1.SendAuthorization".to_string(),
+                    msg: "".to_string(),
+                },
+                expiration: None,
+            },
+        ];
+
+        assert_eq!(
+            granted_msg_type_urls(&grants, Timestamp::from_seconds(1000)),
+            vec![MsgSwapExactAmountIn::TYPE_URL, MsgCreatePosition::TYPE_URL]
+        );
+        assert_eq!(
+            granted_msg_type_urls(&grants, Timestamp::from_seconds(2000)),
+            vec![MsgSwapExactAmountIn::TYPE_URL]
+        );
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(Timestamp::from_seconds(0)), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            rfc3339(Timestamp::from_seconds(951782400)),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            rfc3339(Timestamp::from_nanos(1_571_797_419_879_305_533)),
+            "2019-10-23T02:23:39Z"
+        );
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        for seconds in [0, 951782400, 1_571_797_419, 4_102_444_799] {
+            assert_eq!(
+                parse_rfc3339(&rfc3339(Timestamp::from_seconds(seconds))),
+                Some(seconds)
+            );
+        }
+        assert_eq!(
+            parse_rfc3339("2019-10-23T02:23:39.879305533Z"),
+            Some(1_571_797_419)
+        );
+        assert_eq!(parse_rfc3339("2019-10-23 02:23:39"), None);
+        assert_eq!(parse_rfc3339("2019-13-23T02:23:39Z"), None);
+
+        // The chain's JSON of a grant decodes with unknown fields and without a message type
+        let grants: QueriedGrants = serde_json_wasm::from_str(
+            r#"{"grants":[{"authorization":{"@type":"/cosmos.bank.v1beta1.SendAuthorization","spend_limit":[]},"expiration":null}],"pagination":{"next_key":null,"total":"0"}}"#,
+        )
+        .unwrap();
+        assert_eq!(grants.grants[0].authorization.msg, "");
+    }
+
+    #[test]
+    fn test_query_required_grants() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        CONFIG
+            .save(
+                deps.as_mut().storage,
+                &Config {
+                    owner: Addr::unchecked("owner"),
+                    swap_mode: SwapMode::ExactIn,
+                    max_spot_twap_deviation_bps: 100,
+                    twap_window_seconds: 600,
+                },
+            )
+            .unwrap();
+
+        let res = query_required_grants(
+            deps.as_ref(),
+            &env,
+            "granter".to_string(),
+            Flow::SingleSidedSwapAndJoin,
+        )
+        .unwrap();
+        assert_eq!(
+            res.msg_type_urls,
+            vec![MsgSwapExactAmountIn::TYPE_URL, MsgCreatePosition::TYPE_URL]
+        );
+        assert_eq!(res.msg_grants.len(), 2);
+        assert_eq!(res.msg_revokes.len(), 2);
+
+        // mock_env's block time is 2019-10-23T02:23:39Z
+        assert_eq!(
+            res.msg_grants[1],
+            MsgGrantJson {
+                type_url: "/cosmos.authz.v1beta1.MsgGrant".to_string(),
+                granter: "granter".to_string(),
+                grantee: env.contract.address.to_string(),
+                grant: GrantJson {
+                    authorization: GenericAuthorizationJson {
+                        type_url: "/cosmos.authz.v1beta1.GenericAuthorization".to_string(),
+                        msg: "/osmosis.concentratedliquidity.v1beta1.MsgCreatePosition".to_string(),
+                    },
+                    expiration: "2019-11-22T02:23:39Z".to_string(),
+                },
+            }
+        );
+        assert!(serde_json_wasm::to_string(&res.msg_grants[1])
+            .unwrap()
+            .starts_with(r#"{"@type":"/cosmos.authz.v1beta1.MsgGrant","#));
+        assert_eq!(
+            res.msg_revokes[0].msg_type_url,
+            MsgSwapExactAmountIn::TYPE_URL
+        );
+    }
+
+    #[test]
+    fn test_required_msg_type_urls() {
+        assert_eq!(
+            required_msg_type_urls(
+                &Flow::FullRangeSwapAndJoin { superfluid: true },
+                &SwapMode::ExactIn
+            ),
+            vec![
+                MsgSwapExactAmountIn::TYPE_URL,
+                MsgCreateFullRangePositionAndSuperfluidDelegate::TYPE_URL
+            ]
+        );
+        assert_eq!(
+            required_msg_type_urls(&Flow::ChangeRange, &SwapMode::ExactOut),
+            vec![
+                MsgCollectSpreadRewards::TYPE_URL,
+                MsgCollectIncentives::TYPE_URL,
+                MsgWithdrawPosition::TYPE_URL,
+                MsgSwapExactAmountOut::TYPE_URL,
+                MsgCreatePosition::TYPE_URL
+            ]
+        );
+
+        // Reward swaps stay exact in when the ratio swap is exact out
+        assert_eq!(
+            required_msg_type_urls(&Flow::Compound, &SwapMode::ExactOut),
+            vec![
+                MsgCollectSpreadRewards::TYPE_URL,
+                MsgCollectIncentives::TYPE_URL,
+                MsgSwapExactAmountIn::TYPE_URL,
+                MsgSwapExactAmountOut::TYPE_URL,
+                MsgAddToPosition::TYPE_URL
+            ]
+        );
+    }
+}
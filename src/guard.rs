@@ -0,0 +1,235 @@
+use cosmwasm_std::{Decimal256, Deps, DepsMut, Env, MessageInfo, Response, Timestamp, Uint256};
+use osmosis_std::shim::Timestamp as OsmosisTimestamp;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool;
+use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
+
+use crate::cl_sim::parse_chain_dec;
+use crate::msg::ExpectedSqrtPrice;
+use crate::state::{SwapMode, CONFIG};
+use crate::ContractError;
+
+// update_config changes the swap mode and the defaults of the price guards. Only the owner can update the config.
+pub fn update_config(
+    deps: DepsMut,
+    info: &MessageInfo,
+    swap_mode: Option<SwapMode>,
+    max_spot_twap_deviation_bps: Option<u64>,
+    twap_window_seconds: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(swap_mode) = swap_mode {
+        config.swap_mode = swap_mode;
+    }
+    if let Some(max_spot_twap_deviation_bps) = max_spot_twap_deviation_bps {
+        config.max_spot_twap_deviation_bps = max_spot_twap_deviation_bps;
+    }
+    if let Some(twap_window_seconds) = twap_window_seconds {
+        config.twap_window_seconds = validate_twap_window(twap_window_seconds)?;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute("swap_mode", format!("{:?}", config.swap_mode))
+        .add_attribute(
+            "max_spot_twap_deviation_bps",
+            config.max_spot_twap_deviation_bps.to_string(),
+        )
+        .add_attribute(
+            "twap_window_seconds",
+            config.twap_window_seconds.to_string(),
+        ))
+}
+
+// The chain prunes TWAP records older than 48 hours, a longer window can't be queried
+pub const MAX_TWAP_WINDOW_SECONDS: u64 = 48 * 60 * 60;
+
+pub(crate) fn validate_twap_window(twap_window_seconds: u64) -> Result<u64, ContractError> {
+    if twap_window_seconds == 0 || twap_window_seconds > MAX_TWAP_WINDOW_SECONDS {
+        return Err(ContractError::InvalidTwapWindow {
+            twap_window_seconds,
+            max_twap_window_seconds: MAX_TWAP_WINDOW_SECONDS,
+        });
+    }
+    Ok(twap_window_seconds)
+}
+
+// check_spot_twap_deviation errors if the spot price of the pool deviates from its arithmetic TWAP over the
+// configured window by more than max_spot_twap_deviation_bps, which defaults to the one in the config.
+// The solver sizes the swap at the spot price, which can be moved within the same block.
+// A pool without price history over the whole window, like one created more recently, can't be checked and the
+// join is refused with NoTwapHistory rather than letting the swap through unchecked.
+pub(crate) fn check_spot_twap_deviation(
+    deps: Deps,
+    env: &Env,
+    pool: &Pool,
+    max_spot_twap_deviation_bps: Option<u64>,
+) -> Result<(), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let max_deviation_bps =
+        max_spot_twap_deviation_bps.unwrap_or(config.max_spot_twap_deviation_bps);
+
+    let start_time = env
+        .block
+        .time
+        .minus_seconds(config.twap_window_seconds)
+        .seconds();
+    let twap = TwapQuerier::new(&deps.querier)
+        .arithmetic_twap_to_now(
+            pool.id,
+            pool.token0.clone(),
+            pool.token1.clone(),
+            Some(OsmosisTimestamp {
+                seconds: start_time as i64,
+                nanos: 0,
+            }),
+        )
+        .map_err(|err| ContractError::NoTwapHistory {
+            pool_id: pool.id,
+            twap_window_seconds: config.twap_window_seconds,
+            reason: err.to_string(),
+        })?;
+    let twap = parse_chain_dec(&twap.arithmetic_twap)?;
+
+    // The price of the pool is the amount of token1 per token0
+    let sqrt_price = parse_chain_dec(&pool.current_sqrt_price)?;
+    let spot_price = sqrt_price.checked_mul(sqrt_price)?;
+
+    if exceeds_deviation(spot_price, twap, max_deviation_bps)? {
+        return Err(ContractError::SpotPriceDeviatesFromTwap {
+            spot_price,
+            twap,
+            max_deviation_bps,
+        });
+    }
+    Ok(())
+}
+
+// check_deadline errors if the block time is past the deadline of the message
+pub(crate) fn check_deadline(env: &Env, deadline: Option<Timestamp>) -> Result<(), ContractError> {
+    match deadline {
+        Some(deadline) if env.block.time > deadline => Err(ContractError::DeadlineExceeded {
+            deadline,
+            block_time: env.block.time,
+        }),
+        _ => Ok(()),
+    }
+}
+
+// check_expected_sqrt_price errors if the sqrt price of the pool moved away from the expected one by more than its tolerance
+pub(crate) fn check_expected_sqrt_price(
+    pool: &Pool,
+    expected_sqrt_price: Option<ExpectedSqrtPrice>,
+) -> Result<(), ContractError> {
+    let expected = match expected_sqrt_price {
+        Some(expected) => expected,
+        None => return Ok(()),
+    };
+    let sqrt_price = parse_chain_dec(&pool.current_sqrt_price)?;
+    if exceeds_deviation(sqrt_price, expected.sqrt_price, expected.tolerance_bps)? {
+        return Err(ContractError::SqrtPriceMoved {
+            sqrt_price,
+            expected_sqrt_price: expected.sqrt_price,
+            tolerance_bps: expected.tolerance_bps,
+        });
+    }
+    Ok(())
+}
+
+fn exceeds_deviation(
+    spot_price: Decimal256,
+    twap: Decimal256,
+    max_deviation_bps: u64,
+) -> Result<bool, ContractError> {
+    let deviation = if spot_price > twap {
+        spot_price - twap
+    } else {
+        twap - spot_price
+    };
+    let max_deviation = twap.checked_mul(Decimal256::from_ratio(
+        Uint256::from(max_deviation_bps),
+        Uint256::from(10_000u128),
+    ))?;
+    Ok(deviation > max_deviation)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use cosmwasm_std::testing::mock_env;
+
+    use super::*;
+
+    #[test]
+    fn test_exceeds_deviation() {
+        let twap = Decimal256::from_str("2").unwrap();
+
+        // 1% from the TWAP in either direction is allowed with 100 bps
+        for spot_price in ["2.02", "1.98"] {
+            let spot_price = Decimal256::from_str(spot_price).unwrap();
+            assert!(!exceeds_deviation(spot_price, twap, 100).unwrap());
+        }
+        for spot_price in ["2.0201", "1.9799"] {
+            let spot_price = Decimal256::from_str(spot_price).unwrap();
+            assert!(exceeds_deviation(spot_price, twap, 100).unwrap());
+        }
+
+        // Without any allowed deviation only the TWAP itself passes
+        assert!(!exceeds_deviation(twap, twap, 0).unwrap());
+        assert!(exceeds_deviation(Decimal256::from_str("2.000001").unwrap(), twap, 0).unwrap());
+    }
+
+    #[test]
+    fn test_validate_twap_window() {
+        assert_eq!(validate_twap_window(1).unwrap(), 1);
+        assert_eq!(
+            validate_twap_window(MAX_TWAP_WINDOW_SECONDS).unwrap(),
+            MAX_TWAP_WINDOW_SECONDS
+        );
+        for twap_window_seconds in [0, MAX_TWAP_WINDOW_SECONDS + 1] {
+            assert!(matches!(
+                validate_twap_window(twap_window_seconds),
+                Err(ContractError::InvalidTwapWindow { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_check_deadline() {
+        let env = mock_env();
+
+        assert!(check_deadline(&env, None).is_ok());
+        assert!(check_deadline(&env, Some(env.block.time)).is_ok());
+        assert!(check_deadline(&env, Some(env.block.time.plus_seconds(1))).is_ok());
+        assert!(matches!(
+            check_deadline(&env, Some(env.block.time.minus_seconds(1))),
+            Err(ContractError::DeadlineExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_expected_sqrt_price() {
+        let pool = Pool {
+            current_sqrt_price: "1.010000000000000000000000000000000000".to_string(),
+            ..Default::default()
+        };
+        let expected = |sqrt_price: &str, tolerance_bps: u64| {
+            Some(ExpectedSqrtPrice {
+                sqrt_price: Decimal256::from_str(sqrt_price).unwrap(),
+                tolerance_bps,
+            })
+        };
+
+        assert!(check_expected_sqrt_price(&pool, None).is_ok());
+        assert!(check_expected_sqrt_price(&pool, expected("1", 100)).is_ok());
+        assert!(matches!(
+            check_expected_sqrt_price(&pool, expected("1", 99)),
+            Err(ContractError::SqrtPriceMoved { .. })
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+use cosmwasm_std::{
+    Coin, Decimal, Deps, DepsMut, Env, MemoryStorage, MessageInfo, Response, StdError, StdResult,
+    Storage, Uint128,
+};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool;
+
+use crate::cl_sim::{simulate_create_position, simulate_swap_exact_amount_in};
+use crate::execute::query_cl_pool;
+use crate::msg::{LadderBucket, LadderShape, LadderSimulation, PoolAllocation, RangeSpec};
+use crate::multi_pool::{join_pools, size_legs, split_pro_rata};
+use crate::range::resolve_range;
+use crate::tick::{MAX_TICK, MIN_INITIALIZED_TICK};
+use crate::ContractError;
+
+// The most buckets a ladder may have, every bucket is a position of its own
+pub const MAX_LADDER_BUCKETS: u64 = 20;
+
+// ladder_join spreads the provided token over adjacent ranges of a pool, shaped by the weights of the buckets
+// The paramaters to note are:
+// - center: The range of the middle bucket, every bucket is as wide as it
+// - shape: How the provided token is distributed over the buckets
+// - buckets: The number of buckets, each of them becomes a position
+// - max_slippage: How far below their simulated amounts the swap output and the position amounts may be.
+pub fn ladder_join(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    pool_id: u64,
+    center: RangeSpec,
+    shape: LadderShape,
+    buckets: u64,
+    token_provided: Coin,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    let pool = query_cl_pool(&deps.querier, pool_id)?;
+    let pools = ladder_allocations(deps.storage, &pool, &center, &shape, buckets)?;
+
+    // The buckets are joined like pools of a multi pool join that all are the same pool
+    let response = join_pools(env, info, deps, pools, token_provided, max_slippage)?;
+    Ok(response.add_attribute("action", "ladder_join"))
+}
+
+// query_simulate_ladder_join runs the calculation of a ladder join against the current state of the pool and
+// simulates the aggregate swap and the position of every bucket
+pub fn query_simulate_ladder_join(
+    deps: Deps,
+    pool_id: u64,
+    center: RangeSpec,
+    shape: LadderShape,
+    buckets: u64,
+    token_provided: Coin,
+) -> StdResult<LadderSimulation> {
+    // Queries can't write to the contract's storage, the tick conversions cache their data in memory instead
+    let mut storage = MemoryStorage::new();
+    simulate_ladder_join(
+        deps,
+        &mut storage,
+        pool_id,
+        &center,
+        &shape,
+        buckets,
+        token_provided,
+    )
+    .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+fn simulate_ladder_join(
+    deps: Deps,
+    storage: &mut dyn Storage,
+    pool_id: u64,
+    center: &RangeSpec,
+    shape: &LadderShape,
+    buckets: u64,
+    token_provided: Coin,
+) -> Result<LadderSimulation, ContractError> {
+    let pool = query_cl_pool(&deps.querier, pool_id)?;
+    let allocations = ladder_allocations(storage, &pool, center, shape, buckets)?;
+
+    // Split the provided token and size the swap of every bucket like the join does, against the swap of all
+    // of them
+    let sized = size_legs(storage, &deps.querier, &allocations, &token_provided)?;
+    let token_in_amounts: Vec<Uint128> = sized.legs.iter().map(|leg| leg.token_in_amount).collect();
+
+    let token_in = Coin {
+        denom: token_provided.denom.clone(),
+        amount: token_in_amounts
+            .iter()
+            .try_fold(Uint128::zero(), |total, amount| total.checked_add(*amount))?,
+    };
+    // All buckets are in the same pool, so it is the one the swap goes through
+    let snapshot = sized.deepest_snapshot;
+    let (expected_token_out, sqrt_price) = if token_in.amount.is_zero() {
+        (
+            Coin {
+                denom: sized.token_out_denom,
+                amount: Uint128::zero(),
+            },
+            snapshot.current_sqrt_price,
+        )
+    } else {
+        let swap = simulate_swap_exact_amount_in(storage, &snapshot, &token_in)?;
+        (swap.token_out, swap.end_sqrt_price)
+    };
+    let token_out_amounts = split_pro_rata(expected_token_out.amount, &token_in_amounts)?;
+
+    let mut ladder_buckets = vec![];
+    for ((allocation, leg), token_out_amount) in
+        allocations.iter().zip(&sized.legs).zip(token_out_amounts)
+    {
+        let (amount0, amount1) = if token_provided.denom == pool.token0 {
+            (leg.token_provided_remaining, token_out_amount)
+        } else {
+            (token_out_amount, leg.token_provided_remaining)
+        };
+        let position =
+            simulate_create_position(sqrt_price, leg.lower_tick, leg.upper_tick, amount0, amount1)?;
+        ladder_buckets.push(LadderBucket {
+            lower_tick: leg.lower_tick,
+            upper_tick: leg.upper_tick,
+            weight: allocation.weight,
+            amount0: position.amount0,
+            amount1: position.amount1,
+            liquidity: position.liquidity,
+        });
+    }
+
+    Ok(LadderSimulation {
+        token_in,
+        expected_token_out,
+        buckets: ladder_buckets,
+    })
+}
+
+// ladder_allocations resolves the center range against the pool and returns the range and weight of every bucket
+fn ladder_allocations(
+    storage: &mut dyn Storage,
+    pool: &Pool,
+    center: &RangeSpec,
+    shape: &LadderShape,
+    buckets: u64,
+) -> Result<Vec<PoolAllocation>, ContractError> {
+    let weights = ladder_weights(shape, buckets)?;
+    let (lower_tick, upper_tick) =
+        resolve_range(storage, center, pool.current_tick, pool.tick_spacing)?;
+    let ranges = ladder_ranges(lower_tick, upper_tick, buckets)?;
+    Ok(ranges
+        .into_iter()
+        .zip(weights)
+        .map(|((lower, upper), weight)| PoolAllocation {
+            pool_id: pool.id,
+            range: RangeSpec::Ticks { lower, upper },
+            weight,
+        })
+        .collect())
+}
+
+// ladder_weights returns the weight of every bucket of a ladder of the given shape
+pub fn ladder_weights(shape: &LadderShape, buckets: u64) -> Result<Vec<u64>, ContractError> {
+    if buckets == 0 || buckets > MAX_LADDER_BUCKETS {
+        return Err(ContractError::InvalidLadderBuckets {
+            buckets,
+            max_buckets: MAX_LADDER_BUCKETS,
+        });
+    }
+
+    let weights = match shape {
+        LadderShape::Uniform => vec![1; buckets as usize],
+        // 1 at the outermost buckets, growing by 2 per bucket towards the middle
+        LadderShape::Linear => (0..buckets)
+            .map(|index| buckets - (2 * index).abs_diff(buckets - 1))
+            .collect(),
+        // The binomial coefficients of buckets - 1, C(n, k) = C(n, k - 1) * (n - k + 1) / k
+        LadderShape::Gaussian => {
+            let mut weights = vec![1u64];
+            for k in 1..buckets {
+                weights.push(weights[k as usize - 1] * (buckets - k) / k);
+            }
+            weights
+        }
+    };
+    Ok(weights)
+}
+
+// ladder_ranges returns the adjacent tick ranges of the buckets, each as wide as the center range and with the
+// center range in the middle. Ranges aligned to the tick spacing stay aligned.
+pub fn ladder_ranges(
+    lower_tick: i64,
+    upper_tick: i64,
+    buckets: u64,
+) -> Result<Vec<(i64, i64)>, ContractError> {
+    let width = upper_tick - lower_tick;
+    let buckets = i64::try_from(buckets).map_err(|_| ContractError::Overflow {})?;
+    let ladder_lower_tick = ((buckets - 1) / 2)
+        .checked_mul(width)
+        .and_then(|below| lower_tick.checked_sub(below))
+        .ok_or(ContractError::Overflow {})?;
+    let ladder_upper_tick = buckets
+        .checked_mul(width)
+        .and_then(|ladder_width| ladder_lower_tick.checked_add(ladder_width))
+        .ok_or(ContractError::Overflow {})?;
+    if ladder_lower_tick < MIN_INITIALIZED_TICK || ladder_upper_tick > MAX_TICK as i64 {
+        return Err(ContractError::InvalidTickRange {
+            lower_tick: ladder_lower_tick,
+            upper_tick: ladder_upper_tick,
+        });
+    }
+
+    Ok((0..buckets)
+        .map(|index| {
+            let lower = ladder_lower_tick + index * width;
+            (lower, lower + width)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ladder_weights() {
+        assert_eq!(
+            ladder_weights(&LadderShape::Uniform, 3).unwrap(),
+            vec![1, 1, 1]
+        );
+        assert_eq!(
+            ladder_weights(&LadderShape::Linear, 5).unwrap(),
+            vec![1, 3, 5, 3, 1]
+        );
+        assert_eq!(
+            ladder_weights(&LadderShape::Linear, 4).unwrap(),
+            vec![1, 3, 3, 1]
+        );
+        assert_eq!(
+            ladder_weights(&LadderShape::Gaussian, 5).unwrap(),
+            vec![1, 4, 6, 4, 1]
+        );
+        assert_eq!(ladder_weights(&LadderShape::Gaussian, 1).unwrap(), vec![1]);
+
+        for buckets in [0, MAX_LADDER_BUCKETS + 1] {
+            assert!(matches!(
+                ladder_weights(&LadderShape::Uniform, buckets),
+                Err(ContractError::InvalidLadderBuckets { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ladder_ranges() {
+        assert_eq!(
+            ladder_ranges(-100, 100, 3).unwrap(),
+            vec![(-300, -100), (-100, 100), (100, 300)]
+        );
+        // With an even number the center range is the lower of the two middle buckets
+        assert_eq!(
+            ladder_ranges(-100, 100, 2).unwrap(),
+            vec![(-100, 100), (100, 300)]
+        );
+        assert!(matches!(
+            ladder_ranges(MIN_INITIALIZED_TICK, 0, 3),
+            Err(ContractError::InvalidTickRange { .. })
+        ));
+    }
+}
@@ -1,11 +1,29 @@
+// The entry points and join helpers take the fields of their messages as arguments
+#![allow(clippy::too_many_arguments)]
+
+pub mod cl_sim;
 pub mod contract;
 mod error;
+pub mod events;
 pub mod execute;
+pub mod grants;
+pub mod guard;
+pub mod ladder;
+pub mod migrate;
 pub mod msg;
+pub mod multi_pool;
+pub mod range;
+pub mod rewards;
+pub mod solver;
 pub mod state;
+pub mod stats;
 pub mod tick;
 
 pub use crate::error::ContractError;
 
+#[cfg(test)]
+mod golden;
+#[cfg(test)]
+mod multitest;
 #[cfg(test)]
 mod test_tube;
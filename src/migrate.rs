@@ -0,0 +1,134 @@
+use cosmwasm_std::{
+    Coin, Decimal, DepsMut, Env, MessageInfo, Reply, Response, SubMsg, SubMsgResponse,
+    SubMsgResult, Uint128,
+};
+use osmosis_std::types::cosmos::authz::v1beta1::MsgExec;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as OsmosisCoin;
+use osmosis_std::types::osmosis::gamm::v1beta1::{GammQuerier, MsgExitPool, MsgExitPoolResponse};
+
+use crate::cl_sim::query_pool_snapshot;
+use crate::contract::EXIT_POOL_REPLY_ID;
+use crate::execute::{
+    amount_of, apply_slippage, parse_msg_exec_response, query_cl_pool, swap_to_ratio_and_join,
+    validate_max_slippage,
+};
+use crate::msg::RangeSpec;
+use crate::range::resolve_range;
+use crate::rewards::sum_coins;
+use crate::state::{ExitPoolMsgReplyState, PositionTarget, EXIT_POOL_REPLY_STATES};
+use crate::ContractError;
+
+// migrate_from_gamm exits a GAMM pool and joins the exit assets into a position of a CL pool of the same pair
+// The paramaters to note are:
+// - gamm_pool_id: The id of the GAMM pool to exit, its assets must be the two tokens of the CL pool
+// - shares: The amount of GAMM pool shares to exit with
+// - cl_pool_id: The id of the CL pool to join
+// - range: The desired tick range of the new position
+// - max_slippage: How far below their estimated amounts the exit assets, the swap output and the new position amounts may be.
+pub fn migrate_from_gamm(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    gamm_pool_id: u64,
+    shares: Uint128,
+    cl_pool_id: u64,
+    range: RangeSpec,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    validate_max_slippage(max_slippage)?;
+
+    // Resolve the tick range of the new position against the current state of the CL pool
+    let pool = query_cl_pool(&deps.querier, cl_pool_id)?;
+    let (lower_tick, upper_tick) =
+        resolve_range(deps.storage, &range, pool.current_tick, pool.tick_spacing)?;
+
+    // Estimate the exit assets, the GAMM pool must not hold anything the CL position can't take
+    let exit_estimate = GammQuerier::new(&deps.querier)
+        .calc_exit_pool_coins_from_shares(gamm_pool_id, shares.to_string())?
+        .tokens_out;
+    if exit_estimate
+        .iter()
+        .any(|coin| coin.denom != pool.token0 && coin.denom != pool.token1)
+    {
+        return Err(ContractError::GammPoolTokensMismatch {
+            gamm_pool_id,
+            cl_pool_id,
+        });
+    }
+    let token_out_mins = sum_coins(exit_estimate.iter())?
+        .into_iter()
+        .map(|coin| {
+            OsmosisCoin::from(Coin {
+                denom: coin.denom,
+                amount: apply_slippage(coin.amount, max_slippage),
+            })
+        })
+        .collect();
+
+    // Exit the GAMM pool on behalf of the user
+    let exec_msg: MsgExec = MsgExec {
+        grantee: env.contract.address.to_string(),
+        msgs: vec![MsgExitPool {
+            sender: info.sender.to_string(),
+            pool_id: gamm_pool_id,
+            share_in_amount: shares.to_string(),
+            token_out_mins,
+        }
+        .to_any()],
+    };
+
+    // Save intermediate state
+    // We will utilize this state after the GAMM pool has been exited
+    EXIT_POOL_REPLY_STATES.save(
+        deps.storage,
+        EXIT_POOL_REPLY_ID,
+        &ExitPoolMsgReplyState {
+            pool_id: cl_pool_id,
+            original_sender: info.sender.clone(),
+            lower_tick,
+            upper_tick,
+            max_slippage,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate_from_gamm")
+        .add_attribute("gamm_pool_id", gamm_pool_id.to_string())
+        .add_attribute("shares", shares)
+        .add_submessage(SubMsg::reply_on_success(exec_msg, EXIT_POOL_REPLY_ID)))
+}
+
+// handle_exit_pool_reply is called after the GAMM pool has been exited successfully
+// This function swaps the exit assets to the ratio of the range and creates the CL position
+pub fn handle_exit_pool_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+    exit_pool_msg_reply_state: ExitPoolMsgReplyState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        // Parse the exit response, the chain already checked the exit assets against their minimums
+        let res: MsgExitPoolResponse = parse_msg_exec_response(b, 0)?;
+        let exit_assets = sum_coins(res.token_out.iter())?;
+
+        let pool = query_cl_pool(&deps.querier, exit_pool_msg_reply_state.pool_id)?;
+        let snapshot = query_pool_snapshot(&deps.querier, &pool)?;
+        return swap_to_ratio_and_join(
+            deps,
+            &env,
+            exit_pool_msg_reply_state.original_sender,
+            &pool,
+            &snapshot,
+            exit_pool_msg_reply_state.lower_tick,
+            exit_pool_msg_reply_state.upper_tick,
+            amount_of(&exit_assets, &pool.token0),
+            amount_of(&exit_assets, &pool.token1),
+            exit_pool_msg_reply_state.max_slippage,
+            PositionTarget::NewPosition,
+        );
+    }
+
+    Err(ContractError::FailedExitPool {
+        reason: msg.result.unwrap_err(),
+    })
+}
@@ -1,10 +1,17 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Uint128};
+use cosmwasm_std::{Coin, Decimal, Decimal256, Timestamp, Uint128};
+
+use crate::state::{JoinStats, SwapMode, SwapRouteHop};
 
 /// Message type for `instantiate` entry_point
 #[cw_serde]
 pub struct InstantiateMsg {
     pub owner: String,
+    pub swap_mode: SwapMode,
+    /// The default of how far in basis points the spot price may deviate from the TWAP when joining
+    pub max_spot_twap_deviation_bps: u64,
+    /// The window the TWAP is taken over, at most 48 hours as the chain prunes older TWAP records
+    pub twap_window_seconds: u64,
 }
 
 /// Message type for `execute` entry_point
@@ -13,34 +20,293 @@ pub enum ExecuteMsg {
     /// Execute a swap and forward it to the receiver address on the specified ibc channel
     SingleSidedSwapAndJoin {
         pool_id: u64,
-        lower_tick: i64,
-        upper_tick: i64,
+        range: RangeSpec,
+        token_provided: Coin,
+        token_min_amount0: Uint128,
+        token_min_amount1: Uint128,
+        /// Overrides the default of the config
+        max_spot_twap_deviation_bps: Option<u64>,
+        /// Refuse to join if the pool's sqrt price moved away from the expected one
+        expected_sqrt_price: Option<ExpectedSqrtPrice>,
+        /// Refuse to join if the message is executed after this block time
+        deadline: Option<Timestamp>,
+        /// If the position can't be created after the swap, keep the swap and leave the tokens with the
+        /// user instead of reverting the join. A `join_failed` event reports the failure.
+        best_effort: Option<bool>,
+    },
+    /// Swap part of the provided token and create a full range position with the result, optionally
+    /// superfluid delegating the position to `superfluid_validator` (OSMO pairs only)
+    FullRangeSwapAndJoin {
+        pool_id: u64,
         token_provided: Coin,
         token_min_amount0: Uint128,
         token_min_amount1: Uint128,
+        superfluid_validator: Option<String>,
+        /// Overrides the default of the config
+        max_spot_twap_deviation_bps: Option<u64>,
+        /// If the position can't be created after the swap, keep the swap and leave the tokens with the
+        /// user instead of reverting the join. A `join_failed` event reports the failure.
+        best_effort: Option<bool>,
+    },
+    /// Create a position from one or both tokens of the pool in any ratio. The part of the token in excess
+    /// for the range is swapped for the other one. The swap output and the position amounts may be at most
+    /// `max_slippage` below their simulated values.
+    BalancedJoin {
+        pool_id: u64,
+        range: RangeSpec,
+        tokens_provided: Vec<Coin>,
+        max_slippage: Decimal,
+    },
+    /// Split the provided token across several CL pools of the same pair by weight and create a position
+    /// in each. The swap to the ratio of all ranges goes through the deepest of the pools and is always exact
+    /// in. The swap output and the position amounts may be at most `max_slippage` below their simulated values.
+    MultiPoolJoin {
+        pools: Vec<PoolAllocation>,
+        token_provided: Coin,
+        max_slippage: Decimal,
+    },
+    /// Spread the provided token over `buckets` adjacent ranges of a pool, each as wide as `center`, with the
+    /// share of each bucket following `shape`. The center range is the middle bucket, or the lower of the two
+    /// middle buckets for an even number. The swap for all buckets is exact in and done at once through the
+    /// pool, and the swap output and the position amounts may be at most `max_slippage` below their simulated
+    /// values.
+    LadderJoin {
+        pool_id: u64,
+        center: RangeSpec,
+        shape: LadderShape,
+        buckets: u64,
+        token_provided: Coin,
+        max_slippage: Decimal,
+    },
+    /// Withdraw a position and create a new one around the current price with the withdrawn assets,
+    /// swapping them to the ratio of the new range. `new_range` defaults to the width of the old range.
+    /// The withdrawn amounts, the swap output and the new position amounts may be at most `max_slippage`
+    /// below their simulated values. The spread rewards and incentives the old position earned are paid to the
+    /// owner by the withdraw and are not moved into the new position.
+    Rebalance {
+        position_id: u64,
+        new_range: Option<RangeSpec>,
+        max_slippage: Decimal,
+    },
+    /// Move a position to a new tick range in one transaction. The position is withdrawn together with its
+    /// spread rewards and incentives, swapped to the ratio of the new range and joined into a new position.
+    /// Incentives in denoms other than the pool's tokens are left in the owner's wallet. The withdrawn
+    /// amounts, the swap output and the new position amounts may be at most `max_slippage` below their
+    /// simulated values.
+    ChangeRange {
+        position_id: u64,
+        new_lower_tick: i64,
+        new_upper_tick: i64,
+        max_slippage: Decimal,
+    },
+    /// Exit `shares` of a GAMM pool and join the exit assets into a position of a CL pool of the same pair.
+    /// The exit assets, the swap output and the new position amounts may be at most `max_slippage` below
+    /// their estimated values.
+    MigrateFromGamm {
+        gamm_pool_id: u64,
+        shares: Uint128,
+        cl_pool_id: u64,
+        range: RangeSpec,
+        max_slippage: Decimal,
+    },
+    /// Collect the spread rewards and incentives of a position and add them back to it. Rewards in other
+    /// denoms than the pool's tokens are swapped through the configured swap routes. The reward swaps,
+    /// the ratio swap and the added amounts may be at most `max_slippage` below their estimated values.
+    Compound {
+        position_id: u64,
+        max_slippage: Decimal,
+    },
+    /// Collect the spread rewards and incentives of several positions and swap them into `target_denom`
+    /// through the configured swap routes. Fails if the user receives less than `min_out` of it in total.
+    HarvestTo {
+        position_ids: Vec<u64>,
+        target_denom: String,
+        min_out: Uint128,
+    },
+    /// Update the defaults of the price guards. Only callable by the owner.
+    UpdateConfig {
+        swap_mode: Option<SwapMode>,
+        max_spot_twap_deviation_bps: Option<u64>,
+        twap_window_seconds: Option<u64>,
+    },
+    /// Set the route rewards of `token_in_denom` are swapped through to get `token_out_denom`. An empty
+    /// route removes it. Only callable by the owner.
+    SetSwapRoute {
+        token_in_denom: String,
+        token_out_denom: String,
+        route: Vec<SwapRouteHop>,
     },
 }
 
+/// The tick range of a position, resolved against the pool's current tick and tick spacing at execution time
+#[cw_serde]
+pub enum RangeSpec {
+    /// Explicit lower and upper tick
+    Ticks { lower: i64, upper: i64 },
+    /// From `down_bps` basis points below to `up_bps` basis points above the current price
+    PercentAroundCurrent { down_bps: u64, up_bps: u64 },
+    /// The tick spacing the current tick is in, plus `n_below` tick spacings below and `n_above` tick spacings above it
+    SpacingsAroundCurrent { n_below: u64, n_above: u64 },
+    /// From the min to the max tick
+    FullRange,
+}
+
+/// A pool of a multi pool join with the range of its position and its share of the provided token
+#[cw_serde]
+pub struct PoolAllocation {
+    pub pool_id: u64,
+    pub range: RangeSpec,
+    /// The share of the provided token relative to the weights of the other pools
+    pub weight: u64,
+}
+
+/// How the provided token of a ladder join is distributed over its buckets
+#[cw_serde]
+pub enum LadderShape {
+    /// The same share for every bucket
+    Uniform,
+    /// Shares falling linearly from the middle to the outermost buckets
+    Linear,
+    /// Shares following the binomial coefficients, a discrete bell curve around the middle
+    Gaussian,
+}
+
 /// Message type for `migrate` entry_point
 #[cw_serde]
 pub enum MigrateMsg {}
 
+/// The sqrt price a join was signed against and how far in basis points the pool may have moved from it
+#[cw_serde]
+pub struct ExpectedSqrtPrice {
+    pub sqrt_price: Decimal256,
+    pub tolerance_bps: u64,
+}
+
+impl ExecuteMsg {
+    /// The flow the message executes on behalf of the sender, if any
+    pub fn flow(&self) -> Option<Flow> {
+        match self {
+            ExecuteMsg::SingleSidedSwapAndJoin { .. } => Some(Flow::SingleSidedSwapAndJoin),
+            ExecuteMsg::FullRangeSwapAndJoin {
+                superfluid_validator,
+                ..
+            } => Some(Flow::FullRangeSwapAndJoin {
+                superfluid: superfluid_validator.is_some(),
+            }),
+            ExecuteMsg::BalancedJoin { .. } => Some(Flow::BalancedJoin),
+            ExecuteMsg::MultiPoolJoin { .. } => Some(Flow::MultiPoolJoin),
+            ExecuteMsg::LadderJoin { .. } => Some(Flow::LadderJoin),
+            ExecuteMsg::Rebalance { .. } => Some(Flow::Rebalance),
+            ExecuteMsg::ChangeRange { .. } => Some(Flow::ChangeRange),
+            ExecuteMsg::MigrateFromGamm { .. } => Some(Flow::MigrateFromGamm),
+            ExecuteMsg::Compound { .. } => Some(Flow::Compound),
+            ExecuteMsg::HarvestTo { .. } => Some(Flow::HarvestTo),
+            ExecuteMsg::UpdateConfig { .. } | ExecuteMsg::SetSwapRoute { .. } => None,
+        }
+    }
+}
+
+/// A flow the contract executes through authz on behalf of the user
+#[cw_serde]
+pub enum Flow {
+    SingleSidedSwapAndJoin,
+    FullRangeSwapAndJoin { superfluid: bool },
+    BalancedJoin,
+    MultiPoolJoin,
+    LadderJoin,
+    Rebalance,
+    ChangeRange,
+    MigrateFromGamm,
+    Compound,
+    HarvestTo,
+}
+
 /// Message type for `query` entry_point
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
-    // This example query variant indicates that any client can query the contract
-    // using `YourQuery` and it will return `YourQueryResponse`
-    // This `returns` information will be included in contract's schema
-    // which is used for client code generation.
-    //
-    // #[returns(YourQueryResponse)]
-    // YourQuery {},
-}
-
-// We define a custom struct for each query response
-// #[cw_serde]
-// pub struct YourQueryResponse {}
+    /// The message types the granter has to grant the contract through authz for the flow, with the
+    /// `MsgGrant`s to sign for them and the `MsgRevoke`s to clean up afterwards
+    #[returns(RequiredGrantsResponse)]
+    RequiredGrants { granter: String, flow: Flow },
+    /// Statistics of the joins into the pool
+    #[returns(JoinStats)]
+    PoolStats { pool_id: u64 },
+    /// Statistics of the joins into all pools
+    #[returns(JoinStats)]
+    GlobalStats {},
+    /// The buckets of a ladder join against the current state of the pool and the positions they are
+    /// expected to create
+    #[returns(LadderSimulation)]
+    SimulateLadderJoin {
+        pool_id: u64,
+        center: RangeSpec,
+        shape: LadderShape,
+        buckets: u64,
+        token_provided: Coin,
+    },
+}
+
+#[cw_serde]
+pub struct LadderSimulation {
+    /// The swap of all buckets together
+    pub token_in: Coin,
+    pub expected_token_out: Coin,
+    pub buckets: Vec<LadderBucket>,
+}
+
+/// A bucket of a ladder join and the position it is expected to create
+#[cw_serde]
+pub struct LadderBucket {
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    pub weight: u64,
+    /// The amounts the position takes, what it leaves over is not part of them
+    pub amount0: Uint128,
+    pub amount1: Uint128,
+    pub liquidity: Decimal256,
+}
+
+#[cw_serde]
+pub struct RequiredGrantsResponse {
+    pub msg_type_urls: Vec<String>,
+    pub msg_grants: Vec<MsgGrantJson>,
+    pub msg_revokes: Vec<MsgRevokeJson>,
+}
+
+/// A `MsgGrant` of a `GenericAuthorization` in the JSON encoding of the chain
+#[cw_serde]
+pub struct MsgGrantJson {
+    #[serde(rename = "@type")]
+    pub type_url: String,
+    pub granter: String,
+    pub grantee: String,
+    pub grant: GrantJson,
+}
+
+#[cw_serde]
+pub struct GrantJson {
+    pub authorization: GenericAuthorizationJson,
+    /// RFC 3339 timestamp
+    pub expiration: String,
+}
+
+#[cw_serde]
+pub struct GenericAuthorizationJson {
+    #[serde(rename = "@type")]
+    pub type_url: String,
+    pub msg: String,
+}
+
+/// A `MsgRevoke` in the JSON encoding of the chain
+#[cw_serde]
+pub struct MsgRevokeJson {
+    #[serde(rename = "@type")]
+    pub type_url: String,
+    pub granter: String,
+    pub grantee: String,
+    pub msg_type_url: String,
+}
 
 // Response for Swap
 #[cw_serde]
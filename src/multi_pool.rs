@@ -0,0 +1,532 @@
+use cosmwasm_std::{
+    Coin, Decimal, Decimal256, DepsMut, Env, Event, MessageInfo, QuerierWrapper, Reply, Response,
+    Storage, SubMsg, SubMsgResponse, SubMsgResult, Uint128,
+};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::authz::v1beta1::MsgExec;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+};
+use std::str::FromStr;
+
+use crate::cl_sim::{
+    calc_amount_one_delta, calc_amount_zero_delta, dec_from_uint, parse_chain_dec,
+    query_pool_snapshot, simulate_create_position, simulate_swap_exact_amount_in,
+    uint_from_dec_floor, PoolSnapshot,
+};
+use crate::contract::MULTI_POOL_SWAP_REPLY_ID;
+use crate::execute::{
+    amount_of, apply_slippage, create_position_msg, join_position_event, parse_msg_exec_response,
+    position_tokens, query_cl_pool, validate_max_slippage,
+};
+use crate::grants::check_swap_grant;
+use crate::guard::check_spot_twap_deviation;
+use crate::msg::PoolAllocation;
+use crate::range::resolve_range;
+use crate::state::{MultiPoolJoinState, MultiPoolLeg, MULTI_POOL_REPLY_STATES};
+use crate::stats::record_join;
+use crate::tick::tick_to_sqrt_price;
+use crate::ContractError;
+
+// multi_pool_join splits the provided token across several CL pools of the same pair and creates a position in each
+// The paramaters to note are:
+// - pools: The pools to join, each with the desired tick range of its position and its weight of the provided token
+// - token_provided: The token to split across the pools, one of the tokens of the pair
+// - max_slippage: How far below their simulated amounts the swap output and the position amounts may be.
+// All the shares are swapped together through the pool with the most liquidity at its current tick to reduce the
+// price impact, and the swap of each share is sized against that swap.
+pub fn multi_pool_join(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    pools: Vec<PoolAllocation>,
+    token_provided: Coin,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    let response = join_pools(env, info, deps, pools, token_provided, max_slippage)?;
+    Ok(response.add_attribute("action", "multi_pool_join"))
+}
+
+// join_pools creates a position for each of the allocations, which may also be several ranges of the same pool,
+// with their share of the provided token and one swap for all of them
+pub(crate) fn join_pools(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    pools: Vec<PoolAllocation>,
+    token_provided: Coin,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    validate_max_slippage(max_slippage)?;
+    let sized = size_legs(deps.storage, &deps.querier, &pools, &token_provided)?;
+    let token_in_amount = sized.legs.iter().try_fold(Uint128::zero(), |total, leg| {
+        total.checked_add(leg.token_in_amount)
+    })?;
+
+    let state = MultiPoolJoinState {
+        original_sender: info.sender.clone(),
+        token_in: Coin {
+            denom: token_provided.denom,
+            amount: token_in_amount,
+        },
+        token_out_denom: sized.token_out_denom,
+        max_slippage,
+        legs: sized.legs,
+    };
+
+    if state.token_in.amount.is_zero() {
+        // None of the ranges needs the other token, create the positions straight away
+        let (position_msgs, position_events) =
+            join_legs(deps.storage, &deps.querier, &state, Uint128::zero())?;
+        let exec_msg: MsgExec = MsgExec {
+            grantee: env.contract.address.to_string(),
+            msgs: position_msgs,
+        };
+        return Ok(Response::new()
+            .add_events(position_events)
+            .add_message(exec_msg));
+    }
+
+    check_swap_grant(
+        deps.as_ref(),
+        env,
+        &info.sender,
+        MsgSwapExactAmountIn::TYPE_URL,
+    )?;
+
+    // The swap is sized at the spot prices, so the pool it goes through must not have been moved away from the TWAP
+    let swap_pool = sized.deepest_pool;
+    check_spot_twap_deviation(deps.as_ref(), env, &swap_pool, None)?;
+    let swap =
+        simulate_swap_exact_amount_in(deps.storage, &sized.deepest_snapshot, &state.token_in)?;
+
+    // Execute the swap on behalf of the user
+    let exec_msg: MsgExec = MsgExec {
+        grantee: env.contract.address.to_string(),
+        msgs: vec![MsgSwapExactAmountIn {
+            sender: info.sender.to_string(),
+            routes: vec![SwapAmountInRoute {
+                pool_id: swap_pool.id,
+                token_out_denom: state.token_out_denom.clone(),
+            }],
+            token_in: Some(state.token_in.clone().into()),
+            // The chain only accepts a positive minimum
+            token_out_min_amount: apply_slippage(swap.token_out.amount, max_slippage)
+                .max(Uint128::one())
+                .to_string(),
+        }
+        .to_any()],
+    };
+
+    // Save intermediate state
+    // We will utilize this state after the swap has been executed
+    MULTI_POOL_REPLY_STATES.save(deps.storage, MULTI_POOL_SWAP_REPLY_ID, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("swap_pool_id", swap_pool.id.to_string())
+        .add_attribute("token_in", state.token_in.to_string())
+        .add_submessage(SubMsg::reply_on_success(exec_msg, MULTI_POOL_SWAP_REPLY_ID)))
+}
+
+// The legs of a multi pool join with the swap of each share sized against the swap of all of them
+pub(crate) struct SizedLegs {
+    pub legs: Vec<MultiPoolLeg>,
+    /// The other token of the pair, which the shares are swapped for
+    pub token_out_denom: String,
+    /// The pool with the most liquidity at its current tick, which the swap goes through
+    pub deepest_pool: Pool,
+    pub deepest_snapshot: PoolSnapshot,
+}
+
+// A share of the provided token with the range of its position resolved against its pool
+struct LegRange {
+    pool_id: u64,
+    lower_tick: i64,
+    upper_tick: i64,
+    amount: Uint128,
+    /// The sqrt price of the pool before the swap
+    sqrt_price: Decimal256,
+    sqrt_price_lower: Decimal256,
+    sqrt_price_upper: Decimal256,
+}
+
+// size_legs splits the provided token across the allocations by weight, resolves the range of each against its pool
+// and determines how much of its share has to be swapped for the other token of the pair.
+// All shares are swapped together through the deepest pool, so the swap is sized jointly: the total is the largest
+// amount that the legs still need at the rate and the end price of swapping that total.
+// Joins and their simulations share it so that they size the swaps the same way.
+pub(crate) fn size_legs(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    pools: &[PoolAllocation],
+    token_provided: &Coin,
+) -> Result<SizedLegs, ContractError> {
+    let first_pool = match pools.first() {
+        Some(allocation) => query_cl_pool(querier, allocation.pool_id)?,
+        None => return Err(ContractError::NoPoolsProvided {}),
+    };
+    let token_out_denom = if token_provided.denom == first_pool.token0 {
+        first_pool.token1.clone()
+    } else if token_provided.denom == first_pool.token1 {
+        first_pool.token0.clone()
+    } else {
+        return Err(ContractError::DenomNotInPool {
+            provided_denom: token_provided.denom.clone(),
+        });
+    };
+    if token_provided.amount.is_zero() {
+        return Err(ContractError::NoTokensProvided {});
+    }
+    let weights: Vec<Uint128> = pools
+        .iter()
+        .map(|allocation| Uint128::from(allocation.weight))
+        .collect();
+    let amounts = split_pro_rata(token_provided.amount, &weights)?;
+
+    let mut leg_ranges = vec![];
+    let mut deepest: Option<(Pool, PoolSnapshot)> = None;
+    for (allocation, amount) in pools.iter().zip(amounts) {
+        let pool = query_cl_pool(querier, allocation.pool_id)?;
+        if pool.token0 != first_pool.token0 || pool.token1 != first_pool.token1 {
+            return Err(ContractError::PoolPairMismatch {
+                pool_id: pool.id,
+                other_pool_id: first_pool.id,
+            });
+        }
+
+        // Resolve the tick range of the position against the current state of its pool
+        let (lower_tick, upper_tick) = resolve_range(
+            storage,
+            &allocation.range,
+            pool.current_tick,
+            pool.tick_spacing,
+        )?;
+        let snapshot = query_pool_snapshot(querier, &pool)?;
+        leg_ranges.push(LegRange {
+            pool_id: pool.id,
+            lower_tick,
+            upper_tick,
+            amount,
+            sqrt_price: snapshot.current_sqrt_price,
+            sqrt_price_lower: tick_to_sqrt_price(lower_tick)?,
+            sqrt_price_upper: tick_to_sqrt_price(upper_tick)?,
+        });
+
+        let deeper = match &deepest {
+            Some((_, deepest_snapshot)) => {
+                snapshot.current_tick_liquidity > deepest_snapshot.current_tick_liquidity
+            }
+            None => true,
+        };
+        if deeper {
+            deepest = Some((pool, snapshot));
+        }
+    }
+    let (deepest_pool, deepest_snapshot) = deepest.ok_or(ContractError::NoPoolsProvided {})?;
+
+    let token_in_amounts = size_joint_swap(
+        storage,
+        deepest_pool.id,
+        &deepest_snapshot,
+        &leg_ranges,
+        &token_provided.denom,
+    )?;
+    let legs = leg_ranges
+        .iter()
+        .zip(token_in_amounts)
+        .map(|(leg, token_in_amount)| {
+            Ok(MultiPoolLeg {
+                pool_id: leg.pool_id,
+                lower_tick: leg.lower_tick,
+                upper_tick: leg.upper_tick,
+                token_provided_remaining: leg.amount.checked_sub(token_in_amount)?,
+                token_in_amount,
+            })
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+
+    Ok(SizedLegs {
+        legs,
+        token_out_denom,
+        deepest_pool,
+        deepest_snapshot,
+    })
+}
+
+// size_joint_swap finds by bisection the largest total swap through the deepest pool that the legs still need
+// all of, and returns how much of it each leg swaps
+fn size_joint_swap(
+    storage: &mut dyn Storage,
+    deepest_pool_id: u64,
+    deepest_snapshot: &PoolSnapshot,
+    legs: &[LegRange],
+    token_in_denom: &str,
+) -> Result<Vec<Uint128>, ContractError> {
+    let no_swap = vec![Uint128::zero(); legs.len()];
+    let total = legs
+        .iter()
+        .try_fold(Uint128::zero(), |total, leg| total.checked_add(leg.amount))?;
+
+    let mut low = (Uint128::zero(), no_swap.clone());
+    let mut high = total.checked_add(Uint128::one())?;
+    while high - low.0 > Uint128::one() {
+        let mid = low.0 + (high - low.0) / Uint128::from(2u128);
+        let token_in_amounts = legs_token_in(
+            storage,
+            deepest_pool_id,
+            deepest_snapshot,
+            legs,
+            token_in_denom,
+            mid,
+        )?;
+        match token_in_amounts {
+            // The legs need at least as much as is swapped, so the swap does not overshoot
+            Some(token_in_amounts)
+                if token_in_amounts
+                    .iter()
+                    .try_fold(Uint128::zero(), |total, amount| total.checked_add(*amount))?
+                    >= mid =>
+            {
+                low = (mid, token_in_amounts)
+            }
+            _ => high = mid,
+        }
+    }
+    Ok(low.1)
+}
+
+// legs_token_in returns how much of its share each leg has to swap if the given total is swapped through the
+// deepest pool, or None if the pool cannot fill the swap.
+// Every leg gets the effective rate of the whole swap and its position is sized at the end price of the deepest
+// pool if it is in that pool and at the price of its own pool otherwise.
+fn legs_token_in(
+    storage: &mut dyn Storage,
+    deepest_pool_id: u64,
+    deepest_snapshot: &PoolSnapshot,
+    legs: &[LegRange],
+    token_in_denom: &str,
+    total: Uint128,
+) -> Result<Option<Vec<Uint128>>, ContractError> {
+    let swap = match simulate_swap_exact_amount_in(
+        storage,
+        deepest_snapshot,
+        &Coin {
+            denom: token_in_denom.to_string(),
+            amount: total,
+        },
+    ) {
+        Ok(swap) => swap,
+        Err(ContractError::NotEnoughLiquidityForSwap {}) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let rate = Decimal256::from_ratio(swap.token_out.amount, total);
+    let zero_for_one = token_in_denom == deepest_snapshot.token0;
+
+    let mut token_in_amounts = Vec::with_capacity(legs.len());
+    for leg in legs {
+        let sqrt_price = if leg.pool_id == deepest_pool_id {
+            swap.end_sqrt_price
+        } else {
+            leg.sqrt_price
+        }
+        .clamp(leg.sqrt_price_lower, leg.sqrt_price_upper);
+
+        // The amounts of a unit of liquidity of the position
+        let amount0 =
+            calc_amount_zero_delta(Decimal256::one(), sqrt_price, leg.sqrt_price_upper, false)?;
+        let amount1 =
+            calc_amount_one_delta(Decimal256::one(), leg.sqrt_price_lower, sqrt_price, false)?;
+
+        // Swapping x of the share at the rate leaves the token in and the token out at the ratio of the position:
+        // (amount - x) / amount0 = x * rate / amount1 for token0 in
+        let (needed, kept) = if zero_for_one {
+            (amount1, amount0)
+        } else {
+            (amount0, amount1)
+        };
+        let token_in_amount = if needed.is_zero() {
+            Uint128::zero()
+        } else {
+            uint_from_dec_floor(
+                dec_from_uint(leg.amount)
+                    .checked_mul(needed)?
+                    .checked_div(needed.checked_add(kept.checked_mul(rate)?)?)?,
+            )?
+            .min(leg.amount)
+        };
+        token_in_amounts.push(token_in_amount);
+    }
+    Ok(Some(token_in_amounts))
+}
+
+// handle_multi_pool_swap_reply is called after the swap through the deepest pool has been executed successfully
+// This function splits the swap output across the pools and creates their positions
+pub fn handle_multi_pool_swap_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+    multi_pool_state: MultiPoolJoinState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        let res: MsgSwapExactAmountInResponse = parse_msg_exec_response(b, 0)?;
+        let token_out_amount = Uint128::from_str(&res.token_out_amount)?;
+
+        let (position_msgs, position_events) = join_legs(
+            deps.storage,
+            &deps.querier,
+            &multi_pool_state,
+            token_out_amount,
+        )?;
+
+        // Execute the position messages on behalf of the user
+        let exec_msg: MsgExec = MsgExec {
+            grantee: env.contract.address.to_string(),
+            msgs: position_msgs,
+        };
+        return Ok(Response::new()
+            .add_events(position_events)
+            .add_message(exec_msg));
+    }
+
+    Err(ContractError::FailedSwap {
+        reason: msg.result.unwrap_err(),
+    })
+}
+
+// join_legs creates the position of each pool with its share of the swap output and records the joins.
+// Every share was sized at the rate of the whole swap, so the output is split in proportion to what each swapped in.
+fn join_legs(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    state: &MultiPoolJoinState,
+    token_out_amount: Uint128,
+) -> Result<(Vec<Any>, Vec<Event>), ContractError> {
+    let token_in_amounts: Vec<Uint128> = state.legs.iter().map(|leg| leg.token_in_amount).collect();
+    let token_out_amounts = split_pro_rata(token_out_amount, &token_in_amounts)?;
+
+    let mut position_msgs = vec![];
+    let mut position_events = vec![];
+    for (leg, leg_token_out_amount) in state.legs.iter().zip(token_out_amounts) {
+        let token_out = Coin {
+            denom: state.token_out_denom.clone(),
+            amount: leg_token_out_amount,
+        };
+        let tokens_provided = position_tokens(vec![
+            Coin {
+                denom: state.token_in.denom.clone(),
+                amount: leg.token_provided_remaining,
+            },
+            token_out.clone(),
+        ]);
+        if tokens_provided.is_empty() {
+            // The weight of the pool was too small to get any of the provided token
+            continue;
+        }
+
+        // The swap moved the deepest pool, the positions are simulated at the current price of their pool
+        let pool = query_cl_pool(querier, leg.pool_id)?;
+        let sqrt_price = parse_chain_dec(&pool.current_sqrt_price)?;
+        let position = simulate_create_position(
+            sqrt_price,
+            leg.lower_tick,
+            leg.upper_tick,
+            amount_of(&tokens_provided, &pool.token0),
+            amount_of(&tokens_provided, &pool.token1),
+        )?;
+        let position_event = join_position_event(
+            &pool,
+            sqrt_price,
+            leg.lower_tick,
+            leg.upper_tick,
+            (!token_out.amount.is_zero()).then_some(token_out),
+            &tokens_provided,
+        )?;
+
+        // What the pool's share was before the swap
+        let provided = vec![Coin {
+            denom: state.token_in.denom.clone(),
+            amount: leg
+                .token_provided_remaining
+                .checked_add(leg.token_in_amount)?,
+        }];
+        let swapped = Coin {
+            denom: state.token_in.denom.clone(),
+            amount: leg.token_in_amount,
+        };
+        record_join(
+            storage,
+            pool.id,
+            &provided,
+            Some(&swapped),
+            &position_event.dust(&pool),
+        )?;
+
+        position_msgs.push(
+            create_position_msg(
+                &state.original_sender,
+                pool.id,
+                leg.lower_tick,
+                leg.upper_tick,
+                tokens_provided,
+                apply_slippage(position.amount0, state.max_slippage),
+                apply_slippage(position.amount1, state.max_slippage),
+            )
+            .to_any(),
+        );
+        position_events.push(Event::from(position_event));
+    }
+    Ok((position_msgs, position_events))
+}
+
+// split_pro_rata splits the amount in proportion to the shares, the last non-zero share gets what the rounding leaves
+pub(crate) fn split_pro_rata(
+    amount: Uint128,
+    shares: &[Uint128],
+) -> Result<Vec<Uint128>, ContractError> {
+    if amount.is_zero() {
+        return Ok(vec![Uint128::zero(); shares.len()]);
+    }
+    let total = shares
+        .iter()
+        .try_fold(Uint128::zero(), |total, share| total.checked_add(*share))?;
+    let last_index = match shares.iter().rposition(|share| !share.is_zero()) {
+        Some(last_index) => last_index,
+        None => return Err(ContractError::InvalidPoolWeights {}),
+    };
+
+    let mut amount_left = amount;
+    let mut amounts = Vec::with_capacity(shares.len());
+    for (index, share) in shares.iter().enumerate() {
+        let share_amount = if index == last_index {
+            amount_left
+        } else {
+            amount.checked_multiply_ratio(*share, total)?
+        };
+        amount_left = amount_left.checked_sub(share_amount)?;
+        amounts.push(share_amount);
+    }
+    Ok(amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_pro_rata() {
+        let shares = [Uint128::new(3), Uint128::new(1), Uint128::zero()];
+        assert_eq!(
+            split_pro_rata(Uint128::new(1001), &shares).unwrap(),
+            vec![Uint128::new(750), Uint128::new(251), Uint128::zero()]
+        );
+        assert_eq!(
+            split_pro_rata(Uint128::zero(), &shares).unwrap(),
+            vec![Uint128::zero(); 3]
+        );
+        assert!(matches!(
+            split_pro_rata(Uint128::new(1), &[Uint128::zero()]),
+            Err(ContractError::InvalidPoolWeights {})
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+mod osmosis;
+mod tests;
+
+pub use osmosis::{MockPool, OsmosisStargate};
+
+use cosmwasm_std::testing::{MockApi, MockStorage};
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Decimal256, Empty, Uint128};
+use cw_multi_test::{
+    App, AppBuilder, BankKeeper, ContractWrapper, DistributionKeeper, Executor, FailingModule,
+    GovFailingModule, IbcFailingModule, StakeKeeper, WasmKeeper,
+};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::authz::v1beta1::{GenericAuthorization, Grant, MsgGrant};
+use prost::Message;
+
+use crate::contract::{execute, instantiate, query, reply};
+use crate::msg::InstantiateMsg;
+use crate::state::SwapMode;
+
+pub const UFOO: &str = "ufoo";
+pub const UBAR: &str = "ubar";
+
+pub type OsmosisApp = App<
+    BankKeeper,
+    MockApi,
+    MockStorage,
+    FailingModule<Empty, Empty, Empty>,
+    WasmKeeper<Empty, Empty>,
+    StakeKeeper,
+    DistributionKeeper,
+    IbcFailingModule,
+    GovFailingModule,
+    OsmosisStargate,
+>;
+
+struct TestEnv {
+    pub app: OsmosisApp,
+    pub admin: Addr,
+    pub alice: Addr,
+    pub pool: MockPool,
+    pub contract_addr: Addr,
+}
+
+impl TestEnv {
+    // setup creates a UFOO/UBAR pool at a price of 1 and deploys the contract, alice holds 1_000_000 of both tokens
+    fn setup() -> TestEnv {
+        let admin = Addr::unchecked("admin");
+        let alice = Addr::unchecked("alice");
+
+        let mut pool = None;
+        let mut app =
+            AppBuilder::new()
+                .with_stargate(OsmosisStargate)
+                .build(|router, _api, storage| {
+                    let created = OsmosisStargate::create_pool(
+                        storage,
+                        UFOO,
+                        UBAR,
+                        100,
+                        Decimal256::permille(1),
+                        Uint128::new(100_000_000),
+                    )
+                    .unwrap();
+                    router
+                        .bank
+                        .init_balance(
+                            storage,
+                            &created.address,
+                            vec![Coin::new(100_000_000, UFOO), Coin::new(100_000_000, UBAR)],
+                        )
+                        .unwrap();
+                    router
+                        .bank
+                        .init_balance(
+                            storage,
+                            &alice,
+                            vec![Coin::new(1_000_000, UFOO), Coin::new(1_000_000, UBAR)],
+                        )
+                        .unwrap();
+                    pool = Some(created);
+                });
+
+        let code_id = app.store_code(Box::new(
+            ContractWrapper::new(execute, instantiate, query).with_reply(reply),
+        ));
+        let contract_addr = app
+            .instantiate_contract(
+                code_id,
+                admin.clone(),
+                &InstantiateMsg {
+                    owner: admin.to_string(),
+                    swap_mode: SwapMode::ExactIn,
+                    max_spot_twap_deviation_bps: 500,
+                    twap_window_seconds: 60,
+                },
+                &[],
+                "single_sided_lp_cl",
+                None,
+            )
+            .unwrap();
+
+        TestEnv {
+            app,
+            admin,
+            alice,
+            pool: pool.unwrap(),
+            contract_addr,
+        }
+    }
+
+    // grant gives the contract generic authz grants of the granter for the message types
+    fn grant(&mut self, granter: &Addr, msg_type_urls: &[&str]) {
+        for msg_type_url in msg_type_urls {
+            let msg = MsgGrant {
+                granter: granter.to_string(),
+                grantee: self.contract_addr.to_string(),
+                grant: Some(Grant {
+                    authorization: Some(Any {
+                        type_url: GenericAuthorization::TYPE_URL.to_string(),
+                        value: GenericAuthorization {
+                            msg: msg_type_url.to_string(),
+                        }
+                        .encode_to_vec(),
+                    }),
+                    expiration: None,
+                }),
+            };
+            self.app
+                .execute(granter.clone(), CosmosMsg::from(msg))
+                .unwrap();
+        }
+    }
+
+    fn balance(&self, address: &Addr, denom: &str) -> Uint128 {
+        self.app
+            .wrap()
+            .query_balance(address, denom)
+            .unwrap()
+            .amount
+    }
+}
@@ -0,0 +1,708 @@
+use std::str::FromStr;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::MockStorage;
+use cosmwasm_std::{
+    to_json_binary, Addr, Api, BankMsg, Binary, BlockInfo, Coin, CosmosMsg, CustomMsg, CustomQuery,
+    Decimal256, Empty, Order, Querier, Storage, Uint128,
+};
+use cw_multi_test::error::{anyhow, bail, AnyResult};
+use cw_multi_test::{AppResponse, CosmosRouter, Module, Stargate, StargateMsg, StargateQuery};
+use cw_storage_plus::{Item, Map};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::authz::v1beta1::{
+    GenericAuthorization, MsgExec, MsgExecResponse, MsgGrant, MsgRevoke, QueryGrantsRequest,
+};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    LiquidityNetInDirectionRequest, LiquidityNetInDirectionResponse, MsgCreatePosition,
+    MsgCreatePositionResponse, Pool, TickLiquidityNet,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    EstimateSwapExactAmountInRequest, EstimateSwapExactAmountInResponse, MsgSwapExactAmountIn,
+    MsgSwapExactAmountInResponse, MsgSwapExactAmountOut, MsgSwapExactAmountOutResponse,
+    PoolRequest, PoolResponse,
+};
+use osmosis_std::types::osmosis::twap::v1beta1::{
+    ArithmeticTwapToNowRequest, ArithmeticTwapToNowResponse,
+};
+use prost::Message;
+use serde::de::DeserializeOwned;
+
+use crate::cl_sim::{
+    simulate_create_position, simulate_swap_exact_amount_in, simulate_swap_exact_amount_out,
+    InitializedTick, PoolSnapshot,
+};
+use crate::grants::{QueriedAuthorization, QueriedGrant, QueriedGrants, GRANTS_QUERY_PATH};
+use crate::tick::{MAX_TICK, MIN_INITIALIZED_TICK};
+
+// The stargate query paths the contract uses through the osmosis-std queriers
+const POOL_PATH: &str = "/osmosis.poolmanager.v1beta1.Query/Pool";
+const ESTIMATE_SWAP_EXACT_AMOUNT_IN_PATH: &str =
+    "/osmosis.poolmanager.v1beta1.Query/EstimateSwapExactAmountIn";
+const LIQUIDITY_NET_IN_DIRECTION_PATH: &str =
+    "/osmosis.concentratedliquidity.v1beta1.Query/LiquidityNetInDirection";
+const ARITHMETIC_TWAP_TO_NOW_PATH: &str = "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow";
+
+const POOLS: Map<u64, MockPool> = Map::new("osmosis_mock_pools");
+const NEXT_POSITION_ID: Item<u64> = Item::new("osmosis_mock_next_position_id");
+// Generic authorizations by granter, grantee and message type
+const GRANTS: Map<(&str, &str, &str), Empty> = Map::new("osmosis_mock_grants");
+
+/// A concentrated liquidity pool of the mock, its tokens are held by `address`
+#[cw_serde]
+pub struct MockPool {
+    pub id: u64,
+    pub address: Addr,
+    pub tick_spacing: u64,
+    pub snapshot: PoolSnapshot,
+}
+
+impl MockPool {
+    fn to_proto(&self) -> Pool {
+        Pool {
+            address: self.address.to_string(),
+            id: self.id,
+            current_tick_liquidity: self.snapshot.current_tick_liquidity.to_string(),
+            token0: self.snapshot.token0.clone(),
+            token1: self.snapshot.token1.clone(),
+            current_sqrt_price: self.snapshot.current_sqrt_price.to_string(),
+            current_tick: self.snapshot.current_tick,
+            tick_spacing: self.tick_spacing,
+            exponent_at_price_one: -6,
+            spread_factor: self.snapshot.spread_factor.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Handles the stargate messages and queries of the Osmosis modules the contract uses: authz grants and
+/// `MsgExec`, swaps through the poolmanager and position creation on concentrated liquidity pools. The
+/// pools are simulated with the swap and position math of `cl_sim`, the tokens move through the bank module.
+pub struct OsmosisStargate;
+
+impl OsmosisStargate {
+    /// Creates a pool at a price of 1 with a full range position of `amount` of both tokens.
+    /// The returned pool's address has to be funded with them.
+    pub fn create_pool(
+        storage: &mut dyn Storage,
+        token0: &str,
+        token1: &str,
+        tick_spacing: u64,
+        spread_factor: Decimal256,
+        amount: Uint128,
+    ) -> AnyResult<MockPool> {
+        let id = POOLS
+            .keys(storage, None, None, Order::Descending)
+            .next()
+            .transpose()?
+            .unwrap_or_default()
+            + 1;
+        let position = simulate_create_position(
+            Decimal256::one(),
+            MIN_INITIALIZED_TICK,
+            MAX_TICK as i64,
+            amount,
+            amount,
+        )?;
+        let pool = MockPool {
+            id,
+            address: Addr::unchecked(format!("clpool{id}")),
+            tick_spacing,
+            snapshot: PoolSnapshot {
+                token0: token0.to_string(),
+                token1: token1.to_string(),
+                current_sqrt_price: Decimal256::one(),
+                current_tick: 0,
+                current_tick_liquidity: position.liquidity,
+                spread_factor,
+                ticks: vec![
+                    InitializedTick {
+                        tick_index: MIN_INITIALIZED_TICK,
+                        liquidity_net: position.liquidity,
+                        liquidity_net_negative: false,
+                    },
+                    InitializedTick {
+                        tick_index: MAX_TICK as i64,
+                        liquidity_net: position.liquidity,
+                        liquidity_net_negative: true,
+                    },
+                ],
+            },
+        };
+        POOLS.save(storage, id, &pool)?;
+        Ok(pool)
+    }
+
+    pub fn pool(storage: &dyn Storage, pool_id: u64) -> AnyResult<MockPool> {
+        POOLS
+            .may_load(storage, pool_id)?
+            .ok_or_else(|| anyhow!("pool {pool_id} not found"))
+    }
+
+    fn grant(
+        &self,
+        storage: &mut dyn Storage,
+        sender: &Addr,
+        msg: MsgGrant,
+    ) -> AnyResult<AppResponse> {
+        if msg.granter != sender.as_str() {
+            bail!("{} cannot grant on behalf of {}", sender, msg.granter);
+        }
+        let authorization = msg
+            .grant
+            .and_then(|grant| grant.authorization)
+            .ok_or_else(|| anyhow!("grant without authorization"))?;
+        if authorization.type_url != GenericAuthorization::TYPE_URL {
+            bail!("only generic authorizations are supported");
+        }
+        let authorization = GenericAuthorization::decode(authorization.value.as_slice())?;
+        GRANTS.save(
+            storage,
+            (&msg.granter, &msg.grantee, &authorization.msg),
+            &Empty {},
+        )?;
+        Ok(AppResponse::default())
+    }
+
+    // exec executes the messages on behalf of their signers, each of which must have granted the sender
+    fn exec<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: &Addr,
+        msg: MsgExec,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        if msg.grantee != sender.as_str() {
+            bail!("{} cannot execute on behalf of {}", sender, msg.grantee);
+        }
+
+        let mut results = vec![];
+        for any in msg.msgs {
+            let result = match any.type_url.as_str() {
+                MsgSwapExactAmountIn::TYPE_URL => {
+                    let msg = MsgSwapExactAmountIn::decode(any.value.as_slice())?;
+                    check_grant(storage, &msg.sender, sender, &any.type_url)?;
+                    self.swap_exact_amount_in(api, storage, router, block, msg)?
+                        .encode_to_vec()
+                }
+                MsgSwapExactAmountOut::TYPE_URL => {
+                    let msg = MsgSwapExactAmountOut::decode(any.value.as_slice())?;
+                    check_grant(storage, &msg.sender, sender, &any.type_url)?;
+                    self.swap_exact_amount_out(api, storage, router, block, msg)?
+                        .encode_to_vec()
+                }
+                MsgCreatePosition::TYPE_URL => {
+                    let msg = MsgCreatePosition::decode(any.value.as_slice())?;
+                    check_grant(storage, &msg.sender, sender, &any.type_url)?;
+                    self.create_position(api, storage, router, block, msg)?
+                        .encode_to_vec()
+                }
+                type_url => bail!("unsupported message {type_url} in MsgExec"),
+            };
+            results.push(result);
+        }
+
+        Ok(AppResponse {
+            events: vec![],
+            data: Some(MsgExecResponse { results }.encode_to_vec().into()),
+        })
+    }
+
+    fn swap_exact_amount_in<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: MsgSwapExactAmountIn,
+    ) -> AnyResult<MsgSwapExactAmountInResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let pool_id = match msg.routes.as_slice() {
+            [route] => route.pool_id,
+            _ => bail!("only single pool routes are supported"),
+        };
+        let mut pool = Self::pool(storage, pool_id)?;
+        let token_in = msg
+            .token_in
+            .ok_or_else(|| anyhow!("swap without token in"))?;
+        let token_in = Coin {
+            denom: token_in.denom,
+            amount: Uint128::from_str(&token_in.amount)?,
+        };
+
+        let swap =
+            simulate_swap_exact_amount_in(&mut MockStorage::new(), &pool.snapshot, &token_in)?;
+        let token_out_min_amount = Uint128::from_str(&msg.token_out_min_amount)?;
+        if swap.token_out.amount < token_out_min_amount {
+            bail!(
+                "token amount calculated ({}) is lesser than min amount ({})",
+                swap.token_out.amount,
+                token_out_min_amount
+            );
+        }
+
+        let sender = Addr::unchecked(msg.sender);
+        transfer(
+            api,
+            storage,
+            router,
+            block,
+            &sender,
+            &pool.address,
+            swap.token_in,
+        )?;
+        transfer(
+            api,
+            storage,
+            router,
+            block,
+            &pool.address,
+            &sender,
+            swap.token_out.clone(),
+        )?;
+
+        pool.snapshot.current_sqrt_price = swap.end_sqrt_price;
+        pool.snapshot.current_tick = swap.end_tick;
+        pool.snapshot.current_tick_liquidity = swap.end_liquidity;
+        POOLS.save(storage, pool_id, &pool)?;
+
+        Ok(MsgSwapExactAmountInResponse {
+            token_out_amount: swap.token_out.amount.to_string(),
+        })
+    }
+
+    fn swap_exact_amount_out<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: MsgSwapExactAmountOut,
+    ) -> AnyResult<MsgSwapExactAmountOutResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let pool_id = match msg.routes.as_slice() {
+            [route] => route.pool_id,
+            _ => bail!("only single pool routes are supported"),
+        };
+        let mut pool = Self::pool(storage, pool_id)?;
+        let token_out = msg
+            .token_out
+            .ok_or_else(|| anyhow!("swap without token out"))?;
+        let token_out = Coin {
+            denom: token_out.denom,
+            amount: Uint128::from_str(&token_out.amount)?,
+        };
+
+        let swap =
+            simulate_swap_exact_amount_out(&mut MockStorage::new(), &pool.snapshot, &token_out)?;
+        let token_in_max_amount = Uint128::from_str(&msg.token_in_max_amount)?;
+        if swap.token_in.amount > token_in_max_amount {
+            bail!(
+                "token amount calculated ({}) is greater than max amount ({})",
+                swap.token_in.amount,
+                token_in_max_amount
+            );
+        }
+
+        let sender = Addr::unchecked(msg.sender);
+        transfer(
+            api,
+            storage,
+            router,
+            block,
+            &sender,
+            &pool.address,
+            swap.token_in.clone(),
+        )?;
+        transfer(
+            api,
+            storage,
+            router,
+            block,
+            &pool.address,
+            &sender,
+            swap.token_out,
+        )?;
+
+        pool.snapshot.current_sqrt_price = swap.end_sqrt_price;
+        pool.snapshot.current_tick = swap.end_tick;
+        pool.snapshot.current_tick_liquidity = swap.end_liquidity;
+        POOLS.save(storage, pool_id, &pool)?;
+
+        Ok(MsgSwapExactAmountOutResponse {
+            token_in_amount: swap.token_in.amount.to_string(),
+        })
+    }
+
+    fn create_position<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: MsgCreatePosition,
+    ) -> AnyResult<MsgCreatePositionResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let mut pool = Self::pool(storage, msg.pool_id)?;
+        if msg.lower_tick >= msg.upper_tick {
+            bail!(
+                "lower tick {} must be below upper tick {}",
+                msg.lower_tick,
+                msg.upper_tick
+            );
+        }
+
+        let provided = |denom: &str| -> AnyResult<Uint128> {
+            match msg.tokens_provided.iter().find(|coin| coin.denom == denom) {
+                Some(coin) => Ok(Uint128::from_str(&coin.amount)?),
+                None => Ok(Uint128::zero()),
+            }
+        };
+        let position = simulate_create_position(
+            pool.snapshot.current_sqrt_price,
+            msg.lower_tick,
+            msg.upper_tick,
+            provided(&pool.snapshot.token0)?,
+            provided(&pool.snapshot.token1)?,
+        )?;
+        if position.liquidity.is_zero() {
+            bail!("position would have no liquidity");
+        }
+        for (denom, amount, min_amount) in [
+            (
+                &pool.snapshot.token0,
+                position.amount0,
+                &msg.token_min_amount0,
+            ),
+            (
+                &pool.snapshot.token1,
+                position.amount1,
+                &msg.token_min_amount1,
+            ),
+        ] {
+            let min_amount = Uint128::from_str(min_amount)?;
+            if amount < min_amount {
+                bail!(
+                    "insufficient amount of {denom}: {amount} is below the minimum of {min_amount}"
+                );
+            }
+        }
+
+        let sender = Addr::unchecked(&msg.sender);
+        for (denom, amount) in [
+            (pool.snapshot.token0.clone(), position.amount0),
+            (pool.snapshot.token1.clone(), position.amount1),
+        ] {
+            transfer(
+                api,
+                storage,
+                router,
+                block,
+                &sender,
+                &pool.address,
+                Coin { denom, amount },
+            )?;
+        }
+
+        add_liquidity_net(
+            &mut pool.snapshot.ticks,
+            msg.lower_tick,
+            position.liquidity,
+            false,
+        );
+        add_liquidity_net(
+            &mut pool.snapshot.ticks,
+            msg.upper_tick,
+            position.liquidity,
+            true,
+        );
+        if msg.lower_tick <= pool.snapshot.current_tick
+            && pool.snapshot.current_tick < msg.upper_tick
+        {
+            pool.snapshot.current_tick_liquidity = pool
+                .snapshot
+                .current_tick_liquidity
+                .checked_add(position.liquidity)?;
+        }
+        POOLS.save(storage, pool.id, &pool)?;
+
+        let position_id = NEXT_POSITION_ID.may_load(storage)?.unwrap_or(1);
+        NEXT_POSITION_ID.save(storage, &(position_id + 1))?;
+
+        Ok(MsgCreatePositionResponse {
+            position_id,
+            amount0: position.amount0.to_string(),
+            amount1: position.amount1.to_string(),
+            liquidity_created: position.liquidity.to_string(),
+            lower_tick: msg.lower_tick,
+            upper_tick: msg.upper_tick,
+        })
+    }
+}
+
+impl Stargate for OsmosisStargate {}
+
+impl Module for OsmosisStargate {
+    type ExecT = StargateMsg;
+    type QueryT = StargateQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: StargateMsg,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let (type_url, value) = (msg.type_url, msg.value);
+        match type_url.as_str() {
+            MsgGrant::TYPE_URL => self.grant(storage, &sender, MsgGrant::decode(value.as_slice())?),
+            MsgRevoke::TYPE_URL => {
+                let msg = MsgRevoke::decode(value.as_slice())?;
+                if msg.granter != sender.as_str() {
+                    bail!("{} cannot revoke on behalf of {}", sender, msg.granter);
+                }
+                GRANTS.remove(storage, (&msg.granter, &msg.grantee, &msg.msg_type_url));
+                Ok(AppResponse::default())
+            }
+            MsgExec::TYPE_URL => self.exec(
+                api,
+                storage,
+                router,
+                block,
+                &sender,
+                MsgExec::decode(value.as_slice())?,
+            ),
+            _ => bail!("unsupported stargate message {type_url}"),
+        }
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: StargateQuery,
+    ) -> AnyResult<Binary> {
+        let (path, data) = (request.path, request.data);
+        match path.as_str() {
+            POOL_PATH => {
+                let request = PoolRequest::decode(data.as_slice())?;
+                let pool = Self::pool(storage, request.pool_id)?.to_proto();
+                Ok(to_json_binary(&PoolResponse {
+                    pool: Some(Any {
+                        type_url: Pool::TYPE_URL.to_string(),
+                        value: pool.encode_to_vec(),
+                    }),
+                })?)
+            }
+            ESTIMATE_SWAP_EXACT_AMOUNT_IN_PATH => {
+                let request = EstimateSwapExactAmountInRequest::decode(data.as_slice())?;
+                let pool_id = match request.routes.as_slice() {
+                    [route] => route.pool_id,
+                    _ => bail!("only single pool routes are supported"),
+                };
+                let pool = Self::pool(storage, pool_id)?;
+                let swap = simulate_swap_exact_amount_in(
+                    &mut MockStorage::new(),
+                    &pool.snapshot,
+                    &parse_coin(&request.token_in)?,
+                )?;
+                Ok(to_json_binary(&EstimateSwapExactAmountInResponse {
+                    token_out_amount: swap.token_out.amount.to_string(),
+                })?)
+            }
+            LIQUIDITY_NET_IN_DIRECTION_PATH => {
+                let request = LiquidityNetInDirectionRequest::decode(data.as_slice())?;
+                let pool = Self::pool(storage, request.pool_id)?;
+                let snapshot = &pool.snapshot;
+                // Swapping token0 in moves the price down, the ticks are returned in crossing order
+                let zero_for_one = request.token_in == snapshot.token0;
+                let mut ticks: Vec<&InitializedTick> = snapshot
+                    .ticks
+                    .iter()
+                    .filter(|tick| (tick.tick_index <= snapshot.current_tick) == zero_for_one)
+                    .filter(|tick| {
+                        request.use_no_bound
+                            || if zero_for_one {
+                                tick.tick_index >= request.bound_tick
+                            } else {
+                                tick.tick_index <= request.bound_tick
+                            }
+                    })
+                    .collect();
+                if zero_for_one {
+                    ticks.reverse();
+                }
+                Ok(to_json_binary(&LiquidityNetInDirectionResponse {
+                    liquidity_depths: ticks
+                        .into_iter()
+                        .map(|tick| TickLiquidityNet {
+                            liquidity_net: if tick.liquidity_net_negative {
+                                format!("-{}", tick.liquidity_net)
+                            } else {
+                                tick.liquidity_net.to_string()
+                            },
+                            tick_index: tick.tick_index,
+                        })
+                        .collect(),
+                    current_tick: snapshot.current_tick,
+                    current_liquidity: snapshot.current_tick_liquidity.to_string(),
+                })?)
+            }
+            ARITHMETIC_TWAP_TO_NOW_PATH => {
+                // The mock keeps no price history, the TWAP is the spot price
+                let request = ArithmeticTwapToNowRequest::decode(data.as_slice())?;
+                let pool = Self::pool(storage, request.pool_id)?;
+                let sqrt_price = pool.snapshot.current_sqrt_price;
+                Ok(to_json_binary(&ArithmeticTwapToNowResponse {
+                    arithmetic_twap: sqrt_price.checked_mul(sqrt_price)?.to_string(),
+                })?)
+            }
+            GRANTS_QUERY_PATH => {
+                let request = QueryGrantsRequest::decode(data.as_slice())?;
+                let grants = GRANTS
+                    .prefix((&request.granter, &request.grantee))
+                    .keys(storage, None, None, Order::Ascending)
+                    .filter(|msg_type_url| match msg_type_url {
+                        Ok(msg_type_url) => {
+                            request.msg_type_url.is_empty() || *msg_type_url == request.msg_type_url
+                        }
+                        Err(_) => true,
+                    })
+                    .map(|msg_type_url| {
+                        Ok(QueriedGrant {
+                            authorization: QueriedAuthorization {
+                                type_url: GenericAuthorization::TYPE_URL.to_string(),
+                                msg: msg_type_url?,
+                            },
+                            expiration: None,
+                        })
+                    })
+                    .collect::<AnyResult<Vec<_>>>()?;
+                // The chain encodes the authorizations as JSON objects with their @type
+                Ok(to_json_binary(&QueriedGrants { grants })?)
+            }
+            _ => bail!("unsupported stargate query {path}"),
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        _msg: Empty,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: CustomMsg + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("sudo is not supported by the osmosis mock")
+    }
+}
+
+fn check_grant(
+    storage: &dyn Storage,
+    granter: &str,
+    grantee: &Addr,
+    msg_type_url: &str,
+) -> AnyResult<()> {
+    if !GRANTS.has(storage, (granter, grantee.as_str(), msg_type_url)) {
+        bail!("authorization not found for {msg_type_url} from {granter} to {grantee}");
+    }
+    Ok(())
+}
+
+fn transfer<ExecC, QueryC>(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+    block: &BlockInfo,
+    from: &Addr,
+    to: &Addr,
+    coin: Coin,
+) -> AnyResult<()>
+where
+    ExecC: CustomMsg + DeserializeOwned + 'static,
+    QueryC: CustomQuery + DeserializeOwned + 'static,
+{
+    if coin.amount.is_zero() {
+        return Ok(());
+    }
+    router.execute(
+        api,
+        storage,
+        block,
+        from.clone(),
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: to.to_string(),
+            amount: vec![coin],
+        }),
+    )?;
+    Ok(())
+}
+
+// add_liquidity_net adds liquidity to the liquidity net of a tick, initializing it if needed
+fn add_liquidity_net(
+    ticks: &mut Vec<InitializedTick>,
+    tick_index: i64,
+    liquidity: Decimal256,
+    negative: bool,
+) {
+    match ticks.iter_mut().find(|tick| tick.tick_index == tick_index) {
+        Some(tick) if tick.liquidity_net_negative == negative => {
+            tick.liquidity_net += liquidity;
+        }
+        Some(tick) if tick.liquidity_net >= liquidity => {
+            tick.liquidity_net -= liquidity;
+        }
+        Some(tick) => {
+            tick.liquidity_net = liquidity - tick.liquidity_net;
+            tick.liquidity_net_negative = negative;
+        }
+        None => {
+            ticks.push(InitializedTick {
+                tick_index,
+                liquidity_net: liquidity,
+                liquidity_net_negative: negative,
+            });
+            ticks.sort_by_key(|tick| tick.tick_index);
+        }
+    }
+}
+
+// parse_coin parses a coin in the "1000uosmo" format of the poolmanager queries
+fn parse_coin(coin: &str) -> AnyResult<Coin> {
+    let split = coin
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("coin {coin} has no denom"))?;
+    let (amount, denom) = coin.split_at(split);
+    Ok(Coin {
+        denom: denom.to_string(),
+        amount: Uint128::from_str(amount)?,
+    })
+}
@@ -0,0 +1,450 @@
+use cosmwasm_std::{Coin, Decimal, Decimal256, Event, Uint128};
+use cw_multi_test::Executor;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::MsgCreatePosition;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountOut,
+};
+
+use crate::msg::{ExecuteMsg, LadderShape, LadderSimulation, PoolAllocation, QueryMsg, RangeSpec};
+use crate::multitest::{OsmosisStargate, TestEnv, UBAR, UFOO};
+use crate::state::{JoinStats, SwapMode};
+use crate::ContractError;
+
+fn join_msg(amount: u128) -> ExecuteMsg {
+    ExecuteMsg::SingleSidedSwapAndJoin {
+        pool_id: 1,
+        range: RangeSpec::Ticks {
+            lower: -1000,
+            upper: 1000,
+        },
+        token_provided: Coin::new(amount, UFOO),
+        token_min_amount0: Uint128::zero(),
+        token_min_amount1: Uint128::zero(),
+        max_spot_twap_deviation_bps: None,
+        expected_sqrt_price: None,
+        deadline: None,
+        best_effort: None,
+    }
+}
+
+#[test]
+fn test_single_sided_swap_and_join() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    t.grant(
+        &alice,
+        &[MsgSwapExactAmountIn::TYPE_URL, MsgCreatePosition::TYPE_URL],
+    );
+
+    t.app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &join_msg(100_000),
+            &[],
+        )
+        .unwrap();
+
+    // Swapping UFOO in moved the price down
+    let pool = t
+        .app
+        .read_module(|_router, _api, storage| OsmosisStargate::pool(storage, t.pool.id).unwrap());
+    assert!(pool.snapshot.current_sqrt_price < Decimal256::one());
+
+    let stats: JoinStats = t
+        .app
+        .wrap()
+        .query_wasm_smart(&t.contract_addr, &QueryMsg::GlobalStats {})
+        .unwrap();
+    assert_eq!(stats.joins, 1);
+    assert_eq!(stats.provided, vec![Coin::new(100_000, UFOO)]);
+
+    // The swap is sized with the spread factor charged on the input, alice only keeps the expected dust,
+    // which is under 0.02% of the provided token
+    assert_eq!(stats.dust, vec![Coin::new(16, UFOO)]);
+    assert_eq!(t.balance(&alice, UFOO), Uint128::new(900_016));
+    assert_eq!(t.balance(&alice, UBAR), Uint128::new(1_000_000));
+}
+
+#[test]
+fn test_out_of_range_join_skips_swap() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    // Without a swap the swap grant isn't needed
+    t.grant(&alice, &[MsgCreatePosition::TYPE_URL]);
+
+    let pool_ufoo = t.balance(&t.pool.address, UFOO);
+
+    // A range above the current price only takes UFOO
+    let res = t
+        .app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &ExecuteMsg::SingleSidedSwapAndJoin {
+                pool_id: 1,
+                range: RangeSpec::Ticks {
+                    lower: 1000,
+                    upper: 2000,
+                },
+                token_provided: Coin::new(100_000, UFOO),
+                token_min_amount0: Uint128::zero(),
+                token_min_amount1: Uint128::zero(),
+                max_spot_twap_deviation_bps: None,
+                expected_sqrt_price: None,
+                deadline: None,
+                best_effort: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // Nothing was swapped, all of the provided UFOO went into the position
+    let pool = t
+        .app
+        .read_module(|_router, _api, storage| OsmosisStargate::pool(storage, t.pool.id).unwrap());
+    assert_eq!(pool.snapshot.current_sqrt_price, Decimal256::one());
+    assert_eq!(t.balance(&alice, UFOO), Uint128::new(900_000));
+    assert_eq!(t.balance(&alice, UBAR), Uint128::new(1_000_000));
+
+    // The position holds exactly the provided UFOO
+    assert!(res.has_event(
+        &Event::new("wasm-join_position")
+            .add_attribute("amount0", "100000")
+            .add_attribute("amount1", "0")
+    ));
+    assert_eq!(
+        t.balance(&t.pool.address, UFOO),
+        pool_ufoo + Uint128::new(100_000)
+    );
+
+    let stats: JoinStats = t
+        .app
+        .wrap()
+        .query_wasm_smart(&t.contract_addr, &QueryMsg::PoolStats { pool_id: 1 })
+        .unwrap();
+    assert_eq!(stats.joins, 1);
+    assert!(stats.swapped.is_empty());
+}
+
+#[test]
+fn test_best_effort_join() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    t.grant(
+        &alice,
+        &[MsgSwapExactAmountIn::TYPE_URL, MsgCreatePosition::TYPE_URL],
+    );
+
+    // The position can't meet its minimum, the swap is kept and the failure reported
+    let res = t
+        .app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &ExecuteMsg::SingleSidedSwapAndJoin {
+                pool_id: 1,
+                range: RangeSpec::Ticks {
+                    lower: -1000,
+                    upper: 1000,
+                },
+                token_provided: Coin::new(100_000, UFOO),
+                token_min_amount0: Uint128::new(100_000),
+                token_min_amount1: Uint128::zero(),
+                max_spot_twap_deviation_bps: None,
+                expected_sqrt_price: None,
+                deadline: None,
+                best_effort: Some(true),
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm-join_failed").add_attribute("pool_id", "1")));
+    assert!(!res.events.iter().any(|e| e.ty == "wasm-join_position"));
+
+    // Alice holds the swapped tokens and the join is not recorded
+    assert!(t.balance(&alice, UFOO) > Uint128::new(900_000));
+    assert!(t.balance(&alice, UBAR) > Uint128::new(1_000_000));
+    let stats: JoinStats = t
+        .app
+        .wrap()
+        .query_wasm_smart(&t.contract_addr, &QueryMsg::GlobalStats {})
+        .unwrap();
+    assert_eq!(stats.joins, 0);
+}
+
+#[test]
+fn test_multi_pool_join() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    t.grant(
+        &alice,
+        &[MsgSwapExactAmountIn::TYPE_URL, MsgCreatePosition::TYPE_URL],
+    );
+
+    // A shallower pool of the same pair with a higher spread factor
+    let shallow_pool = t.app.init_modules(|router, _api, storage| {
+        let pool = OsmosisStargate::create_pool(
+            storage,
+            UFOO,
+            UBAR,
+            100,
+            Decimal256::permille(3),
+            Uint128::new(1_000_000),
+        )
+        .unwrap();
+        router
+            .bank
+            .init_balance(
+                storage,
+                &pool.address,
+                vec![Coin::new(1_000_000, UFOO), Coin::new(1_000_000, UBAR)],
+            )
+            .unwrap();
+        pool
+    });
+
+    let range = RangeSpec::Ticks {
+        lower: -1000,
+        upper: 1000,
+    };
+    t.app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &ExecuteMsg::MultiPoolJoin {
+                pools: vec![
+                    PoolAllocation {
+                        pool_id: t.pool.id,
+                        range: range.clone(),
+                        weight: 3,
+                    },
+                    PoolAllocation {
+                        pool_id: shallow_pool.id,
+                        range,
+                        weight: 1,
+                    },
+                ],
+                token_provided: Coin::new(100_000, UFOO),
+                max_slippage: Decimal::percent(1),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // The swap went through the deeper pool only
+    let (pool, shallow_pool) = t.app.read_module(|_router, _api, storage| {
+        (
+            OsmosisStargate::pool(storage, t.pool.id).unwrap(),
+            OsmosisStargate::pool(storage, shallow_pool.id).unwrap(),
+        )
+    });
+    assert!(pool.snapshot.current_sqrt_price < Decimal256::one());
+    assert_eq!(shallow_pool.snapshot.current_sqrt_price, Decimal256::one());
+
+    // Both pools got a position with their share, sized against the swap through the deeper pool so that
+    // little is left over in either
+    for (pool_id, provided, dust) in [(pool.id, 75_000, 22), (shallow_pool.id, 25_000, 1)] {
+        let stats: JoinStats = t
+            .app
+            .wrap()
+            .query_wasm_smart(&t.contract_addr, &QueryMsg::PoolStats { pool_id })
+            .unwrap();
+        assert_eq!(stats.joins, 1);
+        assert_eq!(stats.provided, vec![Coin::new(provided, UFOO)]);
+        assert_eq!(stats.dust, vec![Coin::new(dust, UFOO)]);
+    }
+    assert!(t.balance(&alice, UFOO) < Uint128::new(901_000));
+}
+
+#[test]
+fn test_ladder_join() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    t.grant(
+        &alice,
+        &[MsgSwapExactAmountIn::TYPE_URL, MsgCreatePosition::TYPE_URL],
+    );
+
+    let center = RangeSpec::Ticks {
+        lower: -1000,
+        upper: 1000,
+    };
+    let simulation: LadderSimulation = t
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &t.contract_addr,
+            &QueryMsg::SimulateLadderJoin {
+                pool_id: 1,
+                center: center.clone(),
+                shape: LadderShape::Linear,
+                buckets: 3,
+                token_provided: Coin::new(100_000, UFOO),
+            },
+        )
+        .unwrap();
+    let buckets: Vec<(i64, i64, u64)> = simulation
+        .buckets
+        .iter()
+        .map(|bucket| (bucket.lower_tick, bucket.upper_tick, bucket.weight))
+        .collect();
+    assert_eq!(
+        buckets,
+        vec![(-3000, -1000, 1), (-1000, 1000, 3), (1000, 3000, 1)]
+    );
+    // The swap moves the price into the bucket below, which takes both tokens at the end price while the
+    // others only take UFOO
+    assert!(!simulation.buckets[0].amount0.is_zero());
+    assert!(!simulation.buckets[0].amount1.is_zero());
+    assert!(simulation.buckets[1].amount1.is_zero());
+    assert!(simulation.buckets[2].amount1.is_zero());
+
+    t.app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &ExecuteMsg::LadderJoin {
+                pool_id: 1,
+                center,
+                shape: LadderShape::Linear,
+                buckets: 3,
+                token_provided: Coin::new(100_000, UFOO),
+                max_slippage: Decimal::percent(1),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // Every bucket got a position and the swap was the simulated one
+    let stats: JoinStats = t
+        .app
+        .wrap()
+        .query_wasm_smart(&t.contract_addr, &QueryMsg::PoolStats { pool_id: 1 })
+        .unwrap();
+    assert_eq!(stats.joins, 3);
+    assert_eq!(stats.provided, vec![Coin::new(100_000, UFOO)]);
+    assert_eq!(stats.swapped, vec![simulation.token_in]);
+    // The buckets were sized together at the price the swap ends at, so next to nothing is left over
+    assert_eq!(stats.dust, vec![Coin::new(2, UBAR)]);
+}
+
+#[test]
+fn test_missing_authz_grant() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    t.grant(&alice, &[MsgSwapExactAmountIn::TYPE_URL]);
+
+    let err = t
+        .app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &join_msg(100_000),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        format!(
+            "Missing authz grant for {} to the contract",
+            MsgCreatePosition::TYPE_URL
+        )
+    );
+
+    // Nothing was swapped
+    assert_eq!(t.balance(&alice, UFOO), Uint128::new(1_000_000));
+
+    // The swap grant is checked once the join turns out to need a swap
+    let mut t = TestEnv::setup();
+    t.grant(&alice, &[MsgCreatePosition::TYPE_URL]);
+    let err = t
+        .app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &join_msg(100_000),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        format!(
+            "Missing authz grant for {} to the contract",
+            MsgSwapExactAmountIn::TYPE_URL
+        )
+    );
+    assert_eq!(t.balance(&alice, UFOO), Uint128::new(1_000_000));
+}
+
+#[test]
+fn test_exact_out_swap_mode() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    t.app
+        .execute_contract(
+            t.admin.clone(),
+            t.contract_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                swap_mode: Some(SwapMode::ExactOut),
+                max_spot_twap_deviation_bps: None,
+                twap_window_seconds: None,
+            },
+            &[],
+        )
+        .unwrap();
+    t.grant(
+        &alice,
+        &[MsgSwapExactAmountOut::TYPE_URL, MsgCreatePosition::TYPE_URL],
+    );
+
+    t.app
+        .execute_contract(
+            alice.clone(),
+            t.contract_addr.clone(),
+            &join_msg(100_000),
+            &[],
+        )
+        .unwrap();
+
+    let stats: JoinStats = t
+        .app
+        .wrap()
+        .query_wasm_smart(&t.contract_addr, &QueryMsg::PoolStats { pool_id: 1 })
+        .unwrap();
+    assert_eq!(stats.joins, 1);
+    assert_eq!(stats.swapped.len(), 1);
+    assert!(t.balance(&alice, UFOO) < Uint128::new(901_000));
+}
+
+#[test]
+fn test_exact_out_swap_without_output() {
+    let mut t = TestEnv::setup();
+    let alice = t.alice.clone();
+    t.app
+        .execute_contract(
+            t.admin.clone(),
+            t.contract_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                swap_mode: Some(SwapMode::ExactOut),
+                max_spot_twap_deviation_bps: None,
+                twap_window_seconds: None,
+            },
+            &[],
+        )
+        .unwrap();
+    t.grant(
+        &alice,
+        &[MsgSwapExactAmountOut::TYPE_URL, MsgCreatePosition::TYPE_URL],
+    );
+
+    // Half of 2 UFOO is swapped, which returns nothing after the spread factor
+    let err = t
+        .app
+        .execute_contract(alice.clone(), t.contract_addr.clone(), &join_msg(2), &[])
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::ZeroSwapOutput { token_in } if token_in == Coin::new(1, UFOO)
+    ));
+}
@@ -0,0 +1,268 @@
+use cosmwasm_std::{Decimal256, Storage};
+
+use crate::msg::RangeSpec;
+use crate::tick::{price_to_tick, tick_to_price, MAX_TICK, MIN_INITIALIZED_TICK};
+use crate::ContractError;
+
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// resolve_range turns a range spec into the lower and upper tick of the position, using the pool's
+// current tick and tick spacing at the time of execution. Ticks derived from the current price are
+// widened to the closest tick spacing multiples and clamped to the valid tick range.
+pub fn resolve_range(
+    storage: &mut dyn Storage,
+    range: &RangeSpec,
+    current_tick: i64,
+    tick_spacing: u64,
+) -> Result<(i64, i64), ContractError> {
+    let tick_spacing = i64::try_from(tick_spacing).map_err(|_| ContractError::Overflow {})?;
+    if tick_spacing <= 0 {
+        return Err(ContractError::InvalidTickSpacing {});
+    }
+
+    let (lower_tick, upper_tick) = match range {
+        RangeSpec::Ticks { lower, upper } => (*lower, *upper),
+        RangeSpec::PercentAroundCurrent { down_bps, up_bps } => {
+            let current_price = tick_to_price(current_tick)?;
+            let bps = Decimal256::from_ratio(1u128, BPS_DENOMINATOR);
+
+            let lower_tick = if *down_bps >= BPS_DENOMINATOR {
+                MIN_INITIALIZED_TICK
+            } else {
+                let lower_price = current_price
+                    .checked_mul(Decimal256::one().checked_sub(
+                        bps.checked_mul(Decimal256::from_ratio(*down_bps, 1u128))?,
+                    )?)?;
+                price_to_tick_clamped(storage, lower_price)?
+            };
+            let upper_price = current_price.checked_mul(
+                Decimal256::one()
+                    .checked_add(bps.checked_mul(Decimal256::from_ratio(*up_bps, 1u128))?)?,
+            )?;
+            let upper_tick = price_to_tick_clamped(storage, upper_price)?;
+
+            (
+                round_down_to_spacing(lower_tick, tick_spacing),
+                round_up_to_spacing(upper_tick, tick_spacing),
+            )
+        }
+        RangeSpec::SpacingsAroundCurrent { n_below, n_above } => {
+            // The current tick sits in the spacing that starts at the closest multiple below it
+            let current_spacing_start = round_down_to_spacing(current_tick, tick_spacing);
+            let n_below = i64::try_from(*n_below).map_err(|_| ContractError::Overflow {})?;
+            let n_above = i64::try_from(*n_above).map_err(|_| ContractError::Overflow {})?;
+            (
+                n_below
+                    .checked_mul(tick_spacing)
+                    .and_then(|below| current_spacing_start.checked_sub(below))
+                    .ok_or(ContractError::Overflow {})?,
+                n_above
+                    .checked_add(1)
+                    .and_then(|above| above.checked_mul(tick_spacing))
+                    .and_then(|above| current_spacing_start.checked_add(above))
+                    .ok_or(ContractError::Overflow {})?,
+            )
+        }
+        RangeSpec::FullRange => (MIN_INITIALIZED_TICK, MAX_TICK as i64),
+    };
+
+    // Explicit ticks are used as given, the chain validates them against the tick spacing
+    let (lower_tick, upper_tick) = match range {
+        RangeSpec::Ticks { .. } => (lower_tick, upper_tick),
+        _ => (
+            round_up_to_spacing(lower_tick.max(MIN_INITIALIZED_TICK), tick_spacing),
+            round_down_to_spacing(upper_tick.min(MAX_TICK as i64), tick_spacing),
+        ),
+    };
+
+    if lower_tick >= upper_tick {
+        return Err(ContractError::InvalidTickRange {
+            lower_tick,
+            upper_tick,
+        });
+    }
+
+    Ok((lower_tick, upper_tick))
+}
+
+// same_width_range returns a range spec of the same number of tick spacings as the given range, centered
+// on the spacing the current tick is in
+pub fn same_width_range(
+    lower_tick: i64,
+    upper_tick: i64,
+    tick_spacing: u64,
+) -> Result<RangeSpec, ContractError> {
+    let tick_spacing = i64::try_from(tick_spacing).map_err(|_| ContractError::Overflow {})?;
+    if tick_spacing <= 0 {
+        return Err(ContractError::InvalidTickSpacing {});
+    }
+    if lower_tick >= upper_tick {
+        return Err(ContractError::InvalidTickRange {
+            lower_tick,
+            upper_tick,
+        });
+    }
+
+    // The spacing the current tick is in is part of the range
+    let spacings = ((upper_tick - lower_tick) / tick_spacing).max(1) - 1;
+    let n_below = spacings / 2;
+    let n_above = spacings - n_below;
+    Ok(RangeSpec::SpacingsAroundCurrent {
+        n_below: n_below as u64,
+        n_above: n_above as u64,
+    })
+}
+
+pub fn round_down_to_spacing(tick: i64, tick_spacing: i64) -> i64 {
+    tick.div_euclid(tick_spacing) * tick_spacing
+}
+
+pub fn round_up_to_spacing(tick: i64, tick_spacing: i64) -> i64 {
+    let rounded_down = round_down_to_spacing(tick, tick_spacing);
+    if rounded_down == tick {
+        tick
+    } else {
+        rounded_down + tick_spacing
+    }
+}
+
+// Prices outside of the valid price range are mapped to the min and max tick
+fn price_to_tick_clamped(
+    storage: &mut dyn Storage,
+    price: Decimal256,
+) -> Result<i64, ContractError> {
+    if price <= tick_to_price(MIN_INITIALIZED_TICK)? {
+        return Ok(MIN_INITIALIZED_TICK);
+    }
+    if price >= tick_to_price(MAX_TICK as i64)? {
+        return Ok(MAX_TICK as i64);
+    }
+    i64::try_from(price_to_tick(storage, price)?).map_err(|_| ContractError::Overflow {})
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_dependencies;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_ticks() {
+        let mut deps = mock_dependencies();
+        let range = RangeSpec::Ticks {
+            lower: -200,
+            upper: 342000000,
+        };
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, 0, 100).unwrap(),
+            (-200, 342000000)
+        );
+
+        let range = RangeSpec::Ticks {
+            lower: 100,
+            upper: 100,
+        };
+        assert!(matches!(
+            resolve_range(deps.as_mut().storage, &range, 0, 100).unwrap_err(),
+            ContractError::InvalidTickRange { .. }
+        ));
+    }
+
+    #[test]
+    fn test_resolve_percent_around_current() {
+        let mut deps = mock_dependencies();
+
+        // price 1 -> 0.95 (tick -500000) and 1.05 (tick 50000)
+        let range = RangeSpec::PercentAroundCurrent {
+            down_bps: 500,
+            up_bps: 500,
+        };
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, 0, 100).unwrap(),
+            (-500000, 50000)
+        );
+
+        // price 30352 (tick 38035200) -> 28834.4 and 31869.6, widened to the tick spacing
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, 38035200, 1000).unwrap(),
+            (37883000, 38187000)
+        );
+
+        // 100% down is clamped to the min tick
+        let range = RangeSpec::PercentAroundCurrent {
+            down_bps: 10_000,
+            up_bps: 100,
+        };
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, 0, 100).unwrap(),
+            (MIN_INITIALIZED_TICK, 10000)
+        );
+    }
+
+    #[test]
+    fn test_resolve_spacings_around_current() {
+        let mut deps = mock_dependencies();
+        let range = RangeSpec::SpacingsAroundCurrent {
+            n_below: 2,
+            n_above: 1,
+        };
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, 150, 100).unwrap(),
+            (-100, 300)
+        );
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, -150, 100).unwrap(),
+            (-400, 0)
+        );
+
+        // only the spacing the current tick is in
+        let range = RangeSpec::SpacingsAroundCurrent {
+            n_below: 0,
+            n_above: 0,
+        };
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, 0, 100).unwrap(),
+            (0, 100)
+        );
+    }
+
+    #[test]
+    fn test_same_width_range() {
+        let mut deps = mock_dependencies();
+
+        // 4 spacings wide, one more above the current spacing than below it
+        let range = same_width_range(-300, 100, 100).unwrap();
+        assert_eq!(
+            range,
+            RangeSpec::SpacingsAroundCurrent {
+                n_below: 1,
+                n_above: 2
+            }
+        );
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &range, 1050, 100).unwrap(),
+            (900, 1300)
+        );
+
+        assert_eq!(
+            same_width_range(0, 100, 100).unwrap(),
+            RangeSpec::SpacingsAroundCurrent {
+                n_below: 0,
+                n_above: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_full_range() {
+        let mut deps = mock_dependencies();
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &RangeSpec::FullRange, 0, 100).unwrap(),
+            (MIN_INITIALIZED_TICK, MAX_TICK as i64)
+        );
+        assert_eq!(
+            resolve_range(deps.as_mut().storage, &RangeSpec::FullRange, 0, 7).unwrap(),
+            (-107999997, 341999994)
+        );
+    }
+}
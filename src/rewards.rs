@@ -0,0 +1,700 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use cosmwasm_std::{
+    Addr, Coin, Decimal, DepsMut, Env, MessageInfo, QuerierWrapper, Reply, Response, Storage,
+    SubMsg, SubMsgResponse, SubMsgResult, Uint128,
+};
+use osmosis_std::types::cosmos::authz::v1beta1::MsgExec;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as OsmosisCoin;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    MsgCollectIncentives, MsgCollectIncentivesResponse, MsgCollectSpreadRewards,
+    MsgCollectSpreadRewardsResponse,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, PoolmanagerQuerier, SwapAmountInRoute,
+};
+
+use crate::cl_sim::query_pool_snapshot;
+use crate::contract::{
+    COLLECT_REPLY_ID, HARVEST_COLLECT_REPLY_ID, HARVEST_SWAP_REPLY_ID, REWARD_SWAP_REPLY_ID,
+};
+use crate::execute::{
+    apply_slippage, check_min_amount, parse_msg_exec_response, query_cl_pool, query_owned_position,
+    swap_to_ratio_and_join, validate_max_slippage,
+};
+use crate::grants::check_swap_grant;
+use crate::state::{
+    CompoundState, HarvestState, PositionTarget, RewardSwapMsgReplyState, SwapRouteHop,
+    COLLECT_REPLY_STATES, CONFIG, HARVEST_REPLY_STATES, REWARD_SWAP_REPLY_STATES, SWAP_ROUTES,
+};
+use crate::ContractError;
+
+// set_swap_route sets the route rewards of token_in_denom are swapped through to get token_out_denom
+// An empty route removes it. Only the owner can set swap routes.
+pub fn set_swap_route(
+    deps: DepsMut,
+    info: &MessageInfo,
+    token_in_denom: String,
+    token_out_denom: String,
+    route: Vec<SwapRouteHop>,
+) -> Result<Response, ContractError> {
+    if info.sender != CONFIG.load(deps.storage)?.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if route.is_empty() {
+        SWAP_ROUTES.remove(
+            deps.storage,
+            (token_in_denom.as_str(), token_out_denom.as_str()),
+        );
+    } else {
+        // The route has to end in the token out denom
+        if route.last().map(|hop| hop.token_out_denom.as_str()) != Some(token_out_denom.as_str()) {
+            return Err(ContractError::InvalidSwapRoute {
+                token_in_denom,
+                token_out_denom,
+            });
+        }
+        SWAP_ROUTES.save(
+            deps.storage,
+            (token_in_denom.as_str(), token_out_denom.as_str()),
+            &route,
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_swap_route")
+        .add_attribute("token_in_denom", token_in_denom)
+        .add_attribute("token_out_denom", token_out_denom))
+}
+
+// compound collects the spread rewards and incentives of a position and adds them back to it
+// The paramaters to note are:
+// - position_id: The id of the position to compound, it must be owned by the sender
+// - max_slippage: How far below their estimated amounts the reward swaps, the ratio swap and the added amounts may be.
+// Rewards in denoms other than the pool's tokens are swapped through the configured swap routes, rewards without
+// a route to either token stay with the user.
+pub fn compound(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    position_id: u64,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    validate_max_slippage(max_slippage)?;
+
+    // Get the position to compound, only its owner can compound it
+    let (position, _) = query_owned_position(&deps.querier, position_id, &info.sender)?;
+
+    // Collect the spread rewards and incentives on behalf of the user
+    let exec_msg: MsgExec = MsgExec {
+        grantee: env.contract.address.to_string(),
+        msgs: vec![
+            MsgCollectSpreadRewards {
+                position_ids: vec![position_id],
+                sender: info.sender.to_string(),
+            }
+            .to_any(),
+            MsgCollectIncentives {
+                position_ids: vec![position_id],
+                sender: info.sender.to_string(),
+            }
+            .to_any(),
+        ],
+    };
+
+    // Save intermediate state
+    // We will utilize this state after the rewards have been collected
+    COLLECT_REPLY_STATES.save(
+        deps.storage,
+        COLLECT_REPLY_ID,
+        &CompoundState {
+            pool_id: position.pool_id,
+            position_id,
+            original_sender: info.sender.clone(),
+            lower_tick: position.lower_tick,
+            upper_tick: position.upper_tick,
+            max_slippage,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "compound")
+        .add_attribute("position_id", position_id.to_string())
+        .add_submessage(SubMsg::reply_on_success(exec_msg, COLLECT_REPLY_ID)))
+}
+
+// handle_collect_reply is called after the rewards of a position have been collected
+// Rewards in the pool's tokens are kept, other rewards are swapped into one of the pool's tokens
+pub fn handle_collect_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+    compound_state: CompoundState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        // Parse the collect responses
+        let spread_rewards: MsgCollectSpreadRewardsResponse =
+            parse_msg_exec_response(b.clone(), 0)?;
+        let incentives: MsgCollectIncentivesResponse = parse_msg_exec_response(b, 1)?;
+        let collected = sum_coins(
+            spread_rewards
+                .collected_spread_rewards
+                .iter()
+                .chain(incentives.collected_incentives.iter()),
+        )?;
+
+        let pool = query_cl_pool(&deps.querier, compound_state.pool_id)?;
+        let mut amount0 = Uint128::zero();
+        let mut amount1 = Uint128::zero();
+        let mut swap_msgs = vec![];
+        let mut token_out_denoms = vec![];
+        let mut unswapped = vec![];
+        for coin in collected {
+            if coin.denom == pool.token0 {
+                amount0 = amount0.checked_add(coin.amount)?;
+            } else if coin.denom == pool.token1 {
+                amount1 = amount1.checked_add(coin.amount)?;
+            } else {
+                match reward_swap_msg(
+                    &deps.querier,
+                    deps.storage,
+                    &compound_state.original_sender,
+                    &coin,
+                    &[pool.token0.as_str(), pool.token1.as_str()],
+                    compound_state.max_slippage,
+                )? {
+                    Some((swap_msg, token_out_denom)) => {
+                        swap_msgs.push(swap_msg.to_any());
+                        token_out_denoms.push(token_out_denom);
+                    }
+                    None => unswapped.push(coin.to_string()),
+                }
+            }
+        }
+
+        let response = Response::new().add_attribute("unswapped_rewards", unswapped.join(","));
+
+        if swap_msgs.is_empty() {
+            return add_to_position(deps, &env, compound_state, amount0, amount1, response);
+        }
+        check_swap_grant(
+            deps.as_ref(),
+            &env,
+            &compound_state.original_sender,
+            MsgSwapExactAmountIn::TYPE_URL,
+        )?;
+
+        // Swap the rewards on behalf of the user and store the intermediate state
+        let exec_msg: MsgExec = MsgExec {
+            grantee: env.contract.address.to_string(),
+            msgs: swap_msgs,
+        };
+        REWARD_SWAP_REPLY_STATES.save(
+            deps.storage,
+            REWARD_SWAP_REPLY_ID,
+            &RewardSwapMsgReplyState {
+                compound: compound_state,
+                amount0,
+                amount1,
+                token_out_denoms,
+            },
+        )?;
+
+        return Ok(
+            response.add_submessage(SubMsg::reply_on_success(exec_msg, REWARD_SWAP_REPLY_ID))
+        );
+    }
+
+    Err(ContractError::FailedCollect {
+        reason: msg.result.unwrap_err(),
+    })
+}
+
+// handle_reward_swap_reply is called after the rewards have been swapped into the pool's tokens
+// This function adds the rewards and the swap outputs to the position
+pub fn handle_reward_swap_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+    reward_swap_msg_reply_state: RewardSwapMsgReplyState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        let pool = query_cl_pool(&deps.querier, reward_swap_msg_reply_state.compound.pool_id)?;
+        let mut amount0 = reward_swap_msg_reply_state.amount0;
+        let mut amount1 = reward_swap_msg_reply_state.amount1;
+
+        // Add the output of every swap to the amount of the token it swapped for
+        for (index, token_out_denom) in reward_swap_msg_reply_state
+            .token_out_denoms
+            .iter()
+            .enumerate()
+        {
+            let res: MsgSwapExactAmountInResponse = parse_msg_exec_response(b.clone(), index)?;
+            let token_out_amount = Uint128::from_str(&res.token_out_amount)?;
+            if *token_out_denom == pool.token0 {
+                amount0 = amount0.checked_add(token_out_amount)?;
+            } else {
+                amount1 = amount1.checked_add(token_out_amount)?;
+            }
+        }
+
+        return add_to_position(
+            deps,
+            &env,
+            reward_swap_msg_reply_state.compound,
+            amount0,
+            amount1,
+            Response::new(),
+        );
+    }
+
+    Err(ContractError::FailedSwap {
+        reason: msg.result.unwrap_err(),
+    })
+}
+
+// harvest_to collects the spread rewards and incentives of several positions and swaps them into a single denom
+// The paramaters to note are:
+// - position_ids: The ids of the positions to harvest, they must be owned by the sender
+// - target_denom: The denom the rewards are paid out in. Rewards are swapped through the configured swap routes,
+//   rewards without a route to the target denom stay with the user.
+// - min_out: The minimum total amount of the target denom the user receives
+pub fn harvest_to(
+    env: &Env,
+    info: &MessageInfo,
+    deps: DepsMut,
+    position_ids: Vec<u64>,
+    target_denom: String,
+    min_out: Uint128,
+) -> Result<Response, ContractError> {
+    if position_ids.is_empty() {
+        return Err(ContractError::NoPositionsProvided {});
+    }
+    for position_id in &position_ids {
+        query_owned_position(&deps.querier, *position_id, &info.sender)?;
+    }
+
+    // Collect the spread rewards and incentives on behalf of the user
+    let exec_msg: MsgExec = MsgExec {
+        grantee: env.contract.address.to_string(),
+        msgs: vec![
+            MsgCollectSpreadRewards {
+                position_ids: position_ids.clone(),
+                sender: info.sender.to_string(),
+            }
+            .to_any(),
+            MsgCollectIncentives {
+                position_ids,
+                sender: info.sender.to_string(),
+            }
+            .to_any(),
+        ],
+    };
+
+    // Save intermediate state
+    // We will utilize this state after the rewards have been collected
+    HARVEST_REPLY_STATES.save(
+        deps.storage,
+        HARVEST_COLLECT_REPLY_ID,
+        &HarvestState {
+            original_sender: info.sender.clone(),
+            target_denom,
+            min_out,
+            amount_out: Uint128::zero(),
+            swap_count: 0,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "harvest_to")
+        .add_submessage(SubMsg::reply_on_success(exec_msg, HARVEST_COLLECT_REPLY_ID)))
+}
+
+// handle_harvest_collect_reply is called after the rewards of the positions have been collected
+// Rewards in other denoms than the target denom are swapped into it
+pub fn handle_harvest_collect_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+    mut harvest_state: HarvestState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        // Parse the collect responses
+        let spread_rewards: MsgCollectSpreadRewardsResponse =
+            parse_msg_exec_response(b.clone(), 0)?;
+        let incentives: MsgCollectIncentivesResponse = parse_msg_exec_response(b, 1)?;
+        let collected = sum_coins(
+            spread_rewards
+                .collected_spread_rewards
+                .iter()
+                .chain(incentives.collected_incentives.iter()),
+        )?;
+
+        let mut swap_msgs = vec![];
+        let mut unswapped = vec![];
+        for coin in collected {
+            if coin.denom == harvest_state.target_denom {
+                harvest_state.amount_out = harvest_state.amount_out.checked_add(coin.amount)?;
+                continue;
+            }
+            // The total is checked against min_out once all swaps are done
+            match reward_swap_msg(
+                &deps.querier,
+                deps.storage,
+                &harvest_state.original_sender,
+                &coin,
+                &[harvest_state.target_denom.as_str()],
+                Decimal::one(),
+            )? {
+                Some((swap_msg, _)) => swap_msgs.push(swap_msg.to_any()),
+                None => unswapped.push(coin.to_string()),
+            }
+        }
+
+        let response = Response::new().add_attribute("unswapped_rewards", unswapped.join(","));
+
+        if swap_msgs.is_empty() {
+            check_min_out(&harvest_state)?;
+            return Ok(response.add_attribute("amount_out", harvest_state.amount_out));
+        }
+        check_swap_grant(
+            deps.as_ref(),
+            &env,
+            &harvest_state.original_sender,
+            MsgSwapExactAmountIn::TYPE_URL,
+        )?;
+
+        // Swap the rewards on behalf of the user and store the intermediate state
+        harvest_state.swap_count = swap_msgs.len() as u64;
+        let exec_msg: MsgExec = MsgExec {
+            grantee: env.contract.address.to_string(),
+            msgs: swap_msgs,
+        };
+        HARVEST_REPLY_STATES.save(deps.storage, HARVEST_SWAP_REPLY_ID, &harvest_state)?;
+
+        return Ok(
+            response.add_submessage(SubMsg::reply_on_success(exec_msg, HARVEST_SWAP_REPLY_ID))
+        );
+    }
+
+    Err(ContractError::FailedCollect {
+        reason: msg.result.unwrap_err(),
+    })
+}
+
+// handle_harvest_swap_reply is called after the rewards have been swapped into the target denom
+// This function checks the total amount of the target denom against min_out
+pub fn handle_harvest_swap_reply(
+    _deps: DepsMut,
+    _env: Env,
+    msg: Reply,
+    mut harvest_state: HarvestState,
+) -> Result<Response, ContractError> {
+    if let SubMsgResult::Ok(SubMsgResponse { data: Some(b), .. }) = msg.result {
+        for index in 0..harvest_state.swap_count as usize {
+            let res: MsgSwapExactAmountInResponse = parse_msg_exec_response(b.clone(), index)?;
+            harvest_state.amount_out = harvest_state
+                .amount_out
+                .checked_add(Uint128::from_str(&res.token_out_amount)?)?;
+        }
+
+        check_min_out(&harvest_state)?;
+        return Ok(Response::new().add_attribute("amount_out", harvest_state.amount_out));
+    }
+
+    Err(ContractError::FailedSwap {
+        reason: msg.result.unwrap_err(),
+    })
+}
+
+fn check_min_out(harvest_state: &HarvestState) -> Result<(), ContractError> {
+    check_min_amount(
+        &harvest_state.target_denom,
+        harvest_state.amount_out,
+        harvest_state.min_out,
+    )
+}
+
+// add_to_position swaps the compounded amounts to the ratio of the position and adds them to it
+fn add_to_position(
+    deps: DepsMut,
+    env: &Env,
+    compound_state: CompoundState,
+    amount0: Uint128,
+    amount1: Uint128,
+    response: Response,
+) -> Result<Response, ContractError> {
+    if amount0.is_zero() && amount1.is_zero() {
+        return Ok(response.add_attribute("compounded", "none"));
+    }
+
+    let pool = query_cl_pool(&deps.querier, compound_state.pool_id)?;
+    let snapshot = query_pool_snapshot(&deps.querier, &pool)?;
+    let join = swap_to_ratio_and_join(
+        deps,
+        env,
+        compound_state.original_sender,
+        &pool,
+        &snapshot,
+        compound_state.lower_tick,
+        compound_state.upper_tick,
+        amount0,
+        amount1,
+        compound_state.max_slippage,
+        PositionTarget::ExistingPosition {
+            position_id: compound_state.position_id,
+        },
+    )?;
+
+    Ok(response
+        .add_attribute("compounded_amount0", amount0)
+        .add_attribute("compounded_amount1", amount1)
+        .add_attributes(join.attributes)
+        .add_events(join.events)
+        .add_submessages(join.messages))
+}
+
+// reward_swap_msg creates the swap of the coin through the configured route to the first of the
+// target denoms that has one, with a minimum output of the estimated output less max_slippage.
+// Returns None if there is no route to any of the target denoms.
+pub(crate) fn reward_swap_msg(
+    querier: &QuerierWrapper,
+    storage: &dyn Storage,
+    sender: &Addr,
+    coin: &Coin,
+    target_denoms: &[&str],
+    max_slippage: Decimal,
+) -> Result<Option<(MsgSwapExactAmountIn, String)>, ContractError> {
+    for target_denom in target_denoms {
+        let route = match SWAP_ROUTES.may_load(storage, (coin.denom.as_str(), *target_denom))? {
+            Some(route) => route,
+            None => continue,
+        };
+        let routes: Vec<SwapAmountInRoute> = route
+            .into_iter()
+            .map(|hop| SwapAmountInRoute {
+                pool_id: hop.pool_id,
+                token_out_denom: hop.token_out_denom,
+            })
+            .collect();
+
+        let estimate = PoolmanagerQuerier::new(querier).estimate_swap_exact_amount_in(
+            routes[0].pool_id,
+            coin.to_string(),
+            routes.clone(),
+        )?;
+        // The chain only accepts a positive minimum
+        let token_out_min_amount =
+            apply_slippage(Uint128::from_str(&estimate.token_out_amount)?, max_slippage)
+                .max(Uint128::one());
+
+        return Ok(Some((
+            MsgSwapExactAmountIn {
+                sender: sender.to_string(),
+                routes,
+                token_in: Some(coin.clone().into()),
+                token_out_min_amount: token_out_min_amount.to_string(),
+            },
+            target_denom.to_string(),
+        )));
+    }
+    Ok(None)
+}
+
+// sum_coins adds up the amounts of the coins per denom, dropping zero amounts
+pub(crate) fn sum_coins<'a>(
+    coins: impl Iterator<Item = &'a OsmosisCoin>,
+) -> Result<Vec<Coin>, ContractError> {
+    let mut sums: BTreeMap<String, Uint128> = BTreeMap::new();
+    for coin in coins {
+        let sum = sums.entry(coin.denom.clone()).or_default();
+        *sum = sum.checked_add(Uint128::from_str(&coin.amount)?)?;
+    }
+    Ok(sums
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(denom, amount)| Coin { denom, amount })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::Binary;
+    use osmosis_std::types::cosmos::authz::v1beta1::MsgExecResponse;
+    use prost::Message;
+
+    use super::*;
+
+    #[test]
+    fn test_sum_coins() {
+        let coins = [
+            OsmosisCoin {
+                denom: "uosmo".to_string(),
+                amount: "100".to_string(),
+            },
+            OsmosisCoin {
+                denom: "uion".to_string(),
+                amount: "0".to_string(),
+            },
+            OsmosisCoin {
+                denom: "uosmo".to_string(),
+                amount: "50".to_string(),
+            },
+        ];
+        assert_eq!(
+            sum_coins(coins.iter()).unwrap(),
+            vec![Coin::new(150, "uosmo")]
+        );
+    }
+
+    #[test]
+    fn test_harvest_swap_reply_min_out() {
+        let mut deps = mock_dependencies();
+        let swap_response = |amount: &str| {
+            MsgSwapExactAmountInResponse {
+                token_out_amount: amount.to_string(),
+            }
+            .encode_to_vec()
+        };
+        let reply = Reply {
+            id: HARVEST_SWAP_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(Binary::from(
+                    MsgExecResponse {
+                        results: vec![swap_response("600"), swap_response("350")],
+                    }
+                    .encode_to_vec(),
+                )),
+            }),
+        };
+        let harvest_state = HarvestState {
+            original_sender: Addr::unchecked("alice"),
+            target_denom: "uosmo".to_string(),
+            min_out: Uint128::new(1000),
+            amount_out: Uint128::new(50),
+            swap_count: 2,
+        };
+
+        // 50 collected and 950 swapped meet the minimum
+        let res = handle_harvest_swap_reply(
+            deps.as_mut(),
+            mock_env(),
+            reply.clone(),
+            harvest_state.clone(),
+        )
+        .unwrap();
+        assert_eq!(res.attributes[0].value, "1000");
+
+        let err = handle_harvest_swap_reply(
+            deps.as_mut(),
+            mock_env(),
+            reply,
+            HarvestState {
+                amount_out: Uint128::new(49),
+                ..harvest_state
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::AmountBelowMinimum { .. }));
+    }
+
+    #[test]
+    fn test_harvest_to_no_positions() {
+        let mut deps = mock_dependencies();
+        let err = harvest_to(
+            &mock_env(),
+            &mock_info("alice", &[]),
+            deps.as_mut(),
+            vec![],
+            "uosmo".to_string(),
+            Uint128::zero(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::NoPositionsProvided {}));
+    }
+
+    #[test]
+    fn test_set_swap_route() {
+        let mut deps = mock_dependencies();
+        CONFIG
+            .save(
+                deps.as_mut().storage,
+                &crate::state::Config {
+                    owner: Addr::unchecked("owner"),
+                    swap_mode: crate::state::SwapMode::ExactIn,
+                    max_spot_twap_deviation_bps: 100,
+                    twap_window_seconds: 600,
+                },
+            )
+            .unwrap();
+        let route = vec![
+            SwapRouteHop {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+            },
+            SwapRouteHop {
+                pool_id: 2,
+                token_out_denom: "uatom".to_string(),
+            },
+        ];
+
+        // Only the owner can set routes
+        assert!(matches!(
+            set_swap_route(
+                deps.as_mut(),
+                &mock_info("alice", &[]),
+                "uion".to_string(),
+                "uatom".to_string(),
+                route.clone(),
+            )
+            .unwrap_err(),
+            ContractError::Unauthorized {}
+        ));
+
+        // The route has to end in the token out denom
+        assert!(matches!(
+            set_swap_route(
+                deps.as_mut(),
+                &mock_info("owner", &[]),
+                "uion".to_string(),
+                "uosmo".to_string(),
+                route.clone(),
+            )
+            .unwrap_err(),
+            ContractError::InvalidSwapRoute { .. }
+        ));
+
+        set_swap_route(
+            deps.as_mut(),
+            &mock_info("owner", &[]),
+            "uion".to_string(),
+            "uatom".to_string(),
+            route.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            SWAP_ROUTES
+                .load(deps.as_ref().storage, ("uion", "uatom"))
+                .unwrap(),
+            route
+        );
+
+        // An empty route removes it
+        set_swap_route(
+            deps.as_mut(),
+            &mock_info("owner", &[]),
+            "uion".to_string(),
+            "uatom".to_string(),
+            vec![],
+        )
+        .unwrap();
+        assert!(SWAP_ROUTES
+            .may_load(deps.as_ref().storage, ("uion", "uatom"))
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,299 @@
+use cosmwasm_std::{Coin, Decimal256, Storage, Uint128};
+
+use crate::cl_sim::{
+    dec_from_uint, liquidity0, liquidity1, simulate_swap_exact_amount_in, PoolSnapshot,
+};
+use crate::tick::tick_to_sqrt_price;
+use crate::ContractError;
+
+// calc_two_sided_swap determines the swap that brings amount0 and amount1 to the ratio of assets needed
+// for a position in the given tick range. The token in excess is found at the current price and the amount
+// of it to swap is found by bisection over the swap simulator, so price impact and the spread factor are
+// accounted for the way the chain charges them. Returns None if no swap is needed.
+pub fn calc_two_sided_swap(
+    storage: &mut dyn Storage,
+    snapshot: &PoolSnapshot,
+    amount0: Uint128,
+    amount1: Uint128,
+    lower_tick: i64,
+    upper_tick: i64,
+) -> Result<Option<Coin>, ContractError> {
+    let sqrt_price_lower = tick_to_sqrt_price(lower_tick)?;
+    let sqrt_price_upper = tick_to_sqrt_price(upper_tick)?;
+    let range = SqrtPriceRange {
+        lower: sqrt_price_lower,
+        upper: sqrt_price_upper,
+    };
+
+    // Without any swap, either token0 or token1 limits the position and the other one is in excess
+    let zero_for_one = !range.over_swapped(true, snapshot.current_sqrt_price, amount0, amount1)?;
+    let (excess_denom, excess_amount) = if zero_for_one {
+        (&snapshot.token0, amount0)
+    } else {
+        (&snapshot.token1, amount1)
+    };
+
+    let over_swapped =
+        |storage: &mut dyn Storage, swap_amount: Uint128| -> Result<bool, ContractError> {
+            let swap = match simulate_swap_exact_amount_in(
+                storage,
+                snapshot,
+                &Coin {
+                    denom: excess_denom.clone(),
+                    amount: swap_amount,
+                },
+            ) {
+                Ok(swap) => swap,
+                // Swapping more than the pool can fill is always too much
+                Err(ContractError::NotEnoughLiquidityForSwap {}) => return Ok(true),
+                Err(err) => return Err(err),
+            };
+            let (amount0, amount1) = if zero_for_one {
+                (
+                    amount0.checked_sub(swap.token_in.amount)?,
+                    amount1.checked_add(swap.token_out.amount)?,
+                )
+            } else {
+                (
+                    amount0.checked_add(swap.token_out.amount)?,
+                    amount1.checked_sub(swap.token_in.amount)?,
+                )
+            };
+            range.over_swapped(zero_for_one, swap.end_sqrt_price, amount0, amount1)
+        };
+
+    if excess_amount.is_zero() || over_swapped(storage, Uint128::zero())? {
+        return Ok(None);
+    }
+    if !over_swapped(storage, excess_amount)? {
+        // Even swapping everything leaves the other token limiting the position
+        return Ok(Some(Coin {
+            denom: excess_denom.clone(),
+            amount: excess_amount,
+        }));
+    }
+
+    // Find the largest swap that does not overshoot the ratio of the range
+    let mut low = Uint128::zero();
+    let mut high = excess_amount;
+    while high - low > Uint128::one() {
+        let mid = low + (high - low) / Uint128::from(2u128);
+        if over_swapped(storage, mid)? {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    if low.is_zero() {
+        return Ok(None);
+    }
+    Ok(Some(Coin {
+        denom: excess_denom.clone(),
+        amount: low,
+    }))
+}
+
+struct SqrtPriceRange {
+    lower: Decimal256,
+    upper: Decimal256,
+}
+
+impl SqrtPriceRange {
+    // over_swapped reports whether, at the given sqrt price, the token swapped in (token0 if zero_for_one)
+    // no longer limits the position, meaning too much of it was swapped.
+    fn over_swapped(
+        &self,
+        zero_for_one: bool,
+        sqrt_price: Decimal256,
+        amount0: Uint128,
+        amount1: Uint128,
+    ) -> Result<bool, ContractError> {
+        if sqrt_price <= self.lower {
+            // Only token0 is needed, any token1 is left over
+            return Ok(zero_for_one);
+        }
+        if sqrt_price >= self.upper {
+            // Only token1 is needed, any token0 is left over
+            return Ok(!zero_for_one);
+        }
+
+        let liquidity_from_amount0 = liquidity0(dec_from_uint(amount0), sqrt_price, self.upper)?;
+        let liquidity_from_amount1 = liquidity1(dec_from_uint(amount1), self.lower, sqrt_price)?;
+        if zero_for_one {
+            Ok(liquidity_from_amount1 > liquidity_from_amount0)
+        } else {
+            Ok(liquidity_from_amount0 > liquidity_from_amount1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_dependencies;
+
+    use super::*;
+    use crate::cl_sim::{full_range_snapshot, simulate_create_position};
+    use crate::tick::{MAX_TICK, MIN_INITIALIZED_TICK};
+
+    // Swaps token_in and returns what is left over after creating the position
+    fn dust_after_join(
+        storage: &mut dyn Storage,
+        snapshot: &PoolSnapshot,
+        amount0: Uint128,
+        amount1: Uint128,
+        token_in: &Coin,
+        lower_tick: i64,
+        upper_tick: i64,
+    ) -> (Uint128, Uint128) {
+        let swap = simulate_swap_exact_amount_in(storage, snapshot, token_in).unwrap();
+        let (amount0, amount1) = if token_in.denom == snapshot.token0 {
+            (
+                amount0 - swap.token_in.amount,
+                amount1 + swap.token_out.amount,
+            )
+        } else {
+            (
+                amount0 + swap.token_out.amount,
+                amount1 - swap.token_in.amount,
+            )
+        };
+        let position = simulate_create_position(
+            swap.end_sqrt_price,
+            lower_tick,
+            upper_tick,
+            amount0,
+            amount1,
+        )
+        .unwrap();
+        (amount0 - position.amount0, amount1 - position.amount1)
+    }
+
+    #[test]
+    fn test_two_sided_swap_single_token() {
+        let mut deps = mock_dependencies();
+        let (lower_tick, upper_tick) = (MIN_INITIALIZED_TICK, MAX_TICK as i64);
+
+        // Without a spread factor a little less than half is swapped as the price moves against the swap,
+        // with a 1% spread factor a little more than half
+        for (spread_factor, min_swap, max_swap) in [("0", 4980, 5000), ("0.01", 5000, 5030)] {
+            let snapshot = full_range_snapshot(spread_factor);
+            let (amount0, amount1) = (Uint128::new(10000), Uint128::zero());
+
+            let token_in = calc_two_sided_swap(
+                deps.as_mut().storage,
+                &snapshot,
+                amount0,
+                amount1,
+                lower_tick,
+                upper_tick,
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(token_in.denom, "token0");
+            assert!(
+                token_in.amount > Uint128::new(min_swap)
+                    && token_in.amount < Uint128::new(max_swap),
+                "swap {token_in}"
+            );
+
+            let (dust0, dust1) = dust_after_join(
+                deps.as_mut().storage,
+                &snapshot,
+                amount0,
+                amount1,
+                &token_in,
+                lower_tick,
+                upper_tick,
+            );
+            assert!(dust0 <= Uint128::new(2), "dust0 {dust0}");
+            assert!(dust1 <= Uint128::new(2), "dust1 {dust1}");
+        }
+    }
+
+    #[test]
+    fn test_two_sided_swap_imbalanced() {
+        let mut deps = mock_dependencies();
+        let snapshot = full_range_snapshot("0.001");
+        let (lower_tick, upper_tick) = (-100000, 100000);
+        let (amount0, amount1) = (Uint128::new(1000), Uint128::new(9000));
+
+        let token_in = calc_two_sided_swap(
+            deps.as_mut().storage,
+            &snapshot,
+            amount0,
+            amount1,
+            lower_tick,
+            upper_tick,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(token_in.denom, "token1");
+
+        let (dust0, dust1) = dust_after_join(
+            deps.as_mut().storage,
+            &snapshot,
+            amount0,
+            amount1,
+            &token_in,
+            lower_tick,
+            upper_tick,
+        );
+        assert!(dust0 <= Uint128::new(2), "dust0 {dust0}");
+        assert!(dust1 <= Uint128::new(2), "dust1 {dust1}");
+    }
+
+    #[test]
+    fn test_two_sided_swap_not_needed() {
+        let mut deps = mock_dependencies();
+        let snapshot = full_range_snapshot("0");
+
+        // The range is above the current price, only token0 is needed
+        assert_eq!(
+            calc_two_sided_swap(
+                deps.as_mut().storage,
+                &snapshot,
+                Uint128::new(1000),
+                Uint128::zero(),
+                10000,
+                20000,
+            )
+            .unwrap(),
+            None
+        );
+
+        // Nothing to swap
+        assert_eq!(
+            calc_two_sided_swap(
+                deps.as_mut().storage,
+                &snapshot,
+                Uint128::zero(),
+                Uint128::zero(),
+                -100000,
+                100000,
+            )
+            .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_two_sided_swap_everything() {
+        let mut deps = mock_dependencies();
+        let snapshot = full_range_snapshot("0");
+
+        // The range is above the current price and stays above it after the swap, all token1 is swapped
+        assert_eq!(
+            calc_two_sided_swap(
+                deps.as_mut().storage,
+                &snapshot,
+                Uint128::zero(),
+                Uint128::new(1000),
+                10000,
+                20000,
+            )
+            .unwrap(),
+            Some(Coin::new(1000, "token1"))
+        );
+    }
+}
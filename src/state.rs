@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin, Decimal256, Uint128};
+use cosmwasm_std::{Addr, Coin, Decimal, Decimal256, Event, Uint128};
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -7,10 +7,46 @@ use serde::{Deserialize, Serialize};
 #[cw_serde]
 pub struct Config {
     pub owner: Addr,
+    /// How joins swap to the ratio of their range
+    pub swap_mode: SwapMode,
+    /// How far in basis points the spot price of a pool may deviate from its TWAP when joining,
+    /// unless a join sets its own limit
+    pub max_spot_twap_deviation_bps: u64,
+    /// The window the TWAP is taken over
+    pub twap_window_seconds: u64,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// How joins swap to the ratio of their range
+#[cw_serde]
+pub enum SwapMode {
+    /// Swap the estimated amount in with MsgSwapExactAmountIn
+    ExactIn,
+    /// Swap for the estimated amount out with MsgSwapExactAmountOut
+    ExactOut,
+}
+
+/// How a swap to the ratio of a range is sized
+#[cw_serde]
+#[derive(Eq)]
+pub enum SwapSizing {
+    /// By bisection over the swap simulator, accounting for the price impact and the spread factor
+    Simulated,
+    /// By the ratio of assets calc_asset_ratio_from_ticks gives for the range at the current tick
+    AssetRatio,
+}
+
+/// A hop of a swap route, swapping through `pool_id` for `token_out_denom`
+#[cw_serde]
+pub struct SwapRouteHop {
+    pub pool_id: u64,
+    pub token_out_denom: String,
+}
+
+/// Swap routes set by the owner, keyed by (token in denom, token out denom)
+pub const SWAP_ROUTES: Map<(&str, &str), Vec<SwapRouteHop>> = Map::new("swap_routes");
+
 #[cw_serde]
 pub struct TickExpIndexData {
     pub initial_price: Decimal256,
@@ -21,6 +57,18 @@ pub struct TickExpIndexData {
 
 pub const TICK_EXP_CACHE: Map<i64, TickExpIndexData> = Map::new("tick_exp_cache");
 
+/// What the contract does with the tokens once the swap has been executed
+#[cw_serde]
+#[derive(Eq)]
+pub enum PositionTarget {
+    /// Create a new position in the tick range of the reply state
+    NewPosition,
+    /// Create a full range position and superfluid delegate it to the validator
+    FullRangeSuperfluid { validator: String },
+    /// Add the tokens to an existing position of the user
+    ExistingPosition { position_id: u64 },
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct SwapMsgReplyState {
     pub pool_id: u64,
@@ -30,7 +78,147 @@ pub struct SwapMsgReplyState {
     pub token_min_amount0: Uint128,
     pub token_min_amount1: Uint128,
     pub token_provided_remaining_coin: Coin,
+    /// The token swapped in, the maximum of it for exact out swaps
+    pub token_in: Coin,
+    /// The amount swapped out of exact out swaps
+    pub exact_token_out_amount: Option<Uint128>,
     pub token_out_denom: String,
+    /// Amount of the token out the user provided besides the swap output
+    pub token_out_provided_amount: Uint128,
+    pub position_target: PositionTarget,
+    /// Whether a failed position message leaves the tokens with the user instead of reverting the join
+    pub best_effort: bool,
 }
 
 pub const SWAP_REPLY_STATES: Map<u64, SwapMsgReplyState> = Map::new("swap_reply_states");
+
+/// State of a best effort join, stored until the position message comes back
+#[cw_serde]
+pub struct JoinMsgReplyState {
+    pub pool_id: u64,
+    pub original_sender: Addr,
+    /// The tokens sent to the position message
+    pub tokens_provided: Vec<Coin>,
+    /// What the join records in the statistics once the position is created
+    pub provided: Vec<Coin>,
+    /// The token swapped in, if the join swapped
+    pub swapped: Option<Coin>,
+    pub dust: Vec<Coin>,
+    /// The join_position event, emitted once the position is created
+    pub position_event: Event,
+}
+
+pub const JOIN_REPLY_STATES: Map<u64, JoinMsgReplyState> = Map::new("join_reply_states");
+
+/// A position of a multi pool join
+#[cw_serde]
+pub struct MultiPoolLeg {
+    pub pool_id: u64,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    /// The share of the provided token that goes into the position as it is
+    pub token_provided_remaining: Uint128,
+    /// The share of the provided token swapped for the position, the swap output is split in proportion to it
+    pub token_in_amount: Uint128,
+}
+
+/// State of a multi pool join, stored until the swap through the deepest pool comes back
+#[cw_serde]
+pub struct MultiPoolJoinState {
+    pub original_sender: Addr,
+    /// The token swapped in for all positions together
+    pub token_in: Coin,
+    pub token_out_denom: String,
+    pub max_slippage: Decimal,
+    pub legs: Vec<MultiPoolLeg>,
+}
+
+pub const MULTI_POOL_REPLY_STATES: Map<u64, MultiPoolJoinState> =
+    Map::new("multi_pool_reply_states");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct WithdrawMsgReplyState {
+    pub pool_id: u64,
+    pub original_sender: Addr,
+    /// The tick range of the position created with the withdrawn assets
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    pub max_slippage: Decimal,
+    pub withdraw_min_amount0: Uint128,
+    pub withdraw_min_amount1: Uint128,
+    /// Whether the MsgExec collects the position's spread rewards and incentives before withdrawing it
+    pub collect_rewards: bool,
+    /// How the swap to the ratio of the new range is sized
+    pub swap_sizing: SwapSizing,
+}
+
+pub const WITHDRAW_REPLY_STATES: Map<u64, WithdrawMsgReplyState> =
+    Map::new("withdraw_reply_states");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CompoundState {
+    pub pool_id: u64,
+    pub position_id: u64,
+    pub original_sender: Addr,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    pub max_slippage: Decimal,
+}
+
+pub const COLLECT_REPLY_STATES: Map<u64, CompoundState> = Map::new("collect_reply_states");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RewardSwapMsgReplyState {
+    pub compound: CompoundState,
+    /// Rewards collected in the pool's tokens, the reward swap outputs are added to these
+    pub amount0: Uint128,
+    pub amount1: Uint128,
+    /// The token out denom of each swap in the MsgExec, in order
+    pub token_out_denoms: Vec<String>,
+}
+
+pub const REWARD_SWAP_REPLY_STATES: Map<u64, RewardSwapMsgReplyState> =
+    Map::new("reward_swap_reply_states");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct HarvestState {
+    pub original_sender: Addr,
+    pub target_denom: String,
+    pub min_out: Uint128,
+    /// Amount of the target denom received so far
+    pub amount_out: Uint128,
+    /// Number of reward swaps in the MsgExec
+    pub swap_count: u64,
+}
+
+pub const HARVEST_REPLY_STATES: Map<u64, HarvestState> = Map::new("harvest_reply_states");
+
+/// State of a GAMM pool exit, stored until the exit assets are joined into the CL position
+#[cw_serde]
+pub struct ExitPoolMsgReplyState {
+    /// The CL pool to join
+    pub pool_id: u64,
+    pub original_sender: Addr,
+    pub lower_tick: i64,
+    pub upper_tick: i64,
+    pub max_slippage: Decimal,
+}
+
+pub const EXIT_POOL_REPLY_STATES: Map<u64, ExitPoolMsgReplyState> =
+    Map::new("exit_pool_reply_states");
+
+/// Statistics of the joins of a pool or of all pools
+#[cw_serde]
+#[derive(Default)]
+pub struct JoinStats {
+    pub joins: u64,
+    /// Tokens provided to the joins per denom
+    pub provided: Vec<Coin>,
+    /// Tokens swapped in by the joins per denom
+    pub swapped: Vec<Coin>,
+    /// Tokens the joins are expected to have left over per denom
+    pub dust: Vec<Coin>,
+}
+
+pub const POOL_STATS: Map<u64, JoinStats> = Map::new("pool_stats");
+pub const GLOBAL_STATS: Item<JoinStats> = Item::new("global_stats");
@@ -0,0 +1,120 @@
+use cosmwasm_std::{Coin, Deps, StdResult, Storage};
+
+use crate::state::{JoinStats, GLOBAL_STATS, POOL_STATS};
+use crate::ContractError;
+
+// record_join adds a join to the statistics of its pool and the global ones
+pub(crate) fn record_join(
+    storage: &mut dyn Storage,
+    pool_id: u64,
+    provided: &[Coin],
+    swapped: Option<&Coin>,
+    dust: &[Coin],
+) -> Result<(), ContractError> {
+    let update = |mut stats: JoinStats| -> Result<JoinStats, ContractError> {
+        stats.joins += 1;
+        add_coins(&mut stats.provided, provided)?;
+        add_coins(&mut stats.swapped, swapped)?;
+        add_coins(&mut stats.dust, dust)?;
+        Ok(stats)
+    };
+
+    let pool_stats = POOL_STATS.may_load(storage, pool_id)?.unwrap_or_default();
+    POOL_STATS.save(storage, pool_id, &update(pool_stats)?)?;
+    let global_stats = GLOBAL_STATS.may_load(storage)?.unwrap_or_default();
+    GLOBAL_STATS.save(storage, &update(global_stats)?)?;
+    Ok(())
+}
+
+// add_coins adds the coins to the totals per denom, keeping the totals sorted by denom
+fn add_coins<'a>(
+    totals: &mut Vec<Coin>,
+    coins: impl IntoIterator<Item = &'a Coin>,
+) -> Result<(), ContractError> {
+    for coin in coins {
+        if coin.amount.is_zero() {
+            continue;
+        }
+        match totals.binary_search_by(|total| total.denom.cmp(&coin.denom)) {
+            Ok(index) => totals[index].amount = totals[index].amount.checked_add(coin.amount)?,
+            Err(index) => totals.insert(index, coin.clone()),
+        }
+    }
+    Ok(())
+}
+
+pub fn query_pool_stats(deps: Deps, pool_id: u64) -> StdResult<JoinStats> {
+    Ok(POOL_STATS
+        .may_load(deps.storage, pool_id)?
+        .unwrap_or_default())
+}
+
+pub fn query_global_stats(deps: Deps) -> StdResult<JoinStats> {
+    Ok(GLOBAL_STATS.may_load(deps.storage)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::mock_dependencies;
+
+    use super::*;
+
+    #[test]
+    fn test_record_join() {
+        let mut deps = mock_dependencies();
+
+        record_join(
+            deps.as_mut().storage,
+            1,
+            &[Coin::new(1000, "token1")],
+            Some(&Coin::new(500, "token1")),
+            &[Coin::new(2, "token0"), Coin::new(0, "token1")],
+        )
+        .unwrap();
+        record_join(
+            deps.as_mut().storage,
+            1,
+            &[Coin::new(1000, "token0"), Coin::new(10, "token1")],
+            None,
+            &[Coin::new(1, "token0")],
+        )
+        .unwrap();
+        record_join(
+            deps.as_mut().storage,
+            2,
+            &[Coin::new(300, "token2")],
+            Some(&Coin::new(150, "token2")),
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            query_pool_stats(deps.as_ref(), 1).unwrap(),
+            JoinStats {
+                joins: 2,
+                provided: vec![Coin::new(1000, "token0"), Coin::new(1010, "token1")],
+                swapped: vec![Coin::new(500, "token1")],
+                dust: vec![Coin::new(3, "token0")],
+            }
+        );
+        assert_eq!(
+            query_global_stats(deps.as_ref()).unwrap(),
+            JoinStats {
+                joins: 3,
+                provided: vec![
+                    Coin::new(1000, "token0"),
+                    Coin::new(1010, "token1"),
+                    Coin::new(300, "token2")
+                ],
+                swapped: vec![Coin::new(500, "token1"), Coin::new(150, "token2")],
+                dust: vec![Coin::new(3, "token0")],
+            }
+        );
+
+        // Pools without joins have empty statistics
+        assert_eq!(
+            query_pool_stats(deps.as_ref(), 3).unwrap(),
+            JoinStats::default()
+        );
+    }
+}
@@ -1,7 +1,7 @@
 mod modules;
 mod tests;
 
-use crate::test_tube::modules::{Authz, SingleSidedLpCl};
+use crate::test_tube::modules::{Authz, SingleSidedLpCl, TWAP_WINDOW_SECONDS};
 
 use cosmwasm_std::{Coin, Uint128};
 use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
@@ -92,6 +92,9 @@ impl<'a> TestEnvBuilder<'a> {
         };
         let _position = cl.create_position(initial_position, &admin).unwrap();
 
+        // Build up the TWAP window of the pool
+        app.increase_time(TWAP_WINDOW_SECONDS);
+
         TestEnv {
             authz,
             bank,
@@ -3,7 +3,11 @@ use osmosis_std::{
     types::{
         cosmos::authz::v1beta1::{GenericAuthorization, Grant, MsgGrant, MsgGrantResponse},
         osmosis::{
-            concentratedliquidity::v1beta1::MsgCreatePosition,
+            concentratedliquidity::v1beta1::{
+                MsgAddToPosition, MsgCollectIncentives, MsgCollectSpreadRewards, MsgCreatePosition,
+                MsgWithdrawPosition,
+            },
+            gamm::v1beta1::MsgExitPool,
             poolmanager::v1beta1::MsgSwapExactAmountIn,
         },
     },
@@ -62,4 +66,51 @@ impl Authz<'_, OsmosisTestApp> {
 
         Ok(())
     }
+
+    pub fn grant_required_authz_for_rebalance(
+        &self,
+        user: &SigningAccount,
+        contract_addr: &str,
+    ) -> Result<(), RunnerError> {
+        self.grant_generic_authz(user, contract_addr, MsgWithdrawPosition::TYPE_URL)?;
+        self.grant_required_authz_for_lp(user, contract_addr)?;
+
+        Ok(())
+    }
+
+    pub fn grant_required_authz_for_change_range(
+        &self,
+        user: &SigningAccount,
+        contract_addr: &str,
+    ) -> Result<(), RunnerError> {
+        self.grant_generic_authz(user, contract_addr, MsgCollectSpreadRewards::TYPE_URL)?;
+        self.grant_generic_authz(user, contract_addr, MsgCollectIncentives::TYPE_URL)?;
+        self.grant_required_authz_for_rebalance(user, contract_addr)?;
+
+        Ok(())
+    }
+
+    pub fn grant_required_authz_for_migrate_from_gamm(
+        &self,
+        user: &SigningAccount,
+        contract_addr: &str,
+    ) -> Result<(), RunnerError> {
+        self.grant_generic_authz(user, contract_addr, MsgExitPool::TYPE_URL)?;
+        self.grant_required_authz_for_lp(user, contract_addr)?;
+
+        Ok(())
+    }
+
+    pub fn grant_required_authz_for_compound(
+        &self,
+        user: &SigningAccount,
+        contract_addr: &str,
+    ) -> Result<(), RunnerError> {
+        self.grant_generic_authz(user, contract_addr, MsgCollectSpreadRewards::TYPE_URL)?;
+        self.grant_generic_authz(user, contract_addr, MsgCollectIncentives::TYPE_URL)?;
+        self.grant_generic_authz(user, contract_addr, MsgSwapExactAmountIn::TYPE_URL)?;
+        self.grant_generic_authz(user, contract_addr, MsgAddToPosition::TYPE_URL)?;
+
+        Ok(())
+    }
 }
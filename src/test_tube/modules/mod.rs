@@ -4,4 +4,4 @@ mod authz;
 mod single_sided_lp_cl;
 
 pub use authz::Authz;
-pub use single_sided_lp_cl::SingleSidedLpCl;
+pub use single_sided_lp_cl::{SingleSidedLpCl, TWAP_WINDOW_SECONDS};
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::state::SwapMode;
 use cosmwasm_std::Coin;
 use osmosis_std::types::cosmwasm::wasm::v1::MsgExecuteContractResponse;
 use osmosis_test_tube::{
@@ -13,6 +14,9 @@ pub struct SingleSidedLpCl<'a> {
     pub contract_addr: String,
 }
 
+/// The TWAP window the contract is deployed with, pools need to be at least this old to be joined
+pub const TWAP_WINDOW_SECONDS: u64 = 60;
+
 impl<'a> SingleSidedLpCl<'a> {
     pub fn deploy(app: &'a OsmosisTestApp, signer: &SigningAccount) -> Result<Self, RunnerError> {
         let wasm = Wasm::new(app);
@@ -26,6 +30,9 @@ impl<'a> SingleSidedLpCl<'a> {
                 code_id,
                 &InstantiateMsg {
                     owner: signer.address(),
+                    swap_mode: SwapMode::ExactIn,
+                    max_spot_twap_deviation_bps: 500,
+                    twap_window_seconds: TWAP_WINDOW_SECONDS,
                 },
                 None,
                 None,
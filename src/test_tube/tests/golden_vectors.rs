@@ -0,0 +1,322 @@
+#[cfg(test)]
+mod tests {
+    use crate::cl_sim::{InitializedTick, PoolSnapshot};
+    use crate::golden::{
+        AmountDeltaVector, GoldenVectors, PositionVector, SwapVector, TickVector,
+        UnitLiquidityVector, FIXTURE_PATH,
+    };
+    use crate::test_tube::{TestEnv, UBAR, UFOO};
+    use crate::tick::{MAX_TICK, MIN_INITIALIZED_TICK};
+    use cosmwasm_std::{Coin, Decimal, Uint128};
+    use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+        CreateConcentratedLiquidityPoolsProposal, LiquidityNetInDirectionRequest,
+        MsgCreatePosition, Pool, PoolRecord, PoolsRequest, PositionByIdRequest,
+        PositionByIdResponse,
+    };
+    use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+        MsgSwapExactAmountIn, SwapAmountInRoute,
+    };
+    use osmosis_test_tube::{
+        Account, ConcentratedLiquidity, GovWithAppAccess, Module, OsmosisTestApp, PoolManager,
+        Runner,
+    };
+    use prost::Message;
+    use std::str::FromStr;
+
+    fn query_pool(cl: &ConcentratedLiquidity<OsmosisTestApp>, pool_id: u64) -> Pool {
+        let pools = cl.query_pools(&PoolsRequest { pagination: None }).unwrap();
+        pools
+            .pools
+            .iter()
+            .map(|pool| Pool::decode(pool.value.as_slice()).unwrap())
+            .find(|pool| pool.id == pool_id)
+            .unwrap()
+    }
+
+    // query_snapshot builds the snapshot the contract would from the chain's liquidity net in both directions
+    fn query_snapshot(cl: &ConcentratedLiquidity<OsmosisTestApp>, pool: &Pool) -> PoolSnapshot {
+        let mut ticks = vec![];
+        for token_in in [&pool.token0, &pool.token1] {
+            let liquidity_net = cl
+                .query_liquidity_net_in_direction(&LiquidityNetInDirectionRequest {
+                    pool_id: pool.id,
+                    token_in: token_in.clone(),
+                    start_tick: 0,
+                    use_cur_tick: true,
+                    bound_tick: 0,
+                    use_no_bound: true,
+                })
+                .unwrap();
+            for depth in liquidity_net.liquidity_depths {
+                ticks.push(
+                    InitializedTick::from_liquidity_net(depth.tick_index, &depth.liquidity_net)
+                        .unwrap(),
+                );
+            }
+        }
+        PoolSnapshot::new(pool, ticks).unwrap()
+    }
+
+    // capture_golden_vectors swaps and creates positions on the chain and writes what it returned to the
+    // golden vectors fixture
+    #[test]
+    #[ignore]
+    fn capture_golden_vectors() {
+        let app = OsmosisTestApp::new();
+        let _t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+        let pm = PoolManager::new(&app);
+
+        let gov = GovWithAppAccess::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(10_000_000_000_000, UFOO),
+                Coin::new(10_000_000_000_000, UBAR),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // A narrow position gives the swaps ticks to cross
+        cl.create_position(
+            MsgCreatePosition {
+                pool_id: 1,
+                sender: alice.address(),
+                lower_tick: -1000,
+                upper_tick: 1000,
+                tokens_provided: vec![
+                    Coin::new(1_000_000, UFOO).into(),
+                    Coin::new(1_000_000, UBAR).into(),
+                ],
+                token_min_amount0: "0".to_string(),
+                token_min_amount1: "0".to_string(),
+            },
+            &alice,
+        )
+        .unwrap();
+
+        let mut vectors = GoldenVectors {
+            swaps: vec![],
+            positions: vec![],
+            ticks: vec![],
+            amount_deltas: vec![],
+            unit_liquidity: vec![],
+        };
+
+        for (amount, denom, token_out_denom) in [
+            (1, UFOO, UBAR),
+            (1_000, UFOO, UBAR),
+            (600_000, UFOO, UBAR),
+            (2_500_000, UBAR, UFOO),
+            (10, UBAR, UFOO),
+            (1_000_000, UFOO, UBAR),
+        ] {
+            let pool = query_pool(&cl, 1);
+            let snapshot = query_snapshot(&cl, &pool);
+            let token_out_amount = pm
+                .swap_exact_amount_in(
+                    MsgSwapExactAmountIn {
+                        sender: alice.address(),
+                        routes: vec![SwapAmountInRoute {
+                            pool_id: pool.id,
+                            token_out_denom: token_out_denom.to_string(),
+                        }],
+                        token_in: Some(Coin::new(amount, denom).into()),
+                        token_out_min_amount: "1".to_string(),
+                    },
+                    &alice,
+                )
+                .unwrap()
+                .data
+                .token_out_amount;
+            let end_pool = query_pool(&cl, 1);
+            vectors.swaps.push(SwapVector {
+                pool: snapshot,
+                token_in: Coin::new(amount, denom),
+                token_out: Coin {
+                    denom: token_out_denom.to_string(),
+                    amount: Uint128::from_str(&token_out_amount).unwrap(),
+                },
+                end_tick: end_pool.current_tick,
+                end_sqrt_price: end_pool.current_sqrt_price,
+            });
+        }
+
+        for (lower_tick, upper_tick, amount0, amount1) in [
+            (-1000, 1000, 100_000, 100_000),
+            (-5000, -100, 100_000, 100_000),
+            (100, 5000, 100_000, 100_000),
+            (MIN_INITIALIZED_TICK, MAX_TICK as i64, 123_456, 654_321),
+        ] {
+            let pool = query_pool(&cl, 1);
+            let position = cl
+                .create_position(
+                    MsgCreatePosition {
+                        pool_id: pool.id,
+                        sender: alice.address(),
+                        lower_tick,
+                        upper_tick,
+                        tokens_provided: vec![
+                            Coin::new(amount0, UFOO).into(),
+                            Coin::new(amount1, UBAR).into(),
+                        ],
+                        token_min_amount0: "0".to_string(),
+                        token_min_amount1: "0".to_string(),
+                    },
+                    &alice,
+                )
+                .unwrap()
+                .data;
+            vectors.positions.push(PositionVector {
+                sqrt_price: pool.current_sqrt_price,
+                lower_tick,
+                upper_tick,
+                amount0: Uint128::new(amount0),
+                amount1: Uint128::new(amount1),
+                used_amount0: Uint128::from_str(&position.amount0).unwrap(),
+                used_amount1: Uint128::from_str(&position.amount1).unwrap(),
+                liquidity_created: position.liquidity_created,
+            });
+        }
+
+        // Positions far above and below the current price only hold token0 and token1 respectively
+        for (lower_tick, upper_tick, amount) in [
+            (200_000, 300_000, 1_000_000),
+            (1_000_000, 9_000_000, 123_457),
+            (-300_000, -200_000, 1_000_000),
+            (-5_000_000, -1_000_000, 123_457),
+        ] {
+            let is_token0 = lower_tick > 0;
+            let denom = if is_token0 { UFOO } else { UBAR };
+            let created = cl
+                .create_position(
+                    MsgCreatePosition {
+                        pool_id: 1,
+                        sender: alice.address(),
+                        lower_tick,
+                        upper_tick,
+                        tokens_provided: vec![Coin::new(amount, denom).into()],
+                        token_min_amount0: "0".to_string(),
+                        token_min_amount1: "0".to_string(),
+                    },
+                    &alice,
+                )
+                .unwrap()
+                .data;
+            let breakdown = app
+                .query::<_, PositionByIdResponse>(
+                    "/osmosis.concentratedliquidity.v1beta1.Query/PositionById",
+                    &PositionByIdRequest {
+                        position_id: created.position_id,
+                    },
+                )
+                .unwrap()
+                .position
+                .unwrap();
+            let (amount_round_up, asset) = if is_token0 {
+                (created.amount0, breakdown.asset0)
+            } else {
+                (created.amount1, breakdown.asset1)
+            };
+            vectors.amount_deltas.push(AmountDeltaVector {
+                lower_tick,
+                upper_tick,
+                liquidity: breakdown.position.unwrap().liquidity,
+                is_token0,
+                amount_round_up: Uint128::from_str(&amount_round_up).unwrap(),
+                amount_round_down: Uint128::from_str(&asset.unwrap().amount).unwrap(),
+            });
+        }
+
+        // Every new pool is initialized at the spot price amount1 / amount0 of its first position. The prices
+        // are on a tick, so the chain has to put the pool at the tick of exactly that price.
+        let prices = ["1", "1.5", "10", "0.1", "0.0001234", "123456.7", "0.000001"];
+        gov.propose_and_execute(
+            CreateConcentratedLiquidityPoolsProposal::TYPE_URL.to_string(),
+            CreateConcentratedLiquidityPoolsProposal {
+                title: "Create concentrated ufoo:ubar pools".to_string(),
+                description: "Create concentrated ufoo:ubar pools at the golden vector prices"
+                    .to_string(),
+                pool_records: prices
+                    .iter()
+                    .map(|_| PoolRecord {
+                        denom0: UFOO.to_string(),
+                        denom1: UBAR.to_string(),
+                        tick_spacing: 100,
+                        spread_factor: "100000000000000".to_string(),
+                    })
+                    .collect(),
+            },
+            alice.address(),
+            false,
+            &alice,
+        )
+        .unwrap();
+        for (i, price) in prices.iter().enumerate() {
+            let pool_id = i as u64 + 2;
+            let amount0 = Uint128::new(10_000_000);
+            let amount1 = amount0 * Decimal::from_str(price).unwrap();
+            cl.create_position(
+                MsgCreatePosition {
+                    pool_id,
+                    sender: alice.address(),
+                    lower_tick: MIN_INITIALIZED_TICK,
+                    upper_tick: MAX_TICK as i64,
+                    tokens_provided: vec![
+                        Coin::new(amount0.u128(), UFOO).into(),
+                        Coin::new(amount1.u128(), UBAR).into(),
+                    ],
+                    token_min_amount0: "0".to_string(),
+                    token_min_amount1: "0".to_string(),
+                },
+                &alice,
+            )
+            .unwrap();
+            let current_tick = query_pool(&cl, pool_id).current_tick;
+            vectors.ticks.push(TickVector {
+                price: price.to_string(),
+                tick: current_tick,
+            });
+
+            // The pool sits exactly on the tick of its price, which is what the execute helpers assume
+            let spacing_below = current_tick.div_euclid(100) * 100;
+            for (lower_tick, upper_tick) in [
+                (spacing_below - 1_000, spacing_below + 1_100),
+                (spacing_below - 100_000, spacing_below + 5_000),
+            ] {
+                let position = cl
+                    .create_position(
+                        MsgCreatePosition {
+                            pool_id,
+                            sender: alice.address(),
+                            lower_tick,
+                            upper_tick,
+                            tokens_provided: vec![
+                                Coin::new(1_000_000_000, UFOO).into(),
+                                Coin::new(1_000_000_000, UBAR).into(),
+                            ],
+                            token_min_amount0: "0".to_string(),
+                            token_min_amount1: "0".to_string(),
+                        },
+                        &alice,
+                    )
+                    .unwrap()
+                    .data;
+                vectors.unit_liquidity.push(UnitLiquidityVector {
+                    current_tick,
+                    lower_tick,
+                    upper_tick,
+                    liquidity_created: position.liquidity_created,
+                    amount0: Uint128::from_str(&position.amount0).unwrap(),
+                    amount1: Uint128::from_str(&position.amount1).unwrap(),
+                });
+            }
+        }
+
+        std::fs::write(
+            FIXTURE_PATH,
+            serde_json_wasm::to_string(&vectors).unwrap() + "\n",
+        )
+        .unwrap();
+    }
+}
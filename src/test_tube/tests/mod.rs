@@ -1 +1,2 @@
+mod golden_vectors;
 mod swap_and_join;
@@ -1,13 +1,47 @@
 #[cfg(test)]
 mod tests {
-    use crate::msg::ExecuteMsg;
+    use crate::msg::{ExecuteMsg, RangeSpec};
     use crate::test_tube::{TestEnv, UBAR, UFOO};
-    use cosmwasm_std::{Coin, Uint128};
-    use osmosis_std::types::cosmos::bank::v1beta1::QueryAllBalancesRequest;
-    use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{Pool, PoolsRequest};
+    use crate::tick::{MAX_TICK, MIN_INITIALIZED_TICK};
+    use cosmwasm_std::{Coin, Decimal, Uint128};
+    use osmosis_std::types::cosmos::bank::v1beta1::{QueryAllBalancesRequest, QueryBalanceRequest};
+    use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+        CreateConcentratedLiquidityPoolsProposal, MsgCreatePosition, Pool, PoolRecord,
+        PoolsRequest, UserPositionsRequest,
+    };
+    use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+        MsgSwapExactAmountIn, SwapAmountInRoute,
+    };
+    use osmosis_std::types::osmosis::superfluid::v1beta1::SetSuperfluidAssetsProposal;
+    use osmosis_std::types::osmosis::superfluid::{
+        MsgCreateFullRangePositionAndSuperfluidDelegate, SuperfluidAsset, SuperfluidAssetType,
+        UserConcentratedSuperfluidPositionsDelegatedRequest,
+        UserConcentratedSuperfluidPositionsDelegatedResponse,
+    };
     use osmosis_test_tube::OsmosisTestApp;
-    use osmosis_test_tube::{Account, ConcentratedLiquidity, Module};
+    use osmosis_test_tube::{
+        Account, Bank, ConcentratedLiquidity, Gamm, GovWithAppAccess, Module, PoolManager, Runner,
+        SigningAccount,
+    };
     use prost::Message;
+    use std::str::FromStr;
+
+    // query_balance returns the amount of the denom the account holds
+    fn query_balance(
+        bank: &Bank<OsmosisTestApp>,
+        account: &SigningAccount,
+        denom: &str,
+    ) -> Uint128 {
+        let balance = bank
+            .query_balance(&QueryBalanceRequest {
+                address: account.address(),
+                denom: denom.to_string(),
+            })
+            .unwrap()
+            .balance
+            .unwrap();
+        Uint128::from_str(&balance.amount).unwrap()
+    }
 
     #[test]
     fn test_single_sided_swap_and_join_amt_0_in() {
@@ -60,13 +94,17 @@ mod tests {
             .execute(
                 &ExecuteMsg::SingleSidedSwapAndJoin {
                     pool_id: 1,
-                    lower_tick: -200,
-                    upper_tick: 342000000,
-                    // lower_tick: -108000000,
-                    // upper_tick: 342000000,
+                    range: RangeSpec::Ticks {
+                        lower: -200,
+                        upper: 342000000,
+                    },
                     token_provided: Coin::new(1_000_000, UFOO),
                     token_min_amount0: Uint128::zero(),
                     token_min_amount1: Uint128::zero(),
+                    max_spot_twap_deviation_bps: None,
+                    expected_sqrt_price: None,
+                    deadline: None,
+                    best_effort: None,
                 },
                 &[], // nil for the funds parameter
                 &alice,
@@ -163,13 +201,17 @@ mod tests {
             .execute(
                 &ExecuteMsg::SingleSidedSwapAndJoin {
                     pool_id: 1,
-                    lower_tick: -108000000,
-                    upper_tick: 342000,
-                    // lower_tick: -108000000,
-                    // upper_tick: 342000000,
+                    range: RangeSpec::Ticks {
+                        lower: -108000000,
+                        upper: 342000,
+                    },
                     token_provided: Coin::new(1_000_000, UBAR),
                     token_min_amount0: Uint128::zero(),
                     token_min_amount1: Uint128::zero(),
+                    max_spot_twap_deviation_bps: None,
+                    expected_sqrt_price: None,
+                    deadline: None,
+                    best_effort: None,
                 },
                 &[], // nil for the funds parameter
                 &alice,
@@ -253,13 +295,17 @@ mod tests {
             .execute(
                 &ExecuteMsg::SingleSidedSwapAndJoin {
                     pool_id: 1,
-                    lower_tick: -100,
-                    upper_tick: 100,
-                    // lower_tick: -108000000,
-                    // upper_tick: 342000000,
+                    range: RangeSpec::Ticks {
+                        lower: -100,
+                        upper: 100,
+                    },
                     token_provided: Coin::new(50_000_000, UFOO),
                     token_min_amount0: Uint128::zero(),
                     token_min_amount1: Uint128::zero(),
+                    max_spot_twap_deviation_bps: None,
+                    expected_sqrt_price: None,
+                    deadline: None,
+                    best_effort: None,
                 },
                 &[], // nil for the funds parameter
                 &alice,
@@ -321,13 +367,17 @@ mod tests {
             .execute(
                 &ExecuteMsg::SingleSidedSwapAndJoin {
                     pool_id: 1,
-                    lower_tick: -100,
-                    upper_tick: 100,
-                    // lower_tick: -108000000,
-                    // upper_tick: 342000000,
+                    range: RangeSpec::Ticks {
+                        lower: -100,
+                        upper: 100,
+                    },
                     token_provided: Coin::new(75_000_000, UBAR),
                     token_min_amount0: Uint128::zero(),
                     token_min_amount1: Uint128::zero(),
+                    max_spot_twap_deviation_bps: None,
+                    expected_sqrt_price: None,
+                    deadline: None,
+                    best_effort: None,
                 },
                 &[], // nil for the funds parameter
                 &alice,
@@ -350,4 +400,703 @@ mod tests {
         println!("{:?}", pool);
         println!();
     }
+
+    #[test]
+    fn test_full_range_swap_and_join() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Have alice give the contract authz permissions
+        t.authz
+            .grant_required_authz_for_lp(&alice, t.single_sided_lp_cl.contract_addr.as_str())
+            .unwrap();
+
+        t.single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::FullRangeSwapAndJoin {
+                    pool_id: 1,
+                    token_provided: Coin::new(1_000_000, UFOO),
+                    token_min_amount0: Uint128::zero(),
+                    token_min_amount1: Uint128::zero(),
+                    superfluid_validator: None,
+                    max_spot_twap_deviation_bps: None,
+                    best_effort: None,
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap();
+
+        // The position spans the full range of the pool
+        let positions = cl
+            .query_user_positions(&UserPositionsRequest {
+                address: alice.address(),
+                pool_id: 1,
+                pagination: None,
+            })
+            .unwrap();
+        let position = positions.positions[0].position.clone().unwrap();
+        assert_eq!(position.lower_tick, MIN_INITIALIZED_TICK);
+        assert_eq!(position.upper_tick, MAX_TICK as i64);
+        // Both tokens went into it
+        for asset in [
+            &positions.positions[0].asset0,
+            &positions.positions[0].asset1,
+        ] {
+            let amount: u128 = asset.as_ref().unwrap().amount.parse().unwrap();
+            assert!(amount > 0);
+        }
+
+        // All but the rounding dust of the provided token went into the swap or the position
+        let resp = t
+            .bank
+            .query_balance(&QueryBalanceRequest {
+                address: alice.address(),
+                denom: UFOO.to_string(),
+            })
+            .unwrap();
+        let ufoo_left: u128 = resp.balance.unwrap().amount.parse().unwrap();
+        assert!(ufoo_left < 1_000);
+    }
+
+    #[test]
+    fn test_full_range_swap_and_join_superfluid() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+        let gov = GovWithAppAccess::new(&app);
+
+        let admin = app
+            .init_account(&[
+                Coin::new(100_000_000_000_000, UBAR),
+                Coin::new(100_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Superfluid positions need an OSMO pool with OSMO as token0 that is a superfluid asset
+        gov.propose_and_execute(
+            CreateConcentratedLiquidityPoolsProposal::TYPE_URL.to_string(),
+            CreateConcentratedLiquidityPoolsProposal {
+                title: "Create concentrated uosmo:ubar pool".to_string(),
+                description:
+                    "Create concentrated uosmo:ubar pool, so that we can superfluid stake it"
+                        .to_string(),
+                pool_records: vec![PoolRecord {
+                    denom0: "uosmo".to_string(),
+                    denom1: UBAR.to_string(),
+                    tick_spacing: 100,
+                    spread_factor: "100000000000000".to_string(),
+                }],
+            },
+            admin.address(),
+            false,
+            &admin,
+        )
+        .unwrap();
+        cl.create_position(
+            MsgCreatePosition {
+                pool_id: 2,
+                sender: admin.address(),
+                lower_tick: MIN_INITIALIZED_TICK,
+                upper_tick: MAX_TICK as i64,
+                tokens_provided: vec![
+                    Coin::new(100_000_000, "uosmo").into(),
+                    Coin::new(100_000_000, UBAR).into(),
+                ],
+                token_min_amount0: "1".to_string(),
+                token_min_amount1: "1".to_string(),
+            },
+            &admin,
+        )
+        .unwrap();
+        gov.propose_and_execute(
+            SetSuperfluidAssetsProposal::TYPE_URL.to_string(),
+            SetSuperfluidAssetsProposal {
+                title: "Set superfluid assets".to_string(),
+                description: "Make the uosmo:ubar position shares a superfluid asset".to_string(),
+                assets: vec![SuperfluidAsset {
+                    denom: "cl/pool/2".to_string(),
+                    asset_type: SuperfluidAssetType::ConcentratedShare.into(),
+                }],
+            },
+            admin.address(),
+            false,
+            &admin,
+        )
+        .unwrap();
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UBAR),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Have alice give the contract authz permissions, including the superfluid position message
+        t.authz
+            .grant_required_authz_for_lp(&alice, t.single_sided_lp_cl.contract_addr.as_str())
+            .unwrap();
+        t.authz
+            .grant_generic_authz(
+                &alice,
+                t.single_sided_lp_cl.contract_addr.as_str(),
+                MsgCreateFullRangePositionAndSuperfluidDelegate::TYPE_URL,
+            )
+            .unwrap();
+
+        let validator = app.get_first_validator_address().unwrap();
+        t.single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::FullRangeSwapAndJoin {
+                    pool_id: 2,
+                    token_provided: Coin::new(1_000_000, UBAR),
+                    token_min_amount0: Uint128::zero(),
+                    token_min_amount1: Uint128::zero(),
+                    superfluid_validator: Some(validator.clone()),
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap();
+
+        // The position spans the full range of the pool
+        let positions = cl
+            .query_user_positions(&UserPositionsRequest {
+                address: alice.address(),
+                pool_id: 2,
+                pagination: None,
+            })
+            .unwrap();
+        assert_eq!(positions.positions.len(), 1);
+        let position = positions.positions[0].position.clone().unwrap();
+        assert_eq!(position.lower_tick, MIN_INITIALIZED_TICK);
+        assert_eq!(position.upper_tick, MAX_TICK as i64);
+
+        // And it is superfluid delegated to the validator
+        let delegated: UserConcentratedSuperfluidPositionsDelegatedResponse = app
+            .query(
+                "/osmosis.superfluid.Query/UserConcentratedSuperfluidPositionsDelegated",
+                &UserConcentratedSuperfluidPositionsDelegatedRequest {
+                    delegator_address: alice.address(),
+                },
+            )
+            .unwrap();
+        assert_eq!(delegated.cl_pool_user_position_records.len(), 1);
+        let record = &delegated.cl_pool_user_position_records[0];
+        assert_eq!(record.position_id, position.position_id);
+        assert_eq!(record.validator_address, validator);
+    }
+
+    #[test]
+    fn test_balanced_join() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(100_000, UBAR),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Have alice give the contract authz permissions
+        t.authz
+            .grant_required_authz_for_lp(&alice, t.single_sided_lp_cl.contract_addr.as_str())
+            .unwrap();
+
+        // The range around the current price needs about as much of both tokens, so part of UFOO is swapped
+        t.single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::BalancedJoin {
+                    pool_id: 1,
+                    range: RangeSpec::Ticks {
+                        lower: -1000,
+                        upper: 1000,
+                    },
+                    tokens_provided: vec![Coin::new(1_000_000, UFOO), Coin::new(100_000, UBAR)],
+                    max_slippage: Decimal::percent(1),
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap();
+
+        let positions = cl
+            .query_user_positions(&UserPositionsRequest {
+                address: alice.address(),
+                pool_id: 1,
+                pagination: None,
+            })
+            .unwrap();
+        assert_eq!(positions.positions.len(), 1);
+        let position = positions.positions[0].position.clone().unwrap();
+        assert_eq!(position.lower_tick, -1000);
+        assert_eq!(position.upper_tick, 1000);
+
+        // Both tokens went into the position, leaving less than 1% of either behind
+        for denom in [UFOO, UBAR] {
+            let balance = t
+                .bank
+                .query_balance(&QueryBalanceRequest {
+                    address: alice.address(),
+                    denom: denom.to_string(),
+                })
+                .unwrap()
+                .balance
+                .unwrap();
+            assert!(Uint128::from_str(&balance.amount).unwrap() < Uint128::new(10_000));
+        }
+    }
+
+    #[test]
+    fn test_missing_authz_grant() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Alice only grants the swap
+        t.authz
+            .grant_generic_authz(
+                &alice,
+                t.single_sided_lp_cl.contract_addr.as_str(),
+                MsgSwapExactAmountIn::TYPE_URL,
+            )
+            .unwrap();
+
+        let err = t
+            .single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::SingleSidedSwapAndJoin {
+                    pool_id: 1,
+                    range: RangeSpec::Ticks {
+                        lower: -1000,
+                        upper: 1000,
+                    },
+                    token_provided: Coin::new(1_000_000, UFOO),
+                    token_min_amount0: Uint128::zero(),
+                    token_min_amount1: Uint128::zero(),
+                    max_spot_twap_deviation_bps: None,
+                    expected_sqrt_price: None,
+                    deadline: None,
+                    best_effort: None,
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains(&format!(
+            "Missing authz grant for {} to the contract",
+            MsgCreatePosition::TYPE_URL
+        )));
+    }
+
+    #[test]
+    fn test_spot_price_deviates_from_twap() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let pm = PoolManager::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+        let bob = app
+            .init_account(&[
+                Coin::new(10_000_000, UBAR),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Bob's swap moves the sqrt price of the full range liquidity from 1 to about 1.1, the TWAP over the
+        // window is still close to a price of 1
+        pm.swap_exact_amount_in(
+            MsgSwapExactAmountIn {
+                sender: bob.address(),
+                routes: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: UFOO.to_string(),
+                }],
+                token_in: Some(Coin::new(10_000_000, UBAR).into()),
+                token_out_min_amount: "1".to_string(),
+            },
+            &bob,
+        )
+        .unwrap();
+
+        // Have alice give the contract authz permissions
+        t.authz
+            .grant_required_authz_for_lp(&alice, t.single_sided_lp_cl.contract_addr.as_str())
+            .unwrap();
+
+        let join_msg = |max_spot_twap_deviation_bps| ExecuteMsg::SingleSidedSwapAndJoin {
+            pool_id: 1,
+            range: RangeSpec::Ticks {
+                lower: -1000,
+                upper: 300000,
+            },
+            token_provided: Coin::new(1_000_000, UFOO),
+            token_min_amount0: Uint128::zero(),
+            token_min_amount1: Uint128::zero(),
+            max_spot_twap_deviation_bps,
+            expected_sqrt_price: None,
+            deadline: None,
+            best_effort: None,
+        };
+
+        // The price moved by about 21%, more than the 5% of the config
+        let err = t
+            .single_sided_lp_cl
+            .execute(&join_msg(None), &[], &alice)
+            .unwrap_err();
+        assert!(err.to_string().contains("by more than 500 bps"), "{err}");
+        assert_eq!(
+            t.bank
+                .query_balance(&QueryBalanceRequest {
+                    address: alice.address(),
+                    denom: UFOO.to_string(),
+                })
+                .unwrap()
+                .balance
+                .unwrap()
+                .amount,
+            "1000000"
+        );
+
+        // A join that tolerates the deviation goes through
+        t.single_sided_lp_cl
+            .execute(&join_msg(Some(5000)), &[], &alice)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rebalance_out_of_range_position() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Create a position above the current price, it only holds UFOO and earns nothing
+        let position_id = cl
+            .create_position(
+                MsgCreatePosition {
+                    pool_id: 1,
+                    sender: alice.address(),
+                    lower_tick: 10000,
+                    upper_tick: 11000,
+                    tokens_provided: vec![Coin::new(1_000_000, UFOO).into()],
+                    token_min_amount0: "0".to_string(),
+                    token_min_amount1: "0".to_string(),
+                },
+                &alice,
+            )
+            .unwrap()
+            .data
+            .position_id;
+
+        // Have alice give the contract authz permissions
+        t.authz
+            .grant_required_authz_for_rebalance(&alice, t.single_sided_lp_cl.contract_addr.as_str())
+            .unwrap();
+
+        t.single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::Rebalance {
+                    position_id,
+                    new_range: None,
+                    max_slippage: Decimal::percent(1),
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap();
+
+        // The old position is gone and the new one has the same width around the current tick
+        let positions = cl
+            .query_user_positions(&UserPositionsRequest {
+                address: alice.address(),
+                pool_id: 1,
+                pagination: None,
+            })
+            .unwrap();
+        assert_eq!(positions.positions.len(), 1);
+        let position = positions.positions[0].position.clone().unwrap();
+        assert_ne!(position.position_id, position_id);
+        // 10 tick spacings, 4 below and 5 above the spacing of tick 0
+        assert_eq!(position.lower_tick, -400);
+        assert_eq!(position.upper_tick, 600);
+
+        // The withdrawn UFOO was split between both tokens of the new position, alice only keeps dust
+        assert!(query_balance(&t.bank, &alice, UFOO) < Uint128::new(100));
+        assert!(query_balance(&t.bank, &alice, UBAR) < Uint128::new(100));
+    }
+
+    #[test]
+    fn test_change_range() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(1_000_000, UBAR),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        let position_id = cl
+            .create_position(
+                MsgCreatePosition {
+                    pool_id: 1,
+                    sender: alice.address(),
+                    lower_tick: -1000,
+                    upper_tick: 1000,
+                    tokens_provided: vec![
+                        Coin::new(1_000_000, UFOO).into(),
+                        Coin::new(1_000_000, UBAR).into(),
+                    ],
+                    token_min_amount0: "0".to_string(),
+                    token_min_amount1: "0".to_string(),
+                },
+                &alice,
+            )
+            .unwrap()
+            .data
+            .position_id;
+
+        // Have alice give the contract authz permissions
+        t.authz
+            .grant_required_authz_for_change_range(
+                &alice,
+                t.single_sided_lp_cl.contract_addr.as_str(),
+            )
+            .unwrap();
+
+        // The range around the price only took part of alice's UBAR
+        let ubar_before = query_balance(&t.bank, &alice, UBAR);
+
+        // Move the position to a range below the current price, it only holds UBAR afterwards
+        t.single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::ChangeRange {
+                    position_id,
+                    new_lower_tick: -3000,
+                    new_upper_tick: -1000,
+                    max_slippage: Decimal::percent(1),
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap();
+
+        let positions = cl
+            .query_user_positions(&UserPositionsRequest {
+                address: alice.address(),
+                pool_id: 1,
+                pagination: None,
+            })
+            .unwrap();
+        assert_eq!(positions.positions.len(), 1);
+        let position = positions.positions[0].position.clone().unwrap();
+        assert_ne!(position.position_id, position_id);
+        assert_eq!(position.lower_tick, -3000);
+        assert_eq!(position.upper_tick, -1000);
+
+        // All of the withdrawn UFOO was swapped and the UBAR went into the range below the price, alice's wallet
+        // only gained dust
+        assert!(query_balance(&t.bank, &alice, UFOO) < Uint128::new(100));
+        assert!(query_balance(&t.bank, &alice, UBAR) < ubar_before + Uint128::new(100));
+    }
+
+    #[test]
+    fn test_migrate_from_gamm() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+        let gamm = Gamm::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(2_000_000, UBAR),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // Alice holds all shares of a GAMM pool of the same pair, at a different price than the CL pool
+        let gamm_pool_id = gamm
+            .create_basic_pool(
+                &[Coin::new(1_000_000, UFOO), Coin::new(2_000_000, UBAR)],
+                &alice,
+            )
+            .unwrap()
+            .data
+            .pool_id;
+        let shares = query_balance(&t.bank, &alice, &format!("gamm/pool/{gamm_pool_id}"));
+
+        // Have alice give the contract authz permissions
+        t.authz
+            .grant_required_authz_for_migrate_from_gamm(
+                &alice,
+                t.single_sided_lp_cl.contract_addr.as_str(),
+            )
+            .unwrap();
+
+        t.single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::MigrateFromGamm {
+                    gamm_pool_id,
+                    // The chain doesn't let the last shares of a pool exit, half of them are migrated
+                    shares: shares / Uint128::new(2),
+                    cl_pool_id: 1,
+                    range: RangeSpec::Ticks {
+                        lower: -1000,
+                        upper: 1000,
+                    },
+                    max_slippage: Decimal::percent(1),
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap();
+
+        let positions = cl
+            .query_user_positions(&UserPositionsRequest {
+                address: alice.address(),
+                pool_id: 1,
+                pagination: None,
+            })
+            .unwrap();
+        assert_eq!(positions.positions.len(), 1);
+        let position = positions.positions[0].position.clone().unwrap();
+        assert_eq!(position.lower_tick, -1000);
+        assert_eq!(position.upper_tick, 1000);
+
+        // Both exited tokens went into the position, alice only keeps dust of them and the other half of the shares
+        assert!(query_balance(&t.bank, &alice, UFOO) < Uint128::new(100));
+        assert!(query_balance(&t.bank, &alice, UBAR) < Uint128::new(100));
+        assert_eq!(
+            query_balance(&t.bank, &alice, &format!("gamm/pool/{gamm_pool_id}")),
+            shares - shares / Uint128::new(2)
+        );
+    }
+
+    #[test]
+    fn test_compound_spread_rewards() {
+        let app = OsmosisTestApp::new();
+        let t = TestEnv::setup(&app);
+        let cl = ConcentratedLiquidity::new(&app);
+        let pm = PoolManager::new(&app);
+
+        let alice = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(1_000_000, UBAR),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+        let bob = app
+            .init_account(&[
+                Coin::new(1_000_000, UFOO),
+                Coin::new(1_000_000_000_000, "uosmo"),
+            ])
+            .unwrap();
+
+        // A narrow position around the current price earns most of the spread rewards
+        let position_id = cl
+            .create_position(
+                MsgCreatePosition {
+                    pool_id: 1,
+                    sender: alice.address(),
+                    lower_tick: -1000,
+                    upper_tick: 1000,
+                    tokens_provided: vec![
+                        Coin::new(1_000_000, UFOO).into(),
+                        Coin::new(1_000_000, UBAR).into(),
+                    ],
+                    token_min_amount0: "0".to_string(),
+                    token_min_amount1: "0".to_string(),
+                },
+                &alice,
+            )
+            .unwrap()
+            .data
+            .position_id;
+
+        // Bob's swap pays spread rewards to the position
+        pm.swap_exact_amount_in(
+            MsgSwapExactAmountIn {
+                sender: bob.address(),
+                routes: vec![SwapAmountInRoute {
+                    pool_id: 1,
+                    token_out_denom: UBAR.to_string(),
+                }],
+                token_in: Some(Coin::new(500_000, UFOO).into()),
+                token_out_min_amount: "1".to_string(),
+            },
+            &bob,
+        )
+        .unwrap();
+
+        // Have alice give the contract authz permissions
+        t.authz
+            .grant_required_authz_for_compound(&alice, t.single_sided_lp_cl.contract_addr.as_str())
+            .unwrap();
+
+        let ufoo_before = query_balance(&t.bank, &alice, UFOO);
+        let ubar_before = query_balance(&t.bank, &alice, UBAR);
+
+        t.single_sided_lp_cl
+            .execute(
+                &ExecuteMsg::Compound {
+                    position_id,
+                    max_slippage: Decimal::percent(1),
+                },
+                &[], // nil for the funds parameter
+                &alice,
+            )
+            .unwrap();
+
+        // Adding to a position replaces it with a new one in the same range
+        let positions = cl
+            .query_user_positions(&UserPositionsRequest {
+                address: alice.address(),
+                pool_id: 1,
+                pagination: None,
+            })
+            .unwrap();
+        assert_eq!(positions.positions.len(), 1);
+        let position = positions.positions[0].position.clone().unwrap();
+        assert_eq!(position.lower_tick, -1000);
+        assert_eq!(position.upper_tick, 1000);
+
+        // The about 47 UFOO of spread rewards were added to the position instead of being paid out to alice,
+        // her wallet only gained rounding dust
+        assert!(query_balance(&t.bank, &alice, UFOO) <= ufoo_before + Uint128::new(5));
+        assert!(query_balance(&t.bank, &alice, UBAR) <= ubar_before + Uint128::new(5));
+    }
 }
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
-use cosmwasm_std::Uint128;
-use cosmwasm_std::{Decimal, Decimal256, Storage};
+use cosmwasm_std::{Decimal, Decimal256, Isqrt, Storage};
+use cosmwasm_std::{Uint128, Uint256, Uint512};
 
 use crate::state::{TickExpIndexData, TICK_EXP_CACHE};
 use crate::ContractError;
@@ -127,6 +127,75 @@ pub fn price_to_tick(storage: &mut dyn Storage, price: Decimal256) -> Result<i12
     Ok(tick_index)
 }
 
+pub fn tick_to_sqrt_price(tick_index: i64) -> Result<Decimal256, ContractError> {
+    let price = tick_to_price(tick_index)?;
+    monotonic_sqrt(price)
+}
+
+// calculate_sqrt_price_to_tick mirrors CalculateSqrtPriceToTick from the chain's cl math package.
+// Since squaring the sqrt price may carry rounding errors, the tick derived from the price is only a
+// candidate and is moved within a +/- 1 tick range based on the sqrt prices those ticks would imply.
+pub fn calculate_sqrt_price_to_tick(
+    storage: &mut dyn Storage,
+    sqrt_price: Decimal256,
+) -> Result<i64, ContractError> {
+    let price = sqrt_price.checked_mul(sqrt_price)?;
+    let mut tick =
+        i64::try_from(price_to_tick(storage, price)?).map_err(|_| ContractError::Overflow {})?;
+
+    // If the candidate is the min tick, we set it to min tick + 1, and if it is at or above
+    // max tick - 1, we set it to max tick - 2 so that the neighbour checks below never leave
+    // the valid tick range. We check at max tick - 1 since the candidate may carry some error
+    // that pushes it over the boundary.
+    let mut out_of_bounds = false;
+    if tick <= MIN_INITIALIZED_TICK {
+        tick = MIN_INITIALIZED_TICK + 1;
+        out_of_bounds = true;
+    } else if tick >= MAX_TICK as i64 - 1 {
+        tick = MAX_TICK as i64 - 2;
+        out_of_bounds = true;
+    }
+
+    let sqrt_price_t_min_1 = tick_to_sqrt_price(tick - 1)?;
+    let sqrt_price_t = tick_to_sqrt_price(tick)?;
+    let sqrt_price_t_plus_1 = tick_to_sqrt_price(tick + 1)?;
+    let sqrt_price_t_plus_2 = tick_to_sqrt_price(tick + 2)?;
+
+    // The upper end is exclusive unless the candidate was shifted off a limit, in which case it is
+    // inclusive, since otherwise the min/max tick would be impossible to reach by construction.
+    if (!out_of_bounds && sqrt_price >= sqrt_price_t_plus_2)
+        || (out_of_bounds && sqrt_price > sqrt_price_t_plus_2)
+        || sqrt_price < sqrt_price_t_min_1
+    {
+        return Err(ContractError::SqrtPriceToTickError { sqrt_price, tick });
+    }
+
+    // We expect this case to only be hit when the provided sqrt price is exactly the max sqrt price
+    if sqrt_price == sqrt_price_t_plus_2 {
+        return Ok(tick + 2);
+    }
+
+    // The remaining cases shift the tick index by +/- 1
+    if sqrt_price >= sqrt_price_t_plus_1 {
+        return Ok(tick + 1);
+    }
+    if sqrt_price >= sqrt_price_t {
+        return Ok(tick);
+    }
+    Ok(tick - 1)
+}
+
+// monotonic_sqrt mirrors osmomath's MonotonicSqrt: the root is rounded up at the last decimal place
+// so that the returned root r always satisfies r^2 >= value, which keeps the function monotonic.
+fn monotonic_sqrt(value: Decimal256) -> Result<Decimal256, ContractError> {
+    let scaled = Uint512::from(value.atomics()).checked_mul(Decimal256::one().atomics().into())?;
+    let mut root = scaled.isqrt();
+    if root.checked_mul(root)? < scaled {
+        root = root.checked_add(Uint512::one())?;
+    }
+    Ok(Decimal256::new(Uint256::try_from(root)?))
+}
+
 fn build_tick_exp_cache(storage: &mut dyn Storage) -> Result<(), ContractError> {
     // Build positive indices
     let mut max_price = Decimal256::one();
@@ -450,4 +519,107 @@ mod tests {
         price = Decimal256::from_str(MIN_SPOT_PRICE).unwrap() / Decimal256::from_str("10").unwrap();
         assert!(price_to_tick(deps.as_mut().storage, price).is_err());
     }
+
+    // Sqrt prices the chain's TickToSqrtPrice returns for ticks spread across the whole
+    // MIN_INITIALIZED_TICK..MAX_TICK range, including the geometric exponent boundaries.
+    const TICK_SQRT_PRICE_VECTORS: &[(i64, &str)] = &[
+        (-108000000, "0.000001"),
+        (-107999999, "0.0000010000005"),
+        (-107999899, "0.000001000050498725"),
+        (-99000001, "0.000003162277502055"),
+        (-99000000, "0.000003162277660169"),
+        (-98999999, "0.000003162279241307"),
+        (-90000000, "0.00001"),
+        (-81000001, "0.000031622775020545"),
+        (-72000000, "0.0001"),
+        (-63000000, "0.000316227766016838"),
+        (-54000000, "0.001"),
+        (-45000000, "0.00316227766016838"),
+        (-44821000, "0.00343365694267788"),
+        (-44820900, "0.003433802556933057"),
+        (-36000000, "0.01"),
+        (-29204000, "0.027921318020466011"),
+        (-29203900, "0.027921497094532736"),
+        (-27000000, "0.031622776601683794"),
+        (-20594000, "0.086058119895800652"),
+        (-20593900, "0.086058700896539218"),
+        (-18000000, "0.1"),
+        (-12150000, "0.261725046566048012"),
+        (-12149900, "0.261726956960875546"),
+        (-12104000, "0.262602360994717639"),
+        (-12103900, "0.262604265007253834"),
+        (-9000001, "0.316227750205449238"),
+        (-9000000, "0.316227766016837934"),
+        (-8999999, "0.316227924130681414"),
+        (-1000000, "0.9486832980505138"),
+        (-200, "0.9999899999499995"),
+        (-101, "0.999994949987248686"),
+        (-100, "0.999994999987499938"),
+        (-1, "0.99999994999999875"),
+        (0, "1"),
+        (1, "1.000000499999875001"),
+        (100, "1.000049998750062497"),
+        (101, "1.00005049872493939"),
+        (200, "1.000099995000499938"),
+        (1000000, "1.414213562373095049"),
+        (8999999, "3.162277502054492371"),
+        (9000000, "3.162277660168379332"),
+        (9000001, "3.162279241306814132"),
+        (18000000, "10"),
+        (27000000, "31.62277660168379332"),
+        (38035200, "174.218253923060542193"),
+        (38035300, "174.221123862750925603"),
+        (45000000, "316.2277660168379332"),
+        (64576000, "5075.431016179808152078"),
+        (64576100, "5075.529529024533219399"),
+        (72000000, "10000"),
+        (99000000, "316227.76601683793319989"),
+        (135000000, "31622776.601683793319988936"),
+        (180000000, "10000000000"),
+        (225000000, "3162277660168.379331998893544433"),
+        (270000000, "1000000000000000"),
+        (315000000, "316227766016837933.199889354443271854"),
+        (341999900, "9999949999874999374.996093722656044921"),
+        (341999998, "9999998999999949999.994999999374999913"),
+        (341999999, "9999999499999987499.999374999960937498"),
+        (342000000, "10000000000000000000"),
+    ];
+
+    #[test]
+    fn test_tick_to_sqrt_price() {
+        for (tick_index, expected_sqrt_price) in TICK_SQRT_PRICE_VECTORS {
+            let expected_sqrt_price = Decimal256::from_str(expected_sqrt_price).unwrap();
+            let sqrt_price = tick_to_sqrt_price(*tick_index).unwrap();
+            assert_eq!(sqrt_price, expected_sqrt_price, "tick {tick_index}");
+        }
+
+        assert!(tick_to_sqrt_price(MAX_TICK as i64 + 1).is_err());
+        assert!(tick_to_sqrt_price(MIN_INITIALIZED_TICK - 1).is_err());
+    }
+
+    #[test]
+    fn test_calculate_sqrt_price_to_tick() {
+        let mut deps = mock_dependencies();
+        for (tick_index, sqrt_price) in TICK_SQRT_PRICE_VECTORS {
+            let sqrt_price = Decimal256::from_str(sqrt_price).unwrap();
+
+            // the exact sqrt price of a tick maps back to that tick
+            let tick = calculate_sqrt_price_to_tick(deps.as_mut().storage, sqrt_price).unwrap();
+            assert_eq!(tick, *tick_index, "sqrt price {sqrt_price}");
+
+            // one unit below the tick's sqrt price falls into the previous tick
+            if *tick_index > MIN_INITIALIZED_TICK {
+                let tick = calculate_sqrt_price_to_tick(
+                    deps.as_mut().storage,
+                    sqrt_price - Decimal256::raw(1),
+                )
+                .unwrap();
+                assert_eq!(tick, tick_index - 1, "sqrt price {sqrt_price} - 1ulp");
+            }
+        }
+
+        // out of bounds
+        let sqrt_price = Decimal256::from_str("0.000000999999999999").unwrap();
+        assert!(calculate_sqrt_price_to_tick(deps.as_mut().storage, sqrt_price).is_err());
+    }
 }